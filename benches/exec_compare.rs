@@ -98,6 +98,9 @@ fn main() {
         ("igcc", "emlop",  &["-F","{emerge.log}","s","gcc","-e"], None),
         // Show overall stats
         ("st", "emlop",  &["-F","{emerge.log}","s","-sa"], None),
+        ("stn", "emlop",  &["-F","{emerge.log}","s","-sa","--no-predict"], None),
+        ("stgd", "emlop",  &["-F","{emerge.log}","s","-sa","-gd"], None),
+        ("stgdn", "emlop",  &["-F","{emerge.log}","s","-sa","-gd","--no-predict"], None),
     ];
 
     // CLI definition
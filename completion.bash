@@ -27,7 +27,7 @@ _emlop() {
 
     case "${cmd}" in
         emlop)
-            opts="log predict stats accuracy -f -t -H -o -F -v -h -V --from --to --header --showskip --duration --date --utc --color --output --logfile --help --version"
+            opts="log predict stats accuracy -f -t -H -o -F -v -q -h -V --from --since-sync --between-syncs --to --to-exclusive --header --showskip --skip-fmt --duration --duration-parts --date --utc --tz --color --color-category --theme-preset --pager --progress --output --out --logfile --run-regex --staleness-check --quiet --dump-config --help --help-all --version"
             if [[ ${cur} == -* ]] ; then
                 COMPREPLY=( $(compgen -W "${opts}" -- "${cur}") )
                 return 0
@@ -36,23 +36,41 @@ _emlop() {
                 --from|--to|-f|-t)
                     COMPREPLY=($(compgen -W "1h 1d 1w 1m 1h $(date -Is)" "${cur}"))
                     ;;
-                --header|-H|--showskip)
+                --header|-H)
+                    COMPREPLY=($(compgen -W "yes no repeat" "${cur}"))
+                    ;;
+                --showskip)
                     COMPREPLY=($(compgen -W "yes no" "${cur}"))
                     ;;
                 --duration)
-                    COMPREPLY=($(compgen -W "hms secs hmsfixed human" "${cur}"))
+                    COMPREPLY=($(compgen -W "hms secs hmsfixed human iso hours" "${cur}"))
                     ;;
                 --date)
                     COMPREPLY=($(compgen -W "ymd ymdhms ymdhmso rfc3339 rfc2822 compact unix" "${cur}"))
                     ;;
-                --utc)
+                --utc|--staleness-check|--progress)
                     COMPREPLY=($(compgen -W "yes no" "${cur}"))
                     ;;
+                --tz)
+                    COMPREPLY=($(compgen -W "local utc +00:00" "${cur}"))
+                    ;;
                 --color)
                     COMPREPLY=($(compgen -W "yes no auto" "${cur}"))
                     ;;
+                --color-category)
+                    COMPREPLY=($(compgen -W "yes no" "${cur}"))
+                    ;;
+                --theme-preset)
+                    COMPREPLY=($(compgen -W "dark light none" "${cur}"))
+                    ;;
+                --pager)
+                    COMPREPLY=($(compgen -W "always never auto" "${cur}"))
+                    ;;
                 --output|-o)
-                    COMPREPLY=($(compgen -W "tab columns auto" "${cur}"))
+                    COMPREPLY=($(compgen -W "tab columns tsv auto" "${cur}"))
+                    ;;
+                --out)
+                    COMPREPLY=($(compgen -f "${cur}"))
                     ;;
                 --logfile|-F)
                     COMPREPLY=($(compgen -f "${cur}"))
@@ -64,7 +82,7 @@ _emlop() {
             return 0
             ;;
         emlop__accuracy)
-            opts="[search]... -e -s -n -f -t -H -o -F -v -h --exact --show --last --avg --limit --from --to --header --showskip --duration --date --utc --color --output --logfile --help"
+            opts="[search]... -e -s -n -f -t -H -o -F -v -q -h --exact --glob --invert --show --last --avg --limit --from --since-sync --between-syncs --to --to-exclusive --header --showskip --skip-fmt --duration --duration-parts --date --utc --tz --color --color-category --theme-preset --pager --progress --output --out --logfile --run-regex --staleness-check --quiet --dump-config --help"
             if [[ ${cur} == -* ]] ; then
                 COMPREPLY=( $(compgen -W "${opts}" -- "${cur}") )
                 return 0
@@ -73,23 +91,41 @@ _emlop() {
                 --from|--to|-f|-t)
                     COMPREPLY=($(compgen -W "1h 1d 1w 1m 1h $(date -Is)" "${cur}"))
                     ;;
-                --header|-H|--showskip)
+                --header|-H)
+                    COMPREPLY=($(compgen -W "yes no repeat" "${cur}"))
+                    ;;
+                --showskip)
                     COMPREPLY=($(compgen -W "yes no" "${cur}"))
                     ;;
                 --duration)
-                    COMPREPLY=($(compgen -W "hms secs hmsfixed human" "${cur}"))
+                    COMPREPLY=($(compgen -W "hms secs hmsfixed human iso hours" "${cur}"))
                     ;;
                 --date)
                     COMPREPLY=($(compgen -W "ymd ymdhms ymdhmso rfc3339 rfc2822 compact unix" "${cur}"))
                     ;;
-                --utc)
+                --utc|--staleness-check|--progress)
                     COMPREPLY=($(compgen -W "yes no" "${cur}"))
                     ;;
+                --tz)
+                    COMPREPLY=($(compgen -W "local utc +00:00" "${cur}"))
+                    ;;
                 --color)
                     COMPREPLY=($(compgen -W "yes no auto" "${cur}"))
                     ;;
+                --color-category)
+                    COMPREPLY=($(compgen -W "yes no" "${cur}"))
+                    ;;
+                --theme-preset)
+                    COMPREPLY=($(compgen -W "dark light none" "${cur}"))
+                    ;;
+                --pager)
+                    COMPREPLY=($(compgen -W "always never auto" "${cur}"))
+                    ;;
                 --output|-o)
-                    COMPREPLY=($(compgen -W "tab columns auto" "${cur}"))
+                    COMPREPLY=($(compgen -W "tab columns tsv auto" "${cur}"))
+                    ;;
+                --out)
+                    COMPREPLY=($(compgen -f "${cur}"))
                     ;;
                 --logfile|-F)
                     COMPREPLY=($(compgen -f "${cur}"))
@@ -101,7 +137,7 @@ _emlop() {
                     COMPREPLY=($(compgen -W "1 5 10 20 100" "${cur}"))
                     ;;
                 --avg)
-                    COMPREPLY=($(compgen -W "arith median weighted-arith weighted-median" "${cur}"))
+                    COMPREPLY=($(compgen -W "arith median weighted-arith weighted-median p90 p95" "${cur}"))
                     ;;
                 --limit)
                     COMPREPLY=($(compgen -W "1 5 20 999" "${cur}"))
@@ -117,7 +153,7 @@ _emlop() {
             return 0
             ;;
         emlop__log)
-            opts=" [search]... -N -n -s -e -f -t -H -o -F -v -h --starttime --first --last --show --exact --from --to --header --showskip --duration --date --utc --color --output --logfile --help"
+            opts=" [search]... -N -n -s -e -f -t -H -o -F -v -q -h --starttime --mark-downgrades --coalesce-updates --target-root --line-buffered --json-lines --sort --first --last --tail --every --since-version --skip --take --no-reinstalls --installed-only --binary --show --exact --glob --invert --repo --pkg-file --from --since-sync --between-syncs --to --to-exclusive --header --showskip --skip-fmt --duration --duration-parts --date --utc --tz --color --color-category --theme-preset --pager --progress --output --out --logfile --run-regex --staleness-check --quiet --dump-config --help"
             if [[ ${cur} == -* ]] ; then
                 COMPREPLY=( $(compgen -W "${opts}" -- "${cur}") )
                 return 0
@@ -126,28 +162,52 @@ _emlop() {
                 --from|--to|-f|-t)
                     COMPREPLY=($(compgen -W "1h 1d 1w 1m 1h $(date -Is)" "${cur}"))
                     ;;
-                --header|-H|--showskip)
+                --header|-H)
+                    COMPREPLY=($(compgen -W "yes no repeat" "${cur}"))
+                    ;;
+                --showskip)
                     COMPREPLY=($(compgen -W "yes no" "${cur}"))
                     ;;
                 --duration)
-                    COMPREPLY=($(compgen -W "hms secs hmsfixed human" "${cur}"))
+                    COMPREPLY=($(compgen -W "hms secs hmsfixed human iso hours" "${cur}"))
                     ;;
                 --date)
                     COMPREPLY=($(compgen -W "ymd ymdhms ymdhmso rfc3339 rfc2822 compact unix" "${cur}"))
                     ;;
-                --utc)
+                --utc|--staleness-check|--progress)
                     COMPREPLY=($(compgen -W "yes no" "${cur}"))
                     ;;
+                --tz)
+                    COMPREPLY=($(compgen -W "local utc +00:00" "${cur}"))
+                    ;;
                 --color)
                     COMPREPLY=($(compgen -W "yes no auto" "${cur}"))
                     ;;
+                --color-category)
+                    COMPREPLY=($(compgen -W "yes no" "${cur}"))
+                    ;;
+                --binary)
+                    COMPREPLY=($(compgen -W "both only exclude" "${cur}"))
+                    ;;
+                --sort)
+                    COMPREPLY=($(compgen -W "date duration" "${cur}"))
+                    ;;
+                --theme-preset)
+                    COMPREPLY=($(compgen -W "dark light none" "${cur}"))
+                    ;;
+                --pager)
+                    COMPREPLY=($(compgen -W "always never auto" "${cur}"))
+                    ;;
                 --output|-o)
-                    COMPREPLY=($(compgen -W "tab columns auto" "${cur}"))
+                    COMPREPLY=($(compgen -W "tab columns tsv auto" "${cur}"))
                     ;;
-                --logfile|-F)
+                --out)
                     COMPREPLY=($(compgen -f "${cur}"))
                     ;;
-                --starttime)
+                --logfile|-F|--pkg-file)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    ;;
+                --starttime|--mark-downgrades|--coalesce-updates|--target-root)
                     COMPREPLY=($(compgen -W "yes no" "${cur}"))
                     ;;
                 --first|-N|--last|-n)
@@ -167,30 +227,45 @@ _emlop() {
             return 0
             ;;
         emlop__predict)
-            opts="-s -N -n -f -t -H -o -F -v -h --show --first --last --tmpdir --resume --unknown --avg --limit --from --to --header --showskip --duration --date --utc --color --output --pdepth --pwidth --logfile --help"
+            opts="-s -N -n -f -t -H -o -F -v -q -h --show --no-total --first --last --tmpdir --pretend-file --plan-json --atoms --pretend-since --pred-from --pred-to --resume --resume-backup --unknown --avg --limit --min-samples --scale --from --since-sync --between-syncs --to --to-exclusive --header --showskip --skip-fmt --duration --duration-parts --date --utc --tz --color --color-category --theme-preset --pager --progress --output --out --finish-times --status-code --pdepth --pwidth --logfile --run-regex --staleness-check --quiet --dump-config --help"
             if [[ ${cur} == -* ]] ; then
                 COMPREPLY=( $(compgen -W "${opts}" -- "${cur}") )
                 return 0
             fi
             case "${prev}" in
-                --from|--to|-f|-t)
+                --from|--to|-f|-t|--pretend-since|--pred-from|--pred-to)
                     COMPREPLY=($(compgen -W "1h 1d 1w 1m 1h $(date -Is)" "${cur}"))
                     ;;
-                --header|-H|--showskip)
+                --header|-H)
+                    COMPREPLY=($(compgen -W "yes no repeat" "${cur}"))
+                    ;;
+                --showskip|--finish-times|--resume-backup)
                     COMPREPLY=($(compgen -W "yes no" "${cur}"))
                     ;;
                 --duration)
-                    COMPREPLY=($(compgen -W "hms secs hmsfixed human" "${cur}"))
+                    COMPREPLY=($(compgen -W "hms secs hmsfixed human iso hours" "${cur}"))
                     ;;
                 --date)
                     COMPREPLY=($(compgen -W "ymd ymdhms ymdhmso rfc3339 rfc2822 compact unix" "${cur}"))
                     ;;
-                --utc)
+                --utc|--staleness-check|--progress)
                     COMPREPLY=($(compgen -W "yes no" "${cur}"))
                     ;;
+                --tz)
+                    COMPREPLY=($(compgen -W "local utc +00:00" "${cur}"))
+                    ;;
                 --color)
                     COMPREPLY=($(compgen -W "yes no auto" "${cur}"))
                     ;;
+                --color-category)
+                    COMPREPLY=($(compgen -W "yes no" "${cur}"))
+                    ;;
+                --theme-preset)
+                    COMPREPLY=($(compgen -W "dark light none" "${cur}"))
+                    ;;
+                --pager)
+                    COMPREPLY=($(compgen -W "always never auto" "${cur}"))
+                    ;;
                 --pwidth)
                     COMPREPLY=($(compgen -W "10 20 40 80 160" "${cur}"))
                     ;;
@@ -198,13 +273,16 @@ _emlop() {
                     COMPREPLY=($(compgen -W "0 1 3 5 7 99" "${cur}"))
                     ;;
                 --output|-o)
-                    COMPREPLY=($(compgen -W "tab columns auto" "${cur}"))
+                    COMPREPLY=($(compgen -W "tab columns tsv auto" "${cur}"))
+                    ;;
+                --out)
+                    COMPREPLY=($(compgen -f "${cur}"))
                     ;;
                 --logfile|-F)
                     COMPREPLY=($(compgen -f "${cur}"))
                     ;;
                 --show|-s)
-                    COMPREPLY=($(compgen -W "emta" "${cur}"))
+                    COMPREPLY=($(compgen -W "rmsta" "${cur}"))
                     ;;
                 --first|-N|--last|-n)
                     COMPREPLY=($(compgen -W "1 5 10 20 100" "${cur}"))
@@ -212,18 +290,27 @@ _emlop() {
                 --tmpdir)
                     COMPREPLY=($(compgen -d "${cur}"))
                     ;;
+                --pretend-file|--plan-json)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    ;;
                 --resume)
                     COMPREPLY=($(compgen -W "auto either main backup no" -- "${cur}"))
                     ;;
                 --unknown)
                     COMPREPLY=($(compgen -W "0 5 10 20 60" "${cur}"))
                     ;;
+                --scale)
+                    COMPREPLY=($(compgen -W "0.5 0.7 1 1.5 2" "${cur}"))
+                    ;;
                 --avg)
-                    COMPREPLY=($(compgen -W "arith median weighted-arith weighted-median" "${cur}"))
+                    COMPREPLY=($(compgen -W "arith median weighted-arith weighted-median p90 p95" "${cur}"))
                     ;;
                 --limit)
                     COMPREPLY=($(compgen -W "1 5 20 999" "${cur}"))
                     ;;
+                --min-samples)
+                    COMPREPLY=($(compgen -W "0 2 3 5" "${cur}"))
+                    ;;
                 *)
                     COMPREPLY=($(compgen -W "${opts}" -- "${cur}"))
                     ;;
@@ -231,7 +318,7 @@ _emlop() {
             return 0
             ;;
         emlop__stats)
-            opts="[search]... -s -g -e -f -t -H -o -F -v -h --show --groupby --exact --avg --limit --from --to --header --showskip --duration --date --utc --color --output --logfile --help"
+            opts="[search]... -s -g -e -f -t -H -o -F -v -q -h --show --summary-only --group-regex --by-version --histogram --clock --overview --count --active-days --compare --csv-series --when --top -N -n --first --last --no-predict --live --groupby --relative-groups --exact --glob --invert --repo --pkg-file --preserved-rebuild --no-reinstalls --installed-only --binary --avg --limit --min-samples --from --since-sync --between-syncs --to --to-exclusive --header --showskip --skip-fmt --duration --duration-parts --date --utc --tz --color --color-category --theme-preset --pager --progress --output --out --logfile --run-regex --staleness-check --quiet --dump-config --help"
             if [[ ${cur} == -* ]] ; then
                 COMPREPLY=( $(compgen -W "${opts}" -- "${cur}") )
                 return 0
@@ -240,25 +327,49 @@ _emlop() {
                 --from|--to|-f|-t)
                     COMPREPLY=($(compgen -W "1h 1d 1w 1m 1h $(date -Is)" "${cur}"))
                     ;;
-                --header|-H|--showskip)
+                --header|-H)
+                    COMPREPLY=($(compgen -W "yes no repeat" "${cur}"))
+                    ;;
+                --first|-N|--last|-n)
+                    COMPREPLY=($(compgen -W "1 5 10 20 100" "${cur}"))
+                    ;;
+                --showskip)
                     COMPREPLY=($(compgen -W "yes no" "${cur}"))
                     ;;
                 --duration)
-                    COMPREPLY=($(compgen -W "hms secs hmsfixed human" "${cur}"))
+                    COMPREPLY=($(compgen -W "hms secs hmsfixed human iso hours" "${cur}"))
                     ;;
                 --date)
                     COMPREPLY=($(compgen -W "ymd ymdhms ymdhmso rfc3339 rfc2822 compact unix" "${cur}"))
                     ;;
-                --utc)
+                --utc|--staleness-check|--progress)
                     COMPREPLY=($(compgen -W "yes no" "${cur}"))
                     ;;
+                --tz)
+                    COMPREPLY=($(compgen -W "local utc +00:00" "${cur}"))
+                    ;;
                 --color)
                     COMPREPLY=($(compgen -W "yes no auto" "${cur}"))
                     ;;
+                --color-category)
+                    COMPREPLY=($(compgen -W "yes no" "${cur}"))
+                    ;;
+                --binary)
+                    COMPREPLY=($(compgen -W "both only exclude" "${cur}"))
+                    ;;
+                --theme-preset)
+                    COMPREPLY=($(compgen -W "dark light none" "${cur}"))
+                    ;;
+                --pager)
+                    COMPREPLY=($(compgen -W "always never auto" "${cur}"))
+                    ;;
                 --output|-o)
-                    COMPREPLY=($(compgen -W "tab columns auto" "${cur}"))
+                    COMPREPLY=($(compgen -W "tab columns tsv prometheus auto" "${cur}"))
                     ;;
-                --logfile|-F)
+                --out)
+                    COMPREPLY=($(compgen -f "${cur}"))
+                    ;;
+                --logfile|-F|--pkg-file)
                     COMPREPLY=($(compgen -f "${cur}"))
                     ;;
                 --show|-s)
@@ -267,12 +378,24 @@ _emlop() {
                 --groupby|-g)
                     COMPREPLY=($(compgen -W "year month week day none" "${cur}"))
                     ;;
+                --when)
+                    COMPREPLY=($(compgen -W "first last" "${cur}"))
+                    ;;
+                --relative-groups)
+                    COMPREPLY=($(compgen -W "yes no" "${cur}"))
+                    ;;
+                --group-regex)
+                    COMPREPLY=($(compgen -W "'^([a-z]+)-' '^([a-z0-9-]+)/'" -- "${cur}"))
+                    ;;
                 --avg)
-                    COMPREPLY=($(compgen -W "arith median weighted-arith weighted-median" "${cur}"))
+                    COMPREPLY=($(compgen -W "arith median weighted-arith weighted-median p90 p95" "${cur}"))
                     ;;
                 --limit)
                     COMPREPLY=($(compgen -W "1 5 20 999" "${cur}"))
                     ;;
+                --min-samples)
+                    COMPREPLY=($(compgen -W "0 2 3 5" "${cur}"))
+                    ;;
                 *)
                     if [[ -z "${cur}" ]]; then
                         COMPREPLY=($(compgen -W "${opts}" -- "${cur}"))
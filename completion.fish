@@ -1,19 +1,35 @@
 complete -c emlop -e
 complete -c emlop -f
 complete -c emlop -s f -l from -d 'Only parse log entries after <date>' -x -a "{1y	'One year ago',1m	'One month ago',1w	'One week ago',1d	'One day ago',1h	'One hour ago',(date -Is)	'Exact date'}"
+complete -c emlop -l since-sync -d 'Only parse log entries after the last sync'
+complete -c emlop -l between-syncs -d 'Only parse log entries between the last two syncs'
 complete -c emlop -s t -l to -d 'Only parse log entries before <date>' -x -a "{1y	'One year ago',1m	'One month ago',1w	'One week ago',1d	'One day ago',1h	'One hour ago',(date -Is)	'Exact date'}"
-complete -c emlop -s H -l header -d 'Show table header' -f -a "yes no"
+complete -c emlop -l to-exclusive -d 'Exclude entries at exactly the --to date'
+complete -c emlop -s H -l header -d 'Show table header' -f -a "yes no repeat"
 complete -c emlop -l showskip -d 'Show skipped rows' -f -a "yes no"
-complete -c emlop -l duration -d 'Output durations in different formats' -x -a "hms hmsfixed human secs"
+complete -c emlop -l skip-fmt -d 'Template of the placeholder for skipped rows' -x
+complete -c emlop -l duration -d 'Output durations in different formats' -x -a "hms hmsfixed human secs iso hours"
+complete -c emlop -l duration-parts -d 'Show at most <num> parts of `human` durations' -x -a "1 2 3 4"
 complete -c emlop -l date -d 'Output dates in different formats' -x -a "ymd ymdhms ymdhmso rfc3339 rfc2822 compact unix"
 complete -c emlop -l utc -d 'Parse/display dates in UTC instead of local time' -f -a "yes no"
+complete -c emlop -l tz -d 'Timezone to parse/display dates with' -x -a "local utc"
 complete -c emlop -l color -d 'Enable color (yes/no/auto)' -f -a "{yes	Enabled,no	Disabled,auto	'Enabled on terminal'}"
-complete -c emlop -s o -l output -d 'Ouput format' -x -a "columns tab auto"
+complete -c emlop -l color-category -d 'Color package categories by name (yes/no)' -f -a "yes no"
+complete -c emlop -l theme-preset -d 'Color theme' -x -a "{dark	'For dark terminals',light	'For light terminals',none	'No colors'}"
+complete -c emlop -l pager -d 'Page output through $PAGER' -f -a "{always	Enabled,never	Disabled,auto	'Enabled on terminal'}"
+complete -c emlop -l progress -d 'Show the number of parsed lines while reading the log' -f -a "yes no"
+complete -c emlop -s o -l output -d 'Ouput format' -x -a "columns tab tsv auto"
+complete -c emlop -l out -d 'Write output to file' -r -F
 complete -c emlop -s F -l logfile -d 'Location of emerge log file' -r -F
+complete -c emlop -l run-regex -d 'Also treat lines matching <regex> as emerge command starts' -x
+complete -c emlop -l staleness-check -d 'Warn if the newest log entry is more than 30 days old' -f -a "yes no"
 complete -c emlop -s v -x -a "{	'Show warnings',v	'Show info',vv	'Show debug',vvv	'Show trace'}" -d 'Increase verbosity'
+complete -c emlop -s q -l quiet -d 'Silence all log messages, including errors'
+complete -c emlop -l dump-config -d 'Print the resolved configuration and exit'
 complete -c emlop -s h -d 'Print short help'
 complete -c emlop -l help -d 'Print long help'
 complete -c emlop -n "__fish_use_subcommand" -s V -l version -d 'Print version'
+complete -c emlop -n "__fish_use_subcommand" -l help-all -d 'Print long help of all commands'
 
 complete -c emlop -n "__fish_use_subcommand" -f -a "log" -d 'Show log of sucessful merges, unmerges and syncs'
 complete -c emlop -n "__fish_use_subcommand" -f -a "predict" -d 'Predict merge times for current or pretended merges'
@@ -21,32 +37,87 @@ complete -c emlop -n "__fish_use_subcommand" -f -a "stats" -d 'Show statistics a
 complete -c emlop -n "__fish_use_subcommand" -f -a "accuracy" -d 'Compare actual merge time against predicted merge time'
 
 complete -c emlop -n "__fish_seen_subcommand_from log" -l starttime -d 'Display start time instead of end time' -f -a "yes no"
+complete -c emlop -n "__fish_seen_subcommand_from log" -l mark-downgrades -d 'Mark merges of a lower version than the previous merge of that package' -f -a "yes no"
+complete -c emlop -n "__fish_seen_subcommand_from log" -l coalesce-updates -d 'Fold the unmerge of the old version into the merge row during updates' -f -a "yes no"
+complete -c emlop -n "__fish_seen_subcommand_from log" -l target-root -d 'Display the target root of merges' -f -a "yes no"
+complete -c emlop -n "__fish_seen_subcommand_from log" -l line-buffered -d 'Write each row as soon as it is ready'
+complete -c emlop -n "__fish_seen_subcommand_from log" -l json-lines -d 'Output one JSON object per line'
+complete -c emlop -n "__fish_seen_subcommand_from log" -l sort -d 'Sort rows by date or by duration' -f -a "{date	'',duration	''}"
 complete -c emlop -n "__fish_seen_subcommand_from log" -s N -l first -d 'Show only the first <num> entries' -f -a "{	'Show only first entry',5	'Show only first 5 entries',10	'Show only first 10 entries'}"
 complete -c emlop -n "__fish_seen_subcommand_from log" -s n -l last -d 'Show only the last <num> entries' -f -a "{	'Show only last entry',5	'Show only last 5 entries',10	'Show only last 10 entries'}"
+complete -c emlop -n "__fish_seen_subcommand_from log" -l tail -d 'Show only the last <num> entries, reading only the end of the log' -x -a "5 10 20 50"
+complete -c emlop -n "__fish_seen_subcommand_from log" -l every -d 'Show only every <num>th entry' -x -a "2 5 10 100"
+complete -c emlop -n "__fish_seen_subcommand_from log" -l since-version -d 'Only show (un)merges of <version> or later' -x
+complete -c emlop -n "__fish_seen_subcommand_from log" -l skip -d 'Skip the first <num> entries' -x -a "10 20 50 100"
+complete -c emlop -n "__fish_seen_subcommand_from log" -l take -d 'Show only <num> entries after the skipped ones' -x -a "10 20 50 100"
 complete -c emlop -n "__fish_seen_subcommand_from log" -s s -l show -d 'Show (m)erges, (u)nmerges, (s)yncs, and/or (a)ll' -x -a "musa"
 complete -c emlop -n "__fish_seen_subcommand_from log" -s e -l exact -d 'Match <search> using plain string'
-complete -c emlop -n "__fish_seen_subcommand_from log" -a "(emlop complete '$1')"
+complete -c emlop -n "__fish_seen_subcommand_from log" -l glob -d 'Match <search> using shell globs'
+complete -c emlop -n "__fish_seen_subcommand_from log" -l invert -d "Show only entries that don't match <search>"
+complete -c emlop -n "__fish_seen_subcommand_from log" -l repo -d 'Show only repos matching <search>' -x
+complete -c emlop -n "__fish_seen_subcommand_from log" -l pkg-file -d 'Show only packages listed in <file>' -r -F
+complete -c emlop -n "__fish_seen_subcommand_from log" -a "(emlop complete -- (commandline -ct))"
 
-complete -c emlop -n "__fish_seen_subcommand_from predict" -s s -l show -d 'Show (e)emerge processes, (m)erges, (t)otal, and/or (a)ll' -x -a "emta"
+complete -c emlop -n "__fish_seen_subcommand_from predict" -s s -l show -d 'Show (r)unning processes, (m)erges, (s)yncs, (t)otal, and/or (a)ll' -x -a "rmsta"
+complete -c emlop -n "__fish_seen_subcommand_from predict" -l no-total -d 'Don\'t show the total estimate, overriding `--show`'
 complete -c emlop -n "__fish_seen_subcommand_from predict" -s N -l first -d 'Show only the first <num> entries' -f -a "{	'Show only first entry',5	'Show only first 5 entries',10	'Show only first 10 entries'}"
 complete -c emlop -n "__fish_seen_subcommand_from predict" -s n -l last -d 'Show only the last <num> entries' -f -a "{	'Show only last entry',5	'Show only last 5 entries',10	'Show only last 10 entries'}"
 complete -c emlop -n "__fish_seen_subcommand_from predict" -l tmpdir -d 'Location of portage tmpdir' -x -a "(__fish_complete_directories '$1')"
+complete -c emlop -n "__fish_seen_subcommand_from predict" -l finish-times -d 'Display predicted finish time of each merge' -f -a "yes no"
+complete -c emlop -n "__fish_seen_subcommand_from predict" -l status-code -d 'Exit with code 3 if an emerge process is running'
+complete -c emlop -n "__fish_seen_subcommand_from predict" -l pretend-file -d 'Read pretended merges from <file> instead of STDIN' -r -F
+complete -c emlop -n "__fish_seen_subcommand_from predict" -l plan-json -d 'Read pretended merges from a JSON <file> instead of STDIN' -r -F
+complete -c emlop -n "__fish_seen_subcommand_from predict" -l atoms -d 'Predict merge times for the given atoms' -x -a "(emlop complete -- (commandline -ct))"
+complete -c emlop -n "__fish_seen_subcommand_from predict" -l pretend-since -d 'Ignore pretended merges that completed after <date/command>' -x -a "{1C	'Last emerge command',1d	'One day ago',(date -Is)	'Exact date'}"
+complete -c emlop -n "__fish_seen_subcommand_from predict" -l pred-from -d 'Only use merges that completed after <date/command> for predictions' -x -a "{1y	'One year ago',1m	'One month ago',(date -Is)	'Exact date'}"
+complete -c emlop -n "__fish_seen_subcommand_from predict" -l pred-to -d 'Only use merges that completed before <date/command> for predictions' -x -a "{1y	'One year ago',1m	'One month ago',(date -Is)	'Exact date'}"
 complete -c emlop -n "__fish_seen_subcommand_from predict" -l resume -d 'Use main, backup, either, or no portage resume list' -f -a "{auto	'',either	'',main	'',backup	'',no	''}"
+complete -c emlop -n "__fish_seen_subcommand_from predict" -l resume-backup -d 'Fall back to the backup resume list when the main one is empty' -f -a "yes no"
 complete -c emlop -n "__fish_seen_subcommand_from predict" -l unknown -d 'Assume unkown packages take <secs> seconds to merge' -x -a "0 5 10 20 60"
-complete -c emlop -n "__fish_seen_subcommand_from predict" -l avg -d 'Select function used to predict durations' -x -a "arith median weighted-arith weighted-median"
+complete -c emlop -n "__fish_seen_subcommand_from predict" -l avg -d 'Select function used to predict durations' -x -a "arith median weighted-arith weighted-median p90 p95"
 complete -c emlop -n "__fish_seen_subcommand_from predict" -l limit -d 'Use the last <num> merge times to predict durations' -x -a "1 5 20 999"
+complete -c emlop -n "__fish_seen_subcommand_from predict" -l min-samples -d 'Mark predictions based on fewer than <num> merges with a `~`' -x -a "0 2 3 5"
+complete -c emlop -n "__fish_seen_subcommand_from predict" -l scale -d 'Multiply predicted merge times by <factor>' -x -a "0.5 0.7 1 1.5 2"
 complete -c emlop -n "__fish_seen_subcommand_from predict" -l pwidth -d 'Maximum width of emerge proces comandline' -x -a "10 20 40 80 160"
 complete -c emlop -n "__fish_seen_subcommand_from predict" -l pdepth -d 'Maximum depth of emerge proces tree' -x -a "0 1 3 5 7 99"
 
 complete -c emlop -n "__fish_seen_subcommand_from stats" -s s -l show -d 'Show (p)ackages, (t)otals, (s)yncs, and/or (a)ll' -x -a "ptsa"
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l by-version -d 'Also show per-version package stats'
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l histogram -d 'Also show a histogram of merge times below each package'
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l clock -d 'Also show the number of syncs by hour of the day'
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l overview -d 'Also show an overview line with the number of distinct packages and merges'
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l count -d 'Only print the number of package rows that would be shown'
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l active-days -d 'Also show the number of active days and the (un)merge time per active day'
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l compare -d 'Compare package merge times between two date ranges' -x
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l csv-series -d 'Export merges as a CSV time series'
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l when -d 'Only print the date of the first or last merge' -f -a "{first	'',last	''}"
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l top -d 'Only show the <num> packages with the most merge time' -x -a "5 10 20 50"
+complete -c emlop -n "__fish_seen_subcommand_from stats" -s N -l first -d 'Show only the first <num> entries' -f -a "{	'Show only first entry',5	'Show only first 5 entries',10	'Show only first 10 entries'}"
+complete -c emlop -n "__fish_seen_subcommand_from stats" -s n -l last -d 'Show only the last <num> entries' -f -a "{	'Show only last entry',5	'Show only last 5 entries',10	'Show only last 10 entries'}"
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l no-predict -d "Don't compute predicted times"
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l live -d 'Show packages currently being merged'
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l summary-only -d 'Only show totals and sync tables, overriding `--show`'
 complete -c emlop -n "__fish_seen_subcommand_from stats" -s g -l groupby -d 'Group by (y)ear, (m)onth, (w)eek, (d)ay, (n)one' -x -a "year month week day none"
-complete -c emlop -n "__fish_seen_subcommand_from stats" -l avg -d 'Select function used to predict durations' -x -a "arith median weighted-arith weighted-median"
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l relative-groups -d 'Label the current and previous groups relatively' -f -a "yes no"
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l group-regex -d 'Group packages by the first capture of <regex>' -x
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l avg -d 'Select function used to predict durations' -x -a "arith median weighted-arith weighted-median p90 p95"
 complete -c emlop -n "__fish_seen_subcommand_from stats" -l limit -d 'Use the last <num> merge times to predict durations' -x -a "1 5 20 999"
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l min-samples -d 'Mark predictions based on fewer than <num> merges with a `~`' -x -a "0 2 3 5"
 complete -c emlop -n "__fish_seen_subcommand_from stats" -s e -l exact -d 'Match <search> using plain string'
-complete -c emlop -n "__fish_seen_subcommand_from stats" -a "(emlop complete '$1')"
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l glob -d 'Match <search> using shell globs'
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l invert -d "Show only entries that don't match <search>"
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l repo -d 'Show only repos matching <search>' -x
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l pkg-file -d 'Show only packages listed in <file>' -r -F
+complete -c emlop -n "__fish_seen_subcommand_from stats" -l preserved-rebuild -d 'Only count (un)merges from `@preserved-rebuild` runs'
+complete -c emlop -n "__fish_seen_subcommand_from log stats" -l no-reinstalls -d 'Skip merges of the same version as the previous merge'
+complete -c emlop -n "__fish_seen_subcommand_from log stats" -l installed-only -d 'Show only packages that are currently installed'
+complete -c emlop -n "__fish_seen_subcommand_from log stats" -l binary -d 'Filter binary merges' -x -a "{both	'',only	'',exclude	''}"
+complete -c emlop -n "__fish_seen_subcommand_from stats" -a "(emlop complete -- (commandline -ct))"
 
-complete -c emlop -n "__fish_seen_subcommand_from accuracy" -s s -l show -d 'Show (m)erges, (t)otals, and/or (a)ll' -x -a "mta"
+complete -c emlop -n "__fish_seen_subcommand_from accuracy" -s s -l show -d 'Show (m)erges, (s)yncs, (t)otals, and/or (a)ll' -x -a "msta"
 complete -c emlop -n "__fish_seen_subcommand_from accuracy" -s n -l last -d 'Show only the last <num> entries' -x -a "{	'Show only last entry',5	'Show only last 5 entries',10	'Show only last 10 entries'}"
-complete -c emlop -n "__fish_seen_subcommand_from accuracy" -l avg -d 'Select function used to predict durations' -x -a "arith median weighted-arith weighted-median"
+complete -c emlop -n "__fish_seen_subcommand_from accuracy" -l avg -d 'Select function used to predict durations' -x -a "arith median weighted-arith weighted-median p90 p95"
 complete -c emlop -n "__fish_seen_subcommand_from accuracy" -l limit -d 'Use the last <num> merge times to predict durations' -x -a "1 5 20 999"
 complete -c emlop -n "__fish_seen_subcommand_from accuracy" -s e -l exact -d 'Match <search> using plain string'
+complete -c emlop -n "__fish_seen_subcommand_from accuracy" -l glob -d 'Match <search> using shell globs'
+complete -c emlop -n "__fish_seen_subcommand_from accuracy" -l invert -d "Show only entries that don't match <search>"
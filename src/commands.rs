@@ -1,39 +1,183 @@
 use crate::{datetime::*, parse::*, table::*, *};
+use anyhow::Context;
 use libc::pid_t;
-use std::{collections::{BTreeMap, HashMap, HashSet},
+use serde_json::{json, Value};
+use std::{cmp::Ordering,
+          collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+          fs::File,
           io::{stdin, IsTerminal}};
 
+/// Command outcome, turned into the process exit code by `main()`
+pub enum Status {
+    /// Something was found (exit code 0)
+    Found,
+    /// Nothing was found (exit code 1)
+    NotFound,
+    /// Emerge is running and `predict --status-code` was given (exit code 3)
+    Running,
+}
+impl From<bool> for Status {
+    fn from(found: bool) -> Self {
+        if found {
+            Self::Found
+        } else {
+            Self::NotFound
+        }
+    }
+}
+
 /// Straightforward display of merge events
 ///
 /// We store the start times in a hashmap to compute/print the duration when we reach a stop event.
 pub fn cmd_log(gc: Conf, sc: ConfLog) -> Result<bool, Error> {
-    let hist = get_hist(&gc.logfile, gc.from, gc.to, sc.show, &sc.search, sc.exact)?;
+    // Json lines are streamed as-is, without header, skip rows, or color reset
+    // Sorted rows don't have a meaningful place for skip rows
+    let sort = sc.sort == LogSort::Duration;
+    let gc = match (sc.json_lines, sort) {
+        (true, _) => Conf { header: false, showskip: false, lineend: b"\n", ..gc },
+        (false, true) => Conf { showskip: false, ..gc },
+        (false, false) => gc,
+    };
+    // Run starts are needed to forget about failed merges when coalescing updates
+    let show = Show { run: sc.show.run || sc.coalesce_updates, ..sc.show };
+    let tail = sc.tail.map(|n| (n, sc.show));
+    let hist = get_hist(&gc, show, &sc.search, &sc.repo, sc.exact, sc.invert, tail)?;
     let mut merges: HashMap<String, i64> = HashMap::new();
     let mut unmerges: HashMap<String, i64> = HashMap::new();
+    let mut versions: HashMap<String, String> = HashMap::new();
+    // With `--coalesce-updates`: ebuilds being merged in the current run, and the unmerge of the
+    // version they replaced, held back until we know if the merge row gets shown
+    let mut updates: HashMap<String, Option<Hist>> = HashMap::new();
+    // Unmerges that were held back but not coalesced, to be logged normally
+    let mut released: VecDeque<Hist> = VecDeque::new();
     let mut sync_start: Option<i64> = None;
     let mut found = 0;
+    let mut matched = 0;
+    let mut skipped = false;
+    // Only keep every nth match, before applying `--skip` and `--first`
+    let keep = |matched: &mut usize, found: &mut usize| {
+        *matched += 1;
+        if (*matched - 1) % sc.every == 0 {
+            *found += 1;
+            *found > sc.skip && *found <= sc.first
+        } else {
+            false
+        }
+    };
     let h = ["Date", "Duration", "Package/Repo"];
-    let mut tbl =
-        Table::new(&gc).align_left(0).align_left(2).margin(2, " ").last(sc.last).header(h);
-    for p in hist {
+    let mut tbl = Table::new(&gc).align_left(0)
+                                 .align_left(2)
+                                 .margin(2, " ")
+                                 .last(sc.last)
+                                 .line_buffered(sc.line_buffered)
+                                 .sort(sort)
+                                 .header(h);
+    let mut jsonl = sc.json_lines.then(|| {
+                                     Table::<1>::new(&gc).align_left(0)
+                                                         .last(sc.last)
+                                                         .line_buffered(true)
+                                                         .sort(sort)
+                                 });
+    let vdb = sc.installed_only.then(get_vdb);
+    let mut hist = hist.iter().filter(|p| is_installed(&vdb, p));
+    loop {
+        let (p, was_held) = match released.pop_front() {
+            Some(p) => (p, true),
+            None => match hist.next() {
+                Some(p) => (p, false),
+                None if updates.values().any(Option::is_some) => {
+                    released.extend(updates.drain().filter_map(|(_, u)| u));
+                    continue;
+                },
+                None => break,
+            },
+        };
+        if gc.showskip && !skipped && sc.skip > 0 && found == sc.skip {
+            tbl.skiprow(&[&FmtSkip("first", sc.skip)]);
+            skipped = true;
+        }
         match p {
             Hist::RunStart { ts, args, .. } => {
-                found += 1;
-                if found <= sc.first {
-                    tbl.row([&[&FmtDate(ts)], &[], &[&"Emerge ", &args]]);
+                released.extend(updates.drain().filter_map(|(_, u)| u));
+                if !sc.show.run {
+                    continue;
+                }
+                if keep(&mut matched, &mut found) {
+                    if let Some(j) = &mut jsonl {
+                        j.keyed_row(i64::MIN,
+                                    [&[&json!({"date": json_date(&gc, ts), "type": "run", "args": args})]]);
+                    } else {
+                        tbl.keyed_row(i64::MIN, [&[&FmtDate(ts)], &[], &[&"Emerge ", &args]]);
+                    }
                 }
             },
-            Hist::MergeStart { ts, key, .. } => {
+            Hist::MergeStart { ts, ref key, .. } => {
+                if sc.coalesce_updates {
+                    released.extend(updates.insert(p.ebuild().to_owned(), None).flatten());
+                }
                 // This'll overwrite any previous entry, if a merge started but never finished
-                merges.insert(key, ts);
+                merges.insert(key.clone(), ts);
             },
-            Hist::MergeStop { ts, ref key, .. } => {
-                found += 1;
+            Hist::MergeStop { ts, ref key, bin, merging, .. } => {
                 let started = merges.remove(key).unwrap_or(ts + 1);
-                if found <= sc.first {
-                    tbl.row([&[&FmtDate(if sc.starttime { started } else { ts })],
-                             &[&FmtDur(ts - started)],
-                             &[&gc.merge, &p.ebuild_version()]]);
+                let held = updates.remove(p.ebuild()).flatten();
+                // Without a merge row to coalesce into, the unmerge is logged on its own
+                if !sc.binary.keep(bin) {
+                    released.extend(held);
+                    continue;
+                }
+                let cmp = if sc.downgrades || sc.no_reinstalls {
+                    cmp_prev_version(&mut versions, &p)
+                } else {
+                    None
+                };
+                if (sc.no_reinstalls && cmp.is_some_and(|o| o.is_eq()))
+                   || is_before_version(&sc, &p)
+                {
+                    released.extend(held);
+                    continue;
+                }
+                let replaced = held.map(|h| h.version().to_owned());
+                let downgrade = sc.downgrades && cmp.is_some_and(|o| o.is_lt());
+                if keep(&mut matched, &mut found) {
+                    if let Some(j) = &mut jsonl {
+                        let mut o = json!({"date": json_date(&gc, if sc.starttime { started } else { ts }),
+                                           "duration": json_dur(ts - started),
+                                           "type": if bin { "binmerge" } else { "merge" },
+                                           "package": p.ebuild(),
+                                           "version": p.version()});
+                        if sc.target_root {
+                            o["root"] = p.root().into();
+                        }
+                        if downgrade {
+                            o["downgrade"] = true.into();
+                        }
+                        if let Some(v) = replaced {
+                            o["replaced"] = v.into();
+                        }
+                        if let Some(m) = merging.filter(|&m| m >= started && m <= ts) {
+                            o["build_duration"] = json_dur(m - started);
+                        }
+                        j.keyed_row(ts - started, [&[&o]]);
+                    } else {
+                        let col = if bin { &gc.binmerge } else { &gc.merge };
+                        let mark = if downgrade { " (downgrade)" } else { "" };
+                        let replaced =
+                            replaced.map_or(String::new(), |v| format!(" (replaced {v})"));
+                        let (clr, root): (&dyn Disp, _) = match sc.target_root {
+                            true => (&gc.clr, format!(" to {}", p.root())),
+                            false => (&"", String::new()),
+                        };
+                        tbl.keyed_row(ts - started,
+                                      [&[&FmtDate(if sc.starttime { started } else { ts })],
+                                       &[&FmtDur(ts - started)],
+                                       &[&FmtPkg(col, p.ebuild_version()),
+                                         clr,
+                                         &root,
+                                         &gc.cnt,
+                                         &mark,
+                                         &replaced]]);
+                    }
                 }
             },
             Hist::UnmergeStart { ts, key, .. } => {
@@ -41,12 +185,28 @@ pub fn cmd_log(gc: Conf, sc: ConfLog) -> Result<bool, Error> {
                 unmerges.insert(key, ts);
             },
             Hist::UnmergeStop { ts, ref key, .. } => {
-                found += 1;
+                if let Some(held @ None) = updates.get_mut(p.ebuild()).filter(|_| !was_held) {
+                    *held = Some(p);
+                    continue;
+                }
                 let started = unmerges.remove(key).unwrap_or(ts + 1);
-                if found <= sc.first {
-                    tbl.row([&[&FmtDate(if sc.starttime { started } else { ts })],
-                             &[&FmtDur(ts - started)],
-                             &[&gc.unmerge, &p.ebuild_version()]]);
+                if is_before_version(&sc, &p) {
+                    continue;
+                }
+                if keep(&mut matched, &mut found) {
+                    if let Some(j) = &mut jsonl {
+                        j.keyed_row(ts - started,
+                                    [&[&json!({"date": json_date(&gc, if sc.starttime { started } else { ts }),
+                                               "duration": json_dur(ts - started),
+                                               "type": "unmerge",
+                                               "package": p.ebuild(),
+                                               "version": p.version()})]]);
+                    } else {
+                        tbl.keyed_row(ts - started,
+                                      [&[&FmtDate(if sc.starttime { started } else { ts })],
+                                       &[&FmtDur(ts - started)],
+                                       &[&FmtPkg(&gc.unmerge, p.ebuild_version())]]);
+                    }
                 }
             },
             Hist::SyncStart { ts } => {
@@ -54,12 +214,20 @@ pub fn cmd_log(gc: Conf, sc: ConfLog) -> Result<bool, Error> {
                 sync_start = Some(ts);
             },
             Hist::SyncStop { ts, repo } => {
-                found += 1;
                 let started = sync_start.take().unwrap_or(ts + 1);
-                if found <= sc.first {
-                    tbl.row([&[&FmtDate(if sc.starttime { started } else { ts })],
-                             &[&FmtDur(ts - started)],
-                             &[&gc.clr, &"Sync ", &repo]]);
+                if keep(&mut matched, &mut found) {
+                    if let Some(j) = &mut jsonl {
+                        j.keyed_row(ts - started,
+                                    [&[&json!({"date": json_date(&gc, if sc.starttime { started } else { ts }),
+                                               "duration": json_dur(ts - started),
+                                               "type": "sync",
+                                               "repo": repo})]]);
+                    } else {
+                        tbl.keyed_row(ts - started,
+                                      [&[&FmtDate(if sc.starttime { started } else { ts })],
+                                       &[&FmtDur(ts - started)],
+                                       &[&gc.clr, &"Sync ", &repo]]);
+                    }
                 }
             },
         }
@@ -67,10 +235,55 @@ pub fn cmd_log(gc: Conf, sc: ConfLog) -> Result<bool, Error> {
             break;
         }
     }
+    if gc.showskip && !skipped && found > 0 && found < sc.skip {
+        tbl.skiprow(&[&FmtSkip("first", found)]);
+    }
     if gc.showskip && found >= sc.first {
-        tbl.skiprow(&[&gc.skip, &"(skip last ", &(found - sc.first), &")"]);
+        tbl.skiprow(&[&FmtSkip("last", found - sc.first)]);
+    }
+    Ok(found > sc.skip)
+}
+
+/// Date for `--json-lines`, as a number with `--date=unix` or as a formatted string
+fn json_date(gc: &Conf, ts: i64) -> Value {
+    if gc.date_fmt.is_unix() {
+        return ts.into();
+    }
+    let mut buf = vec![];
+    FmtDate(ts).out(&mut buf, gc);
+    String::from_utf8_lossy(&buf).into()
+}
+
+/// Duration for `--json-lines`, in seconds or null if unknown
+fn json_dur(secs: i64) -> Value {
+    if secs < 0 {
+        Value::Null
+    } else {
+        secs.into()
+    }
+}
+
+/// Compare with the version of the previous merge of this package, and remember this one
+fn cmp_prev_version(versions: &mut HashMap<String, String>, p: &Hist) -> Option<Ordering> {
+    let prev = versions.insert(p.ebuild().to_owned(), p.version().to_owned())?;
+    Some(version_cmp(p.version(), &prev))
+}
+
+/// Whether this (un)merge is older than `--since-version`
+fn is_before_version(sc: &ConfLog, p: &Hist) -> bool {
+    sc.since_version.as_ref().is_some_and(|v| version_cmp(p.version(), v).is_lt())
+}
+
+/// Whether this is an (un)merge of an installed package, or something that we don't filter
+fn is_installed(vdb: &Option<HashSet<String>>, p: &Hist) -> bool {
+    match (vdb, p) {
+        (Some(vdb),
+         Hist::MergeStart { .. }
+         | Hist::MergeStop { .. }
+         | Hist::UnmergeStart { .. }
+         | Hist::UnmergeStop { .. }) => vdb.contains(p.ebuild()),
+        _ => true,
     }
-    Ok(found > 0)
 }
 
 /// Wrapper to extract stats from a list of data points (durations).
@@ -94,51 +307,89 @@ impl Times {
             self.tot += t;
         }
     }
-    /// Predict the next data point by looking at past ones
-    fn pred(&self, lim: u16, avg: Average) -> i64 {
+    /// Whether the prediction is based on fewer than `min` data points
+    fn few(&self, lim: u16, min: usize) -> bool {
+        !self.vals.is_empty() && self.vals.len().min(lim as usize) < min
+    }
+    /// Predict the next data point by looking at past ones, if there are any
+    fn pred(&self, lim: u16, avg: Average) -> Option<i64> {
         if self.vals.is_empty() {
-            return -1; // FIXME Return None
+            return None;
         }
         let l = self.vals.len().min(lim as usize);
-        match avg {
-            // Simple arithmetic mean
-            Average::Arith => self.vals.iter().take(l).sum::<i64>() / l as i64,
-            // Middle value (or avg of the middle two)
-            Average::Median => {
-                let mut s: Vec<i64> = self.vals.iter().copied().take(l).collect();
-                s.sort_unstable();
-                if l % 2 == 0 {
-                    (s[(l / 2) - 1] + s[l / 2]) / 2
-                } else {
-                    s[l / 2]
-                }
-            },
-            // Arithmically weighted arithmetic mean
-            // Eg for 4 values the weights are 4,3,2,1 (most recent value first)
-            Average::WeightedArith => {
-                let (s, n, w) =
-                    self.vals
-                        .iter()
-                        .take(l)
-                        .fold((0, l as i64, 0), |(s, n, w), v| (s + v * n, n - 1, w + n));
-                debug_assert!(n == 0);
-                s / w
-            },
-            // Arithmically weighted median
-            // Eg 3,1,2 -> 3,3,3,1,1,2 -> 1,1,2,2,3,3,3 -> 2
-            Average::WeightedMedian => {
-                let mut s = Vec::with_capacity((l + 1) * (l / 2 + 1));
-                for (n, v) in self.vals.iter().copied().take(l).enumerate() {
-                    s.resize(s.len() - n + l, v);
-                }
-                s.sort_unstable();
-                let l = s.len();
-                if l % 2 == 0 {
-                    (s[(l / 2) - 1] + s[l / 2]) / 2
-                } else {
-                    s[l / 2]
-                }
-            },
+        Some(match avg {
+                 // Simple arithmetic mean
+                 Average::Arith => self.vals.iter().take(l).sum::<i64>() / l as i64,
+                 // Middle value (or avg of the middle two)
+                 Average::Median => {
+                     let mut s: Vec<i64> = self.vals.iter().copied().take(l).collect();
+                     s.sort_unstable();
+                     if l % 2 == 0 {
+                         (s[(l / 2) - 1] + s[l / 2]) / 2
+                     } else {
+                         s[l / 2]
+                     }
+                 },
+                 // Smallest value that is greater or equal to `p`% of values (nearest-rank method)
+                 Average::Percentile(p) => {
+                     let mut s: Vec<i64> = self.vals.iter().copied().take(l).collect();
+                     s.sort_unstable();
+                     s[(l * p as usize).div_ceil(100) - 1]
+                 },
+                 // Arithmically weighted arithmetic mean
+                 // Eg for 4 values the weights are 4,3,2,1 (most recent value first)
+                 Average::WeightedArith => {
+                     let (s, n, w) =
+                         self.vals
+                             .iter()
+                             .take(l)
+                             .fold((0, l as i64, 0), |(s, n, w), v| (s + v * n, n - 1, w + n));
+                     debug_assert!(n == 0);
+                     s / w
+                 },
+                 // Arithmically weighted median
+                 // Eg 3,1,2 -> 3,3,3,1,1,2 -> 1,1,2,2,3,3,3 -> 2
+                 Average::WeightedMedian => {
+                     let mut s = Vec::with_capacity((l + 1) * (l / 2 + 1));
+                     for (n, v) in self.vals.iter().copied().take(l).enumerate() {
+                         s.resize(s.len() - n + l, v);
+                     }
+                     s.sort_unstable();
+                     let l = s.len();
+                     if l % 2 == 0 {
+                         (s[(l / 2) - 1] + s[l / 2]) / 2
+                     } else {
+                         s[l / 2]
+                     }
+                 },
+             })
+    }
+}
+
+/// Count predictions that used fewer values than `--limit`, to help choosing a sensible limit
+#[derive(Default)]
+struct Window {
+    preds: usize,
+    short: usize,
+    vals: usize,
+}
+impl Window {
+    fn add(&mut self, times: &Times, lim: u16) {
+        if !times.vals.is_empty() {
+            let l = times.vals.len().min(lim as usize);
+            self.preds += 1;
+            self.vals += l;
+            if l < lim as usize {
+                self.short += 1;
+            }
+        }
+    }
+    fn log(&self, lim: u16) {
+        if self.short > 0 {
+            warn!("{} of {} predictions used fewer than --limit={lim} values, {:.1} on average",
+                  self.short,
+                  self.preds,
+                  self.vals as f64 / self.preds as f64);
         }
     }
 }
@@ -146,23 +397,30 @@ impl Times {
 /// Classify emerge commands by looking at their args.
 ///
 /// Note that some commands don't get logged at all, so this enum is quite limited.
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 enum ArgKind {
     All,
     Merge,
     Clean,
     Sync,
+    Preserved,
 }
 impl ArgKind {
     fn new(args: &str) -> Self {
+        let mut preserved = false;
         for arg in args.split_ascii_whitespace() {
             match arg {
                 "--deselect" | "--unmerge" | "--clean" | "--depclean" => return Self::Clean,
                 "--sync" => return Self::Sync,
+                "@preserved-rebuild" | "preserved-rebuild" => preserved = true,
                 _ => (),
             }
         }
-        Self::Merge
+        if preserved {
+            Self::Preserved
+        } else {
+            Self::Merge
+        }
     }
 }
 
@@ -171,76 +429,223 @@ impl ArgKind {
 /// First loop is like cmd_list but we store the merge time for each ebuild instead of printing it.
 /// Then we compute the stats per ebuild, and print that.
 pub fn cmd_stats(gc: Conf, sc: ConfStats) -> Result<bool, Error> {
-    let hist = get_hist(&gc.logfile, gc.from, gc.to, sc.show, &sc.search, sc.exact)?;
-    let h = [sc.group.name(), "Logged emerges", "Install/Update", "Unmerge/Clean", "Sync"];
-    let mut tblc = Table::new(&gc).margin(1, " ").header(h);
-    let h = [sc.group.name(), "Repo", "Syncs", "Total time", "Predict time"];
-    let mut tbls = Table::new(&gc).align_left(0).align_left(1).margin(1, " ").header(h);
+    if let Some(ranges) = sc.compare {
+        return cmd_stats_compare(&gc, &sc, ranges);
+    }
+    if sc.csv_series {
+        return cmd_stats_series(&gc, &sc);
+    }
+    if let Some(when) = sc.when {
+        return cmd_stats_when(&gc, &sc, when);
+    }
+    // Run args are needed to know which merges belong to a preserved-rebuild
+    let show = Show { run: sc.show.run || sc.preserved, ..sc.show };
+    let hist = get_hist(&gc, show, &sc.search, &sc.repo, sc.exact, sc.invert, None)?;
     let h = [sc.group.name(),
-             "Package",
-             "Merges",
-             "Total time",
-             "Predict time",
-             "Unmerges",
-             "Total time",
-             "Predict time"];
+             "Logged emerges",
+             "Install/Update",
+             "Unmerge/Clean",
+             "Sync",
+             "Preserved rebuild",
+             "Merges per emerge"];
+    let mut tblc = Table::new(&gc).margin(1, " ").optional(6).header(h);
+    let ph = if sc.no_predict { "" } else { "Predict time" };
+    let h = [sc.group.name(), "Repo", "Syncs", "Total time", ph];
+    let mut tbls = Table::new(&gc).align_left(0).align_left(1).margin(1, " ").header(h);
+    let h: [&str; 26] = std::array::from_fn(|i| match i {
+        0 => sc.group.name(),
+        1 => "Repo",
+        _ => HOURS[i - 2],
+    });
+    let mut tblh = Table::new(&gc).align_left(0).align_left(1).margin(1, " ").header(h);
+    let h = [sc.group.name(), "Package", "Merges", "Total time", ph, "Unmerges", "Total time", ph];
     let mut tblp = Table::new(&gc).align_left(0).align_left(1).margin(1, " ").header(h);
     let h = [sc.group.name(),
              "Merges",
+             "Source",
+             "Binary",
              "Total time",
              "Average time",
              "Unmerges",
              "Total time",
-             "Average time"];
-    let mut tblt = Table::new(&gc).align_left(0).margin(1, " ").header(h);
+             "Average time",
+             "Wall time",
+             if sc.active_days { "Active days" } else { "" },
+             if sc.active_days { "Daily time" } else { "" },
+             "Binary savings",
+             "Unpredicted"];
+    let mut tblt = Table::new(&gc).align_left(0)
+                                  .margin(1, " ")
+                                  .optional(2)
+                                  .optional(3)
+                                  .optional(12)
+                                  .optional(13)
+                                  .header(h);
+    let mut tblo = Table::<1>::new(&gc);
+    let mut overview = Overview::default();
+    let mut window = Window::default();
+    let mut rows = 0;
     let mut merge_start: HashMap<String, i64> = HashMap::new();
     let mut unmerge_start: HashMap<String, i64> = HashMap::new();
     let mut pkg_time: BTreeMap<String, (Times, Times)> = BTreeMap::new();
+    // Source build times are kept across groups, to predict the time saved by binary merges
+    let mut src_time: HashMap<String, Times> = HashMap::new();
+    let mut bin_time: HashMap<String, Times> = HashMap::new();
+    let mut ver_time: HashMap<String, VerTimes> = HashMap::new();
+    let mut versions: HashMap<String, String> = HashMap::new();
+    let mut spans: Vec<(i64, i64)> = vec![];
+    let mut days: HashSet<i64> = HashSet::new();
     let mut sync_start: Option<i64> = None;
     let mut sync_time: BTreeMap<String, Times> = BTreeMap::new();
+    let mut sync_hours: BTreeMap<String, [u64; 24]> = BTreeMap::new();
     let mut run_args: BTreeMap<ArgKind, usize> = BTreeMap::new();
+    // Merges per emerge only make sense if we see all the merges
+    let count_run_merges = (sc.show.merge || sc.show.pkg || sc.show.tot)
+                           && sc.search.is_empty()
+                           && sc.repo.is_empty()
+                           && !sc.installed_only
+                           && !sc.preserved;
+    let mut run_merges = 0;
+    let mut bin_merges = 0;
+    // Kind of the latest merging run, ignoring sync and clean runs which may run concurrently
+    let mut merge_kind = None;
     let mut nextts = 0;
     let mut curts = 0;
-    for p in hist {
+    // With `--live`: merges started but not finished yet
+    let mut live_start: BTreeMap<Pkg, i64> = BTreeMap::new();
+    let vdb = sc.installed_only.then(get_vdb);
+    let now = epoch_now();
+    let group_label = |ts| match sc.relative_groups {
+        true => sc.group.at_relative(ts, now, &gc.tz),
+        false => sc.group.at(ts, &gc.tz),
+    };
+    let pkg_key = |p: &Hist| stats_pkg_key(&sc, p.ebuild());
+    for p in hist.iter().filter(|p| is_installed(&vdb, p)) {
         if !matches!(sc.group, Timespan::None) {
             let t = p.ts();
             if nextts == 0 {
-                nextts = sc.group.next(t, gc.date_offset);
+                nextts = sc.group.next(t, &gc.tz);
                 curts = t;
             } else if t > nextts {
-                let group = sc.group.at(curts, gc.date_offset);
-                cmd_stats_group(&gc, &sc, &mut tblc, &mut tbls, &mut tblp, &mut tblt, group,
-                                &run_args, &sync_time, &pkg_time);
+                if sc.count {
+                    rows += pkg_time.len().min(sc.top).min(sc.first).min(sc.last);
+                } else {
+                    let group = group_label(curts);
+                    cmd_stats_group(&gc,
+                                    &sc,
+                                    &mut tblc,
+                                    &mut tbls,
+                                    &mut tblh,
+                                    &mut tblp,
+                                    &mut tblt,
+                                    group,
+                                    &run_args,
+                                    &sync_time,
+                                    &sync_hours,
+                                    &pkg_time,
+                                    &ver_time,
+                                    &src_time,
+                                    &bin_time,
+                                    &mut spans,
+                                    days.len(),
+                                    count_run_merges.then_some(run_merges),
+                                    bin_merges);
+                    if sc.show.pkg && !sc.no_predict {
+                        pkg_time.values().for_each(|(m, _)| window.add(m, sc.lim));
+                    }
+                }
                 sync_time.clear();
+                sync_hours.clear();
                 pkg_time.clear();
+                bin_time.clear();
+                ver_time.clear();
+                spans.clear();
+                days.clear();
                 run_args.clear();
-                nextts = sc.group.next(t, gc.date_offset);
+                run_merges = 0;
+                bin_merges = 0;
+                nextts = sc.group.next(t, &gc.tz);
                 curts = t;
             }
         }
         match p {
             Hist::RunStart { args, .. } => {
-                *run_args.entry(ArgKind::All).or_insert(0) += 1;
-                *run_args.entry(ArgKind::new(&args)).or_insert(0) += 1;
+                let kind = ArgKind::new(&args);
+                if matches!(kind, ArgKind::Merge | ArgKind::Preserved) {
+                    merge_kind = Some(kind);
+                }
+                if sc.show.run {
+                    *run_args.entry(ArgKind::All).or_insert(0) += 1;
+                    *run_args.entry(kind).or_insert(0) += 1;
+                }
             },
-            Hist::MergeStart { ts, key, .. } => {
-                merge_start.insert(key, ts);
+            Hist::MergeStart { ts, ref key, .. } => {
+                if !sc.preserved || merge_kind == Some(ArgKind::Preserved) {
+                    if sc.live {
+                        live_start.insert(Pkg::new(p.ebuild(), p.version()), ts);
+                    }
+                    merge_start.insert(key.clone(), ts);
+                }
             },
-            Hist::MergeStop { ts, ref key, .. } => {
-                if let Some(start_ts) = merge_start.remove(key) {
-                    let (times, _) = pkg_time.entry(p.ebuild().to_owned())
-                                             .or_insert((Times::new(), Times::new()));
+            Hist::MergeStop { ts, ref key, bin, .. } => {
+                if sc.live {
+                    live_start.remove(&Pkg::new(p.ebuild(), p.version()));
+                }
+                let start = merge_start.remove(key);
+                if count_run_merges && start.is_some() && merge_kind == Some(ArgKind::Merge) {
+                    run_merges += 1;
+                }
+                if !sc.binary.keep(bin) {
+                    continue;
+                }
+                if sc.no_reinstalls
+                   && cmp_prev_version(&mut versions, &p).is_some_and(|o| o.is_eq())
+                {
+                    continue;
+                }
+                if let Some(start_ts) = start {
+                    if sc.overview {
+                        overview.insert(&p, start_ts, ts);
+                    }
+                    let key = pkg_key(&p);
+                    let (times, _) =
+                        pkg_time.entry(key.clone()).or_insert((Times::new(), Times::new()));
                     times.insert(ts - start_ts);
+                    spans.push((start_ts, ts));
+                    if sc.active_days {
+                        days.insert(gc.tz.day(start_ts));
+                    }
+                    if bin {
+                        bin_merges += 1;
+                    }
+                    if sc.show.tot && !sc.no_predict {
+                        let t = if bin { &mut bin_time } else { &mut src_time };
+                        t.entry(key).or_insert(Times::new()).insert(ts - start_ts);
+                    }
+                    if sc.by_version {
+                        let vers = ver_time.entry(p.ebuild().to_owned()).or_default();
+                        ver_times(vers, p.version()).0.insert(ts - start_ts);
+                    }
                 }
             },
             Hist::UnmergeStart { ts, key, .. } => {
-                unmerge_start.insert(key, ts);
+                if !sc.preserved || merge_kind == Some(ArgKind::Preserved) {
+                    unmerge_start.insert(key, ts);
+                }
             },
             Hist::UnmergeStop { ts, ref key, .. } => {
                 if let Some(start_ts) = unmerge_start.remove(key) {
-                    let (_, times) = pkg_time.entry(p.ebuild().to_owned())
-                                             .or_insert((Times::new(), Times::new()));
+                    let (_, times) =
+                        pkg_time.entry(pkg_key(&p)).or_insert((Times::new(), Times::new()));
                     times.insert(ts - start_ts);
+                    spans.push((start_ts, ts));
+                    if sc.active_days {
+                        days.insert(gc.tz.day(start_ts));
+                    }
+                    if sc.by_version {
+                        let vers = ver_time.entry(p.ebuild().to_owned()).or_default();
+                        ver_times(vers, p.version()).1.insert(ts - start_ts);
+                    }
                 }
             },
             Hist::SyncStart { ts } => {
@@ -249,6 +654,10 @@ pub fn cmd_stats(gc: Conf, sc: ConfStats) -> Result<bool, Error> {
             },
             Hist::SyncStop { ts, repo } => {
                 if let Some(start_ts) = sync_start.take() {
+                    if sc.clock {
+                        sync_hours.entry(repo.clone()).or_insert([0; 24])[gc.tz.hour(start_ts)] +=
+                            1;
+                    }
                     let times = sync_time.entry(repo).or_insert(Times::new());
                     times.insert(ts - start_ts);
                 } else {
@@ -257,68 +666,561 @@ pub fn cmd_stats(gc: Conf, sc: ConfStats) -> Result<bool, Error> {
             },
         }
     }
-    let group = sc.group.at(curts, gc.date_offset);
-    cmd_stats_group(&gc, &sc, &mut tblc, &mut tbls, &mut tblp, &mut tblt, group, &run_args,
-                    &sync_time, &pkg_time);
-    // Controlled drop to ensure table order and insert blank lines
-    let (ec, es, ep, et) = (!tblc.is_empty(), !tbls.is_empty(), !tblp.is_empty(), !tblt.is_empty());
-    drop(tblc);
-    if ec && es {
-        println!();
+    if gc.out == OutStyle::Prometheus {
+        return cmd_stats_prometheus(&sc, &pkg_time, &sync_time);
+    }
+    if sc.count {
+        rows += pkg_time.len().min(sc.top).min(sc.first).min(sc.last);
+        let mut tbl = Table::<1>::new(&gc);
+        tbl.row([&[&gc.cnt, &rows]]);
+        return Ok(rows > 0);
+    }
+    let group = group_label(curts);
+    cmd_stats_group(&gc,
+                    &sc,
+                    &mut tblc,
+                    &mut tbls,
+                    &mut tblh,
+                    &mut tblp,
+                    &mut tblt,
+                    group,
+                    &run_args,
+                    &sync_time,
+                    &sync_hours,
+                    &pkg_time,
+                    &ver_time,
+                    &src_time,
+                    &bin_time,
+                    &mut spans,
+                    days.len(),
+                    count_run_merges.then_some(run_merges),
+                    bin_merges);
+    if sc.show.pkg && !sc.no_predict {
+        pkg_time.values().for_each(|(m, _)| window.add(m, sc.lim));
+        window.log(sc.lim);
+    }
+    if sc.live && sc.show.pkg {
+        let group = group_label(curts);
+        cmd_stats_live(&gc, &sc, &mut tblp, group, &pkg_time, &live_start);
     }
-    drop(tbls);
-    if (ec || es) && ep {
-        println!();
+    if overview.merges > 0 {
+        tblo.row([&[&overview]]);
+    }
+    // Controlled drop to ensure table order and insert blank lines
+    let mut sep = false;
+    flush_sep(tblo, &mut sep);
+    flush_sep(tblc, &mut sep);
+    flush_sep(tbls, &mut sep);
+    flush_sep(tblh, &mut sep);
+    flush_sep(tblp, &mut sep);
+    flush_sep(tblt, &mut sep);
+    Ok(!pkg_time.is_empty() || !sync_time.is_empty())
+}
+
+/// Write totals as Prometheus metrics, for the node_exporter textfile collector
+///
+/// Metrics are counters since the start of the log, or of `--from`.
+fn cmd_stats_prometheus(sc: &ConfStats,
+                        pkg_time: &BTreeMap<String, (Times, Times)>,
+                        sync_time: &BTreeMap<String, Times>)
+                        -> Result<bool, Error> {
+    use std::io::Write;
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    let mut metric = |name: &str,
+                      help: &str,
+                      label: &str,
+                      vals: &mut dyn Iterator<Item = (&String, i64)>|
+     -> std::io::Result<()> {
+        writeln!(out, "# HELP {name} {help}")?;
+        writeln!(out, "# TYPE {name} counter")?;
+        for (k, v) in vals {
+            writeln!(out, "{name}{{{label}=\"{}\"}} {v}", prom_escape(k))?;
+        }
+        Ok(())
+    };
+    if sc.show.pkg || sc.show.tot {
+        let m = || pkg_time.iter().filter(|(_, (m, _))| m.count > 0);
+        let u = || pkg_time.iter().filter(|(_, (_, u))| u.count > 0);
+        metric("emlop_merge_total",
+               "Number of merges",
+               "package",
+               &mut m().map(|(p, (m, _))| (p, m.count)))?;
+        metric("emlop_merge_seconds_total",
+               "Time spent merging",
+               "package",
+               &mut m().map(|(p, (m, _))| (p, m.tot)))?;
+        metric("emlop_unmerge_total",
+               "Number of unmerges",
+               "package",
+               &mut u().map(|(p, (_, u))| (p, u.count)))?;
+        metric("emlop_unmerge_seconds_total",
+               "Time spent unmerging",
+               "package",
+               &mut u().map(|(p, (_, u))| (p, u.tot)))?;
     }
-    drop(tblp);
-    if (ec || es || ep) && et {
-        println!();
+    if sc.show.sync {
+        metric("emlop_sync_total",
+               "Number of syncs",
+               "repo",
+               &mut sync_time.iter().map(|(r, t)| (r, t.count)))?;
+        metric("emlop_sync_seconds_total",
+               "Time spent syncing",
+               "repo",
+               &mut sync_time.iter().map(|(r, t)| (r, t.tot)))?;
     }
-    drop(tblt);
+    out.flush()?;
     Ok(!pkg_time.is_empty() || !sync_time.is_empty())
 }
 
+/// Escape a Prometheus label value
+fn prom_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Add an "in progress" row for each package currently being merged
+fn cmd_stats_live(gc: &Conf,
+                  sc: &ConfStats,
+                  tblp: &mut Table<8>,
+                  group: String,
+                  pkg_time: &BTreeMap<String, (Times, Times)>,
+                  live_start: &BTreeMap<Pkg, i64>) {
+    if !has_procfs() {
+        warn!("/proc is unavailable, cannot show merges in progress");
+        return;
+    }
+    let einfo = get_emerge(&get_all_proc(&mut vec![]));
+    let now = epoch_now();
+    let empty = Times::new();
+    for (pkg, &start) in live_start {
+        let elapsed = einfo.elapsed(pkg, start, now);
+        if elapsed > 0 {
+            let key = stats_pkg_key(sc, pkg.ebuild());
+            let times = pkg_time.get(&key).map_or(&empty, |(m, _)| m);
+            let pred = FmtPred::new(sc, times);
+            tblp.row([&[&group],
+                      &[&FmtPkg(&gc.pkg, &key), &gc.skip, &" (in progress)"],
+                      &[],
+                      &[&FmtElapsed(elapsed, pred.pred)],
+                      &[&pred],
+                      &[],
+                      &[],
+                      &[]]);
+        }
+    }
+}
+
+/// Predicted duration, `?` if unknown, or nothing if predictions are disabled
+struct FmtPred {
+    enabled: bool,
+    pred: Option<i64>,
+    few: bool,
+}
+impl FmtPred {
+    fn new(sc: &ConfStats, times: &Times) -> Self {
+        let enabled = !sc.no_predict;
+        Self { enabled,
+               pred: times.pred(sc.lim, sc.avg).filter(|_| enabled),
+               few: times.few(sc.lim, sc.min_samples) }
+    }
+}
+impl Disp for FmtPred {
+    fn out(&self, buf: &mut Vec<u8>, conf: &Conf) -> usize {
+        if !self.enabled {
+            return 0;
+        }
+        FmtMaybeDur(self.pred).out(buf, conf) + FmtFew(self.few).out(buf, conf)
+    }
+}
+
+/// Low-confidence marker, for predictions based on fewer than `--min-samples` merges
+struct FmtFew(bool);
+impl Disp for FmtFew {
+    fn out(&self, buf: &mut Vec<u8>, conf: &Conf) -> usize {
+        if self.0 {
+            wtb!(buf, "{}~{}", conf.skip.val, conf.clr.val);
+            1
+        } else {
+            0
+        }
+    }
+}
+
+/// Sparkline of the distribution of durations, surrounded by the min and max durations
+struct FmtHist<'a>(&'a Times);
+impl Disp for FmtHist<'_> {
+    fn out(&self, buf: &mut Vec<u8>, conf: &Conf) -> usize {
+        use std::io::Write;
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let vals = &self.0.vals;
+        let (Some(&min), Some(&max)) = (vals.iter().min(), vals.iter().max()) else {
+            return 0;
+        };
+        let buckets = vals.len().min(10);
+        let mut counts = vec![0; buckets];
+        for v in vals {
+            counts[((v - min) * buckets as i64 / (max - min + 1)) as usize] += 1;
+        }
+        let top = counts.iter().copied().max().unwrap_or(1);
+        let mut len = FmtDur(min).out(buf, conf);
+        wtb!(buf, "{} ", conf.clr.val);
+        for c in counts {
+            let bar = if c == 0 { ' ' } else { BARS[(c * BARS.len() - 1) / top] };
+            wtb!(buf, "{bar}");
+        }
+        wtb!(buf, " ");
+        len += buckets + 2;
+        len + FmtDur(max).out(buf, conf)
+    }
+}
+
+/// Key of the per-package stats, the ebuild name or its `--group-regex` capture
+fn stats_pkg_key(sc: &ConfStats, ebuild: &str) -> String {
+    match &sc.group_regex {
+        Some(re) => re.captures(ebuild)
+                      .and_then(|c| c.get(1).or_else(|| c.get(0)))
+                      .map_or("other", |m| m.as_str())
+                      .to_owned(),
+        None => ebuild.to_owned(),
+    }
+}
+
+/// Only print the date of the first or last merge
+fn cmd_stats_when(gc: &Conf, sc: &ConfStats, when: When) -> Result<bool, Error> {
+    let hist = get_hist(gc, Show::m(), &sc.search, &sc.repo, sc.exact, sc.invert, None)?;
+    let vdb = sc.installed_only.then(get_vdb);
+    let mut found = None;
+    for p in hist.iter().filter(|p| is_installed(&vdb, p)) {
+        if let Hist::MergeStop { ts, bin, .. } = p {
+            if sc.binary.keep(bin) {
+                found = Some(ts);
+                if matches!(when, When::First) {
+                    break;
+                }
+            }
+        }
+    }
+    if let Some(ts) = found {
+        let mut tbl = Table::<1>::new(gc);
+        tbl.row([&[&FmtDate(ts)]]);
+    }
+    Ok(found.is_some())
+}
+
+/// Export merge counts and times as a CSV time series
+///
+/// Every group between the first and last merge (or `--from`/`--to`) gets a row for each package,
+/// even when nothing was merged, so that the series is continuous.
+fn cmd_stats_series(gc: &Conf, sc: &ConfStats) -> Result<bool, Error> {
+    use std::io::Write;
+    let group = match sc.group {
+        Timespan::None => Timespan::Day,
+        g => g,
+    };
+    let (min, max) = resolve_range(gc, gc.from, gc.to)?;
+    let hist = get_hist(gc, Show::m(), &sc.search, &sc.repo, sc.exact, sc.invert, None)?;
+    let vdb = sc.installed_only.then(get_vdb);
+    let mut merge_start: HashMap<String, i64> = HashMap::new();
+    let mut versions: HashMap<String, String> = HashMap::new();
+    let mut cells: HashMap<(String, String), (i64, i64)> = HashMap::new();
+    let mut pkgs: BTreeSet<String> = BTreeSet::new();
+    let (mut first, mut last) = (i64::MAX, i64::MIN);
+    for p in hist.iter().filter(|p| is_installed(&vdb, p)) {
+        match p {
+            Hist::MergeStart { ts, key, .. } => {
+                merge_start.insert(key, ts);
+            },
+            Hist::MergeStop { ts, ref key, bin, .. } => {
+                let Some(start) = merge_start.remove(key) else { continue };
+                if !sc.binary.keep(bin)
+                   || (sc.no_reinstalls
+                       && cmp_prev_version(&mut versions, &p).is_some_and(|o| o.is_eq()))
+                {
+                    continue;
+                }
+                // Group totals use an empty package name
+                let pkg = if sc.show.pkg { stats_pkg_key(sc, p.ebuild()) } else { String::new() };
+                let cell = cells.entry((group.at(start, &gc.tz), pkg.clone())).or_insert((0, 0));
+                cell.0 += 1;
+                cell.1 += (ts - start).max(0);
+                pkgs.insert(pkg);
+                first = first.min(start);
+                last = last.max(start);
+            },
+            _ => (),
+        }
+    }
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    match sc.show.pkg {
+        true => writeln!(out, "{},package,merges,seconds", group.name().to_lowercase())?,
+        false => writeln!(out, "{},merges,seconds", group.name().to_lowercase())?,
+    }
+    let mut t = if min == i64::MIN { first } else { min };
+    let end = if max == i64::MAX { last } else { max.min(epoch_now()) };
+    while !pkgs.is_empty() && t <= end {
+        let label = group.at(t, &gc.tz);
+        for pkg in &pkgs {
+            let (count, secs) = cells.remove(&(label.clone(), pkg.clone())).unwrap_or((0, 0));
+            match sc.show.pkg {
+                true => writeln!(out, "{label},{},{count},{secs}", csv_escape(pkg))?,
+                false => writeln!(out, "{label},{count},{secs}")?,
+            }
+        }
+        t = group.next(t, &gc.tz);
+    }
+    out.flush()?;
+    Ok(!pkgs.is_empty())
+}
+fn csv_escape(s: &str) -> std::borrow::Cow<'_, str> {
+    match s.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")).into(),
+        false => s.into(),
+    }
+}
+
+/// Compare the merge times of each package in two time ranges
+///
+/// The log is parsed once, merges are put in each range that contains their start time.
+fn cmd_stats_compare(gc: &Conf,
+                     sc: &ConfStats,
+                     ranges: [(TimeBound, TimeBound); 2])
+                     -> Result<bool, Error> {
+    let resolve = |b, default| match b {
+        TimeBound::None => Ok(default),
+        b => resolve_ts(gc, b),
+    };
+    let mut bounds = [(0, 0); 2];
+    for (b, (from, to)) in bounds.iter_mut().zip(ranges) {
+        *b = (resolve(from, i64::MIN)?, resolve(to, i64::MAX)?);
+    }
+    let hist = get_hist(gc, Show::m(), &sc.search, &sc.repo, sc.exact, sc.invert, None)?;
+    let mut merge_start: HashMap<String, i64> = HashMap::new();
+    let mut pkg_time: BTreeMap<String, [Times; 2]> = BTreeMap::new();
+    for p in hist {
+        match p {
+            Hist::MergeStart { ts, key, .. } => {
+                merge_start.insert(key, ts);
+            },
+            Hist::MergeStop { ts, ref key, bin, .. } => {
+                let Some(start) = merge_start.remove(key) else { continue };
+                if !sc.binary.keep(bin) {
+                    continue;
+                }
+                for (i, (from, to)) in bounds.iter().enumerate() {
+                    if (*from..*to).contains(&start) {
+                        let times = pkg_time.entry(stats_pkg_key(sc, p.ebuild()))
+                                            .or_insert_with(|| [Times::new(), Times::new()]);
+                        times[i].insert(ts - start);
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+    let h = ["Package", "Merges", "Predict time", "Merges", "Predict time", "Change"];
+    let mut tbl = Table::new(gc).align_left(0).header(h);
+    for (pkg, [a, b]) in &pkg_time {
+        let (pa, pb) = (a.pred(sc.lim, sc.avg), b.pred(sc.lim, sc.avg));
+        let change = match (pa, pb) {
+            (Some(pa), Some(pb)) if pa > 0 => format!("{:+}%", (pb - pa) * 100 / pa),
+            _ => String::new(),
+        };
+        tbl.row([&[&FmtPkg(&gc.pkg, pkg)],
+                 &[&gc.cnt, &a.count],
+                 &[&FmtMaybeDur(pa)],
+                 &[&gc.cnt, &b.count],
+                 &[&FmtMaybeDur(pb)],
+                 &[&change]]);
+    }
+    Ok(!pkg_time.is_empty())
+}
+
+/// Number of distinct packages, versions and merges, and the time range they span
+#[derive(Default)]
+struct Overview {
+    pkgs: HashSet<String>,
+    vers: HashSet<String>,
+    merges: usize,
+    first: i64,
+    last: i64,
+}
+impl Overview {
+    fn insert(&mut self, p: &Hist, start: i64, stop: i64) {
+        if self.merges == 0 || start < self.first {
+            self.first = start;
+        }
+        self.last = self.last.max(stop);
+        self.merges += 1;
+        self.pkgs.insert(p.ebuild().to_owned());
+        self.vers.insert(p.ebuild_version().to_owned());
+    }
+}
+impl Disp for Overview {
+    fn out(&self, buf: &mut Vec<u8>, conf: &Conf) -> usize {
+        let mut len = 0;
+        for (n, label, sep) in [(self.pkgs.len(), " distinct package", ", "),
+                                (self.vers.len(), " distinct version", ", "),
+                                (self.merges, " total merge", " over ")]
+        {
+            len += conf.cnt.out(buf, conf) + n.out(buf, conf) + conf.clr.out(buf, conf);
+            len += label.out(buf, conf);
+            if n != 1 {
+                len += "s".out(buf, conf);
+            }
+            len += sep.out(buf, conf);
+        }
+        len += FmtDate(self.first).out(buf, conf);
+        len += " - ".out(buf, conf);
+        len + FmtDate(self.last).out(buf, conf)
+    }
+}
+
+/// Column headers for the sync clock
+const HOURS: [&str; 24] = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12",
+                           "13", "14", "15", "16", "17", "18", "19", "20", "21", "22", "23"];
+
+/// Merge and unmerge times of each version of a package, in order of first appearance
+type VerTimes = Vec<(String, (Times, Times))>;
+
+fn ver_times<'a>(vers: &'a mut VerTimes, ver: &str) -> &'a mut (Times, Times) {
+    let i = match vers.iter().position(|(v, _)| v == ver) {
+        Some(i) => i,
+        None => {
+            vers.push((ver.to_owned(), (Times::new(), Times::new())));
+            vers.len() - 1
+        },
+    };
+    &mut vers[i].1
+}
+
+/// Modification time of a file, as a unix timestamp
+fn file_mtime(file: &File) -> Option<i64> {
+    let t = file.metadata().ok()?.modified().ok()?;
+    Some(t.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs() as i64)
+}
+
+/// Flush a non-empty table, preceded by a blank line if a previous table was printed
+fn flush_sep<const N: usize>(tbl: Table<N>, sep: &mut bool) {
+    if !tbl.is_empty() {
+        if *sep {
+            println!();
+        }
+        drop(tbl);
+        *sep = true;
+    }
+}
+
 // Reducing the arg count here doesn't seem worth it, for either readability or performance
 #[allow(clippy::too_many_arguments)]
 fn cmd_stats_group(gc: &Conf,
                    sc: &ConfStats,
-                   tblc: &mut Table<5>,
+                   tblc: &mut Table<7>,
                    tbls: &mut Table<5>,
+                   tblh: &mut Table<26>,
                    tblp: &mut Table<8>,
-                   tblt: &mut Table<7>,
+                   tblt: &mut Table<14>,
                    group: String,
                    run_args: &BTreeMap<ArgKind, usize>,
                    sync_time: &BTreeMap<String, Times>,
-                   pkg_time: &BTreeMap<String, (Times, Times)>) {
+                   sync_hours: &BTreeMap<String, [u64; 24]>,
+                   pkg_time: &BTreeMap<String, (Times, Times)>,
+                   ver_time: &HashMap<String, VerTimes>,
+                   src_time: &HashMap<String, Times>,
+                   bin_time: &HashMap<String, Times>,
+                   spans: &mut [(i64, i64)],
+                   days: usize,
+                   run_merges: Option<usize>,
+                   bin_merges: i64) {
     // Commands
     if sc.show.run && !run_args.is_empty() {
+        let merge_runs = *run_args.get(&ArgKind::Merge).unwrap_or(&0);
+        let per_run = match (run_merges, merge_runs) {
+            (None, _) => String::new(),
+            (Some(_), 0) => String::from("?"),
+            (Some(m), n) => format!("{:.1}", m as f64 / n as f64),
+        };
         tblc.row([&[&group],
                   &[&gc.cnt, run_args.get(&ArgKind::All).unwrap_or(&0)],
-                  &[&gc.cnt, run_args.get(&ArgKind::Merge).unwrap_or(&0)],
+                  &[&gc.cnt, &merge_runs],
                   &[&gc.cnt, run_args.get(&ArgKind::Clean).unwrap_or(&0)],
-                  &[&gc.cnt, run_args.get(&ArgKind::Sync).unwrap_or(&0)]]);
+                  &[&gc.cnt, run_args.get(&ArgKind::Sync).unwrap_or(&0)],
+                  &[&gc.cnt, run_args.get(&ArgKind::Preserved).unwrap_or(&0)],
+                  &[&gc.cnt, &per_run]]);
     }
     // Syncs
     if sc.show.sync && !sync_time.is_empty() {
+        tbls.group();
+        if !sync_hours.is_empty() {
+            tblh.group();
+        }
         for (repo, time) in sync_time {
             tbls.row([&[&group],
                       &[repo],
                       &[&gc.cnt, &time.count],
                       &[&FmtDur(time.tot)],
-                      &[&FmtDur(time.pred(sc.lim, sc.avg))]]);
+                      &[&FmtPred::new(sc, time)]]);
+        }
+        for (repo, hours) in sync_hours {
+            let (g, r): ([&dyn Disp; 1], [&dyn Disp; 1]) = ([&group], [repo]);
+            let cells: Vec<[&dyn Disp; 2]> = hours.iter().map(|h| [&gc.cnt as _, h as _]).collect();
+            tblh.row(std::array::from_fn(|i| match i {
+                         0 => &g[..],
+                         1 => &r[..],
+                         _ => &cells[i - 2][..],
+                     }));
         }
     }
     // Packages
     if sc.show.pkg && !pkg_time.is_empty() {
-        for (pkg, (merge, unmerge)) in pkg_time {
+        tblp.group();
+        let mut pkgs: Vec<_> = pkg_time.iter().collect();
+        if sc.top != usize::MAX {
+            pkgs.sort_by_key(|(_, (m, _))| std::cmp::Reverse(m.tot));
+        }
+        // Rows cut by --top/--first/--last are summed up in the `others` row
+        let end = sc.top.min(pkgs.len());
+        let start = end.saturating_sub(sc.last);
+        let end = end.min(start.saturating_add(sc.first));
+        let others: Vec<_> = pkgs[..start].iter().chain(&pkgs[end..]).collect();
+        let pkgs = &pkgs[start..end];
+        for &(pkg, (merge, unmerge)) in pkgs {
             tblp.row([&[&group],
-                      &[&gc.pkg, pkg],
+                      &[&FmtPkg(&gc.pkg, pkg)],
                       &[&gc.cnt, &merge.count],
                       &[&FmtDur(merge.tot)],
-                      &[&FmtDur(merge.pred(sc.lim, sc.avg))],
+                      &[&FmtPred::new(sc, merge)],
                       &[&gc.cnt, &unmerge.count],
                       &[&FmtDur(unmerge.tot)],
-                      &[&FmtDur(unmerge.pred(sc.lim, sc.avg))]]);
+                      &[&FmtPred::new(sc, unmerge)]]);
+            if sc.histogram && !merge.vals.is_empty() {
+                tblp.row([&[&group], &[&"  ", &FmtHist(merge)], &[], &[], &[], &[], &[], &[]]);
+            }
+            for (ver, (merge, unmerge)) in ver_time.get(pkg).into_iter().flatten() {
+                tblp.row([&[&group],
+                          &[&"  ", ver],
+                          &[&gc.cnt, &merge.count],
+                          &[&FmtDur(merge.tot)],
+                          &[&FmtPred::new(sc, merge)],
+                          &[&gc.cnt, &unmerge.count],
+                          &[&FmtDur(unmerge.tot)],
+                          &[&FmtPred::new(sc, unmerge)]]);
+            }
+        }
+        if gc.showskip && !others.is_empty() {
+            let (mut mc, mut mt, mut uc, mut ut) = (0, 0, 0, 0);
+            for (_, (merge, unmerge)) in &others {
+                mc += merge.count;
+                mt += merge.tot;
+                uc += unmerge.count;
+                ut += unmerge.tot;
+            }
+            tblp.row([&[&group],
+                      &[&gc.skip, &"(", &others.len(), &" others)"],
+                      &[&gc.cnt, &mc],
+                      &[&FmtDur(mt)],
+                      &[],
+                      &[&gc.cnt, &uc],
+                      &[&FmtDur(ut)],
+                      &[]]);
         }
     }
     // Totals
@@ -333,16 +1235,78 @@ fn cmd_stats_group(gc: &Conf,
             unmerge_time += unmerge.tot;
             unmerge_count += unmerge.count;
         }
+        let daily = FmtDur((merge_time + unmerge_time) / days.max(1) as i64);
+        let saved = bin_saved(sc, src_time, bin_time);
+        let saved_cell: [&dyn Disp; 1] = [&FmtMaybeDur(saved)];
+        let (days_cell, daily_cell): ([&dyn Disp; 2], [&dyn Disp; 1]) =
+            ([&gc.cnt, &days], [&daily]);
+        // Only split source/binary merges if there are some binary ones
+        let src_merges = merge_count - bin_merges;
+        let (src_cell, bin_cell): ([&dyn Disp; 2], [&dyn Disp; 2]) =
+            ([&gc.cnt, &src_merges], [&gc.cnt, &bin_merges]);
+        let has_bin = bin_merges > 0;
+        // Merged packages that get a `?` prediction because all their durations were negative
+        let unpredicted = match sc.no_predict {
+            true => 0,
+            false => pkg_time.values()
+                             .filter(|(m, _)| m.count > 0 && m.pred(sc.lim, sc.avg).is_none())
+                             .count(),
+        };
+        let unpredicted_cell: [&dyn Disp; 2] = [&gc.cnt, &unpredicted];
         tblt.row([&[&group],
                   &[&gc.cnt, &merge_count],
+                  if has_bin { &src_cell } else { &[] },
+                  if has_bin { &bin_cell } else { &[] },
                   &[&FmtDur(merge_time)],
-                  &[&FmtDur(merge_time.checked_div(merge_count).unwrap_or(-1))],
+                  &[&FmtMaybeDur(merge_time.checked_div(merge_count))],
                   &[&gc.cnt, &unmerge_count],
                   &[&FmtDur(unmerge_time)],
-                  &[&FmtDur(unmerge_time.checked_div(unmerge_count).unwrap_or(-1))]]);
+                  &[&FmtMaybeDur(unmerge_time.checked_div(unmerge_count))],
+                  &[&FmtDur(wall_time(spans))],
+                  if sc.active_days { &days_cell } else { &[] },
+                  if sc.active_days { &daily_cell } else { &[] },
+                  if saved.is_some() { &saved_cell } else { &[] },
+                  if unpredicted > 0 { &unpredicted_cell } else { &[] }]);
     }
 }
 
+/// Estimated build time saved by binary merges
+///
+/// Each binary merge is compared to the predicted source build time of the same package. Packages
+/// without a source build in the log are skipped, returns None if there are none left.
+fn bin_saved(sc: &ConfStats,
+             src_time: &HashMap<String, Times>,
+             bin_time: &HashMap<String, Times>)
+             -> Option<i64> {
+    bin_time.iter()
+            .filter_map(|(pkg, bin)| {
+                let src = src_time.get(pkg)?.pred(sc.lim, sc.avg)?;
+                Some(src * bin.vals.len() as i64 - bin.tot)
+            })
+            .reduce(|a, b| a + b)
+}
+
+/// Sum of the (un)merge time spans, counting overlapping parts only once
+///
+/// Unlike the summed merge time, this doesn't count parallel merges multiple times, so it's a good
+/// approximation of the wall-clock time spent emerging.
+fn wall_time(spans: &mut [(i64, i64)]) -> i64 {
+    spans.sort_unstable();
+    let mut tot = 0;
+    let mut cur: Option<(i64, i64)> = None;
+    for &(start, stop) in spans.iter().filter(|(start, stop)| stop > start) {
+        match cur {
+            Some((s, e)) if start <= e => cur = Some((s, e.max(stop))),
+            Some((s, e)) => {
+                tot += e - s;
+                cur = Some((start, stop));
+            },
+            None => cur = Some((start, stop)),
+        }
+    }
+    tot + cur.map_or(0, |(s, e)| e - s)
+}
+
 /// Count processes in tree, including given proces
 fn proc_count(procs: &ProcList, pid: pid_t) -> usize {
     let mut count = 1;
@@ -354,7 +1318,7 @@ fn proc_count(procs: &ProcList, pid: pid_t) -> usize {
 
 /// Display proces tree
 fn proc_rows(now: i64,
-             tbl: &mut Table<3>,
+             tbl: &mut Table<4>,
              procs: &ProcList,
              pid: pid_t,
              depth: usize,
@@ -371,7 +1335,7 @@ fn proc_rows(now: i64,
     };
     // Print current level
     if depth < sc.pdepth {
-        tbl.row([&[&FmtProc(proc, depth, sc.pwidth)], &[&FmtDur(now - proc.start)], &[]]);
+        tbl.row([&[&FmtProc(proc, depth, sc.pwidth)], &[&FmtDur(now - proc.start)], &[], &[]]);
     }
     // Either recurse with children...
     if depth + 1 < sc.pdepth {
@@ -383,77 +1347,198 @@ fn proc_rows(now: i64,
     else if gc.showskip {
         let count = proc_count(procs, pid) - 1;
         if count > 0 {
-            tbl.skiprow(&[&"  ".repeat(depth + 1), &gc.skip, &"(skip ", &count, &" below)"]);
+            tbl.skiprow(&[&"  ".repeat(depth + 1), &FmtSkip("below", count)]);
         }
     }
 }
 
 /// Predict future merge time
 ///
+/// Complete the resume list with the merges that the current emerge is working on
+fn resume_pending(mut r: Vec<Pkg>, einfo: &EmergeInfo, started: &BTreeMap<Pkg, i64>) -> Vec<Pkg> {
+    // Plus emerge.log after main process start time, if we didn't see specific processes
+    if einfo.pkgs.is_empty() {
+        for (p, t) in started.iter() {
+            if *t > einfo.start && !r.contains(p) {
+                r.push(p.clone())
+            }
+        }
+    }
+    // Currently running merges always get a row, even if missing from the list
+    for p in einfo.pkgs.iter().rev() {
+        if !r.contains(p) {
+            r.insert(0, p.clone())
+        }
+    }
+    r
+}
+
 /// Very similar to cmd_summary except we want total build time for a list of ebuilds.
-pub fn cmd_predict(gc: Conf, mut sc: ConfPred) -> Result<bool, Error> {
+pub fn cmd_predict(gc: Conf, mut sc: ConfPred) -> Result<Status, Error> {
     let now = epoch_now();
     let last = if sc.show.tot { sc.last.saturating_add(1) } else { sc.last };
-    let mut tbl = Table::new(&gc).align_left(0).align_left(2).margin(2, " ").last(last);
+    let mut tbl = Table::new(&gc).align_left(0)
+                                 .align_left(2)
+                                 .align_left(3)
+                                 .margin(2, " ")
+                                 .margin(3, " ")
+                                 .last(last);
 
     // Gather and print info about current merge process.
-    let procs = get_all_proc(&mut sc.tmpdirs);
+    let procs = if has_procfs() {
+        get_all_proc(&mut sc.tmpdirs)
+    } else {
+        // Without /proc we can't tell if emerge is running, so use the resume list regardless
+        error!("/proc is unavailable, only predicting pretended or resumed merges");
+        if matches!(sc.resume, ResumeKind::Auto) {
+            sc.resume = ResumeKind::Either;
+        }
+        ProcList::new()
+    };
     let einfo = get_emerge(&procs);
+    let running = !einfo.roots.is_empty();
     if einfo.roots.is_empty()
+       && !sc.show.sync
+       && sc.pretend.is_empty()
+       && sc.plan_json.is_empty()
+       && sc.atoms.is_empty()
        && std::io::stdin().is_terminal()
        && matches!(sc.resume, ResumeKind::No | ResumeKind::Auto)
     {
-        tbl.row([&[&"No ongoing merge found"], &[], &[]]);
-        return Ok(false);
+        tbl.row([&[&"No ongoing merge found"], &[], &[], &[]]);
+        return Ok(Status::NotFound);
     }
     if sc.show.run {
-        for p in einfo.roots {
+        for &p in &einfo.roots {
             proc_rows(now, &mut tbl, &procs, p, 0, &gc, &sc);
         }
+        // A busy system builds slower than usual, predictions can't take that into account
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let load = if running { get_loadavg() } else { None };
+        if let Some(load) = load.filter(|&l| l > cpus as f64) {
+            tbl.row([&[&gc.skip,
+                       &"Load average ",
+                       &gc.cnt,
+                       &format!("{load:.2}"),
+                       &gc.skip,
+                       &" is higher than ",
+                       &gc.cnt,
+                       &cpus,
+                       &gc.skip,
+                       &" CPUs, merges may take longer than predicted"],
+                     &[],
+                     &[],
+                     &[]]);
+        }
     }
 
     // Parse emerge log.
-    let hist = get_hist(&gc.logfile, gc.from, gc.to, Show::m(), &vec![], false)?;
+    let show = Show { run: sc.show.sync, sync: sc.show.sync, ..Show::m() };
+    let hist = get_hist(&gc, show, &vec![], &vec![], false, false, None)?;
     let mut started: BTreeMap<Pkg, i64> = BTreeMap::new();
+    let mut stopped: BTreeMap<Pkg, i64> = BTreeMap::new();
     let mut times: HashMap<String, Times> = HashMap::new();
+    let (pred_min, pred_max) = resolve_range(&gc, sc.pred_from, sc.pred_to)?;
+    let mut sync_start: Option<i64> = None;
+    let mut sync_times: HashMap<String, Times> = HashMap::new();
+    // Repos synced by the latest emerge command that did a sync
+    let mut sync_repos: Vec<String> = vec![];
+    let mut new_run = false;
     for p in hist {
         match p {
             Hist::MergeStart { ts, .. } => {
                 started.insert(Pkg::new(p.ebuild(), p.version()), ts);
             },
             Hist::MergeStop { ts, .. } => {
-                if let Some(start_ts) = started.remove(&Pkg::new(p.ebuild(), p.version())) {
-                    let timevec = times.entry(p.ebuild().to_string()).or_insert(Times::new());
-                    timevec.insert(ts - start_ts);
+                let pkg = Pkg::new(p.ebuild(), p.version());
+                if let Some(start_ts) = started.remove(&pkg) {
+                    if pred_min <= ts && ts <= pred_max {
+                        let timevec = times.entry(p.ebuild().to_string()).or_insert(Times::new());
+                        timevec.insert(ts - start_ts);
+                    }
+                }
+                stopped.insert(pkg, ts);
+            },
+            Hist::RunStart { .. } => new_run = true,
+            Hist::SyncStart { ts } => {
+                // Some sync starts have multiple entries in old logs
+                sync_start = Some(ts);
+            },
+            Hist::SyncStop { ts, repo } => {
+                if let Some(start_ts) = sync_start.take() {
+                    sync_times.entry(repo.clone()).or_insert(Times::new()).insert(ts - start_ts);
+                }
+                if std::mem::take(&mut new_run) {
+                    sync_repos.clear();
+                }
+                if !sync_repos.contains(&repo) {
+                    sync_repos.push(repo);
                 }
             },
             _ => unreachable!("Should only receive Hist::{{Start,Stop}}"),
         }
     }
 
+    // Sync estimates, independent from the merge estimates
+    for repo in &sync_repos {
+        let pred = sync_times.get(repo).and_then(|t| t.pred(sc.lim, sc.avg));
+        tbl.row([&[&gc.clr, &"Sync ", repo], &[&FmtMaybeDur(pred)], &[], &[]]);
+    }
+
+    // Pretended merges that completed after the cutoff are not pending anymore
+    let since = match sc.pretend_since {
+        TimeBound::None => None,
+        b => Some(resolve_ts(&gc, b)?),
+    };
+    let merged = |p: &Pkg, cutoff: i64| {
+        let done = stopped.get(p).is_some_and(|&t| t >= cutoff);
+        if done {
+            debug!("Skipping {}: merged since {cutoff}", p.ebuild_version());
+        }
+        done
+    };
+
     // Build list of pending merges
-    let pkgs: Vec<Pkg> = if std::io::stdin().is_terminal() {
-        // From resume list
-        let mut r = get_resume(sc.resume);
-        // Plus specific emerge processes
-        for p in einfo.pkgs.iter() {
-            if !r.contains(p) {
-                r.push(p.clone())
+    let pkgs: Vec<Pkg> =
+        if !sc.pretend.is_empty() || !sc.plan_json.is_empty() || !sc.atoms.is_empty() {
+            // From the command line, portage's stdout or json plans saved in files, skipping duplicates
+            let mut r: Vec<Pkg> = vec![];
+            for p in sc.atoms.iter().map(|a| Pkg::from_atom(a)) {
+                if !r.contains(&p) {
+                    r.push(p)
+                }
             }
-        }
-        // Plus emerge.log after main process start time, if we didn't see specific processes
-        if einfo.pkgs.is_empty() {
-            for (p, t) in started.iter() {
-                if *t > einfo.start && !r.contains(p) {
-                    r.push(p.clone())
+            let files =
+                sc.pretend.iter().map(|f| (f, false)).chain(sc.plan_json.iter().map(|f| (f, true)));
+            for (f, json) in files {
+                let file = File::open(f).with_context(|| format!("Cannot open {f:?}"))?;
+                let cutoff = match since {
+                    Some(t) => t,
+                    None => file_mtime(&file).unwrap_or(i64::MAX),
+                };
+                let name = f.to_string_lossy();
+                let pkgs = if json {
+                    get_plan_json(file, &name).with_context(|| format!("Cannot parse {f:?}"))?
+                } else {
+                    get_pretend(file, &name)
+                };
+                for p in pkgs {
+                    if !r.contains(&p) && !merged(&p, cutoff) {
+                        r.push(p)
+                    }
                 }
             }
-        }
-        r
-    } else {
-        // From portage's stdout
-        get_pretend(stdin(), "STDIN")
-    };
+            r
+        } else if std::io::stdin().is_terminal() {
+            // From resume list
+            resume_pending(get_resume(sc.resume, sc.resume_backup), &einfo, &started)
+        } else {
+            // From portage's stdout
+            let cutoff = since.unwrap_or(einfo.start);
+            let mut r = get_pretend(stdin(), "STDIN");
+            r.retain(|p| !merged(p, cutoff));
+            r
+        };
     trace!("pending: {pkgs:?}");
 
     // Gather and print per-package and indivudual stats.
@@ -464,21 +1549,18 @@ pub fn cmd_predict(gc: Conf, mut sc: ConfPred) -> Result<bool, Error> {
     for p in pkgs {
         totcount += 1;
         // Find the elapsed time, if currently running
-        let elapsed = match started.remove(&p) {
-            Some(s) if einfo.pkgs.contains(&p) => now - s,
-            Some(s) if einfo.pkgs.is_empty() && s > einfo.start => now - s,
-            _ => 0,
-        };
+        let elapsed = started.remove(&p).map_or(0, |s| einfo.elapsed(&p, s, now));
 
         // Find the predicted time and adjust counters
-        let (fmtpred, pred) = match times.get(p.ebuild()) {
-            Some(tv) => {
-                let pred = tv.pred(sc.lim, sc.avg);
-                (pred, pred)
+        let tv = times.get(p.ebuild());
+        let (fmtpred, pred, few) = match tv.and_then(|tv| tv.pred(sc.lim, sc.avg)) {
+            Some(pred) => {
+                let pred = (pred as f64 * sc.scale).round() as i64;
+                (Some(pred), pred, tv.is_some_and(|tv| tv.few(sc.lim, sc.min_samples)))
             },
             None => {
                 totunknown += 1;
-                (-1, sc.unknown)
+                (None, sc.unknown, false)
             },
         };
         totpredict += std::cmp::max(0, pred - elapsed);
@@ -486,19 +1568,26 @@ pub fn cmd_predict(gc: Conf, mut sc: ConfPred) -> Result<bool, Error> {
 
         // Done
         if sc.show.merge && totcount <= sc.first {
+            let finish = FmtDate(now + totpredict);
+            let finish: [&dyn Disp; 3] = [&"@ ", &gc.dur, &finish];
+            let finish: &[&dyn Disp] = if sc.finish_times { &finish } else { &[] };
             if elapsed > 0 {
                 let stage = get_buildlog(&p, &sc.tmpdirs).unwrap_or_default();
-                tbl.row([&[&gc.pkg, &p.ebuild_version()],
-                         &[&FmtDur(fmtpred)],
-                         &[&gc.clr, &"- ", &FmtDur(elapsed), &gc.clr, &stage]]);
+                tbl.row([&[&FmtPkg(&gc.pkg, p.ebuild_version())],
+                         &[&FmtMaybeDur(fmtpred), &FmtFew(few)],
+                         &[&gc.clr, &"- ", &FmtElapsed(elapsed, fmtpred), &gc.clr, &stage],
+                         finish]);
             } else {
-                tbl.row([&[&gc.pkg, &p.ebuild_version()], &[&FmtDur(fmtpred)], &[]]);
+                tbl.row([&[&FmtPkg(&gc.pkg, p.ebuild_version())],
+                         &[&FmtMaybeDur(fmtpred), &FmtFew(few)],
+                         &[],
+                         finish]);
             }
         }
     }
     let lastskip = totcount.saturating_sub(sc.first);
     if sc.show.merge && gc.showskip && lastskip > 0 {
-        tbl.skiprow(&[&gc.skip, &"(skip last ", &lastskip, &")"]);
+        tbl.skiprow(&[&FmtSkip("last", lastskip)]);
     }
     // Print summary line
     if totcount > 0 {
@@ -515,21 +1604,52 @@ pub fn cmd_predict(gc: Conf, mut sc: ConfPred) -> Result<bool, Error> {
             if totelapsed > 0 {
                 s.extend::<[&dyn Disp; 4]>([&", ", &e, &gc.clr, &" elapsed"]);
             }
-            tbl.row([&s,
-                     &[&FmtDur(totpredict), &gc.clr],
-                     &[&"@ ", &gc.dur, &FmtDate(now + totpredict)]]);
+            // Align the global finish time with the per-merge ones
+            let finish = FmtDate(now + totpredict);
+            let finish: &[&dyn Disp] = &[&"@ ", &gc.dur, &finish];
+            if sc.finish_times {
+                tbl.row([&s, &[&FmtDur(totpredict), &gc.clr], &[], finish]);
+            } else {
+                tbl.row([&s, &[&FmtDur(totpredict), &gc.clr], finish, &[]]);
+            }
         }
-    } else {
-        tbl.row([&[&"No pretended merge found"], &[], &[]]);
+    } else if sync_repos.is_empty() {
+        let msg = if running {
+            "No pending merge found, emerge is running"
+        } else {
+            "No pretended merge found"
+        };
+        tbl.row([&[&msg], &[], &[], &[]]);
+    }
+    if sc.status_code && running {
+        return Ok(Status::Running);
+    }
+    Ok(Status::from(totcount > 0 || !sync_repos.is_empty()))
+}
+
+/// Elapsed time, colored according to whether it overruns the prediction
+struct FmtElapsed(i64, Option<i64>);
+impl Disp for FmtElapsed {
+    fn out(&self, buf: &mut Vec<u8>, conf: &Conf) -> usize {
+        let color = match self.1 {
+            None => conf.dur.val,
+            Some(p) if self.0 <= p => conf.in_time.val,
+            Some(_) => conf.overtime.val,
+        };
+        FmtDur(self.0).out_color(buf, conf, color)
     }
-    Ok(totcount > 0)
 }
 
 pub fn cmd_accuracy(gc: Conf, sc: ConfAccuracy) -> Result<bool, Error> {
-    let hist = get_hist(&gc.logfile, gc.from, gc.to, Show::m(), &sc.search, sc.exact)?;
+    let show = Show { sync: sc.show.sync, ..Show::m() };
+    let hist = get_hist(&gc, show, &sc.search, &vec![], sc.exact, sc.invert, None)?;
     let mut pkg_starts: HashMap<String, i64> = HashMap::new();
     let mut pkg_times: BTreeMap<String, Times> = BTreeMap::new();
     let mut pkg_errs: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    let mut sync_start: Option<i64> = None;
+    let mut sync_times: BTreeMap<String, Times> = BTreeMap::new();
+    let mut sync_errs: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    let mut window = Window::default();
     let mut found = false;
     let h = ["Date", "Package", "Real", "Predicted", "Error"];
     let mut tbl = Table::new(&gc).align_left(0).align_left(1).last(sc.last).header(h);
@@ -545,20 +1665,21 @@ pub fn cmd_accuracy(gc: Conf, sc: ConfAccuracy) -> Result<bool, Error> {
                     let times = pkg_times.entry(p.ebuild().to_owned()).or_insert(Times::new());
                     let real = ts - start;
                     match times.pred(sc.lim, sc.avg) {
-                        -1 => {
+                        None => {
                             if sc.show.merge {
                                 tbl.row([&[&FmtDate(ts)],
-                                         &[&gc.merge, &p.ebuild_version()],
+                                         &[&FmtPkg(&gc.merge, p.ebuild_version())],
                                          &[&FmtDur(real)],
                                          &[],
                                          &[]])
                             }
                         },
-                        pred => {
+                        Some(pred) => {
+                            window.add(times, sc.lim);
                             let err = (pred - real).abs() as f64 * 100.0 / real as f64;
                             if sc.show.merge {
                                 tbl.row([&[&FmtDate(ts)],
-                                         &[&gc.merge, &p.ebuild_version()],
+                                         &[&FmtPkg(&gc.merge, p.ebuild_version())],
                                          &[&FmtDur(real)],
                                          &[&FmtDur(pred)],
                                          &[&gc.cnt, &format!("{err:.1}%")]])
@@ -570,15 +1691,49 @@ pub fn cmd_accuracy(gc: Conf, sc: ConfAccuracy) -> Result<bool, Error> {
                     times.insert(real);
                 }
             },
+            Hist::SyncStart { ts } => {
+                // Some sync starts have multiple entries in old logs
+                sync_start = Some(ts);
+            },
+            Hist::SyncStop { ts, repo } => {
+                found = true;
+                if let Some(start) = sync_start.take() {
+                    let times = sync_times.entry(repo.clone()).or_insert(Times::new());
+                    let real = ts - start;
+                    let pred = times.pred(sc.lim, sc.avg);
+                    let err = pred.map(|p| (p - real).abs() as f64 * 100.0 / real.max(1) as f64);
+                    if sc.show.sync {
+                        let fmt = (pred.map(FmtDur), err.map(|e| format!("{e:.1}%")));
+                        let (p, e): (&[&dyn Disp], &[&dyn Disp]) = match &fmt {
+                            (Some(p), Some(e)) => (&[p], &[&gc.cnt, e]),
+                            _ => (&[], &[]),
+                        };
+                        tbl.row([&[&FmtDate(ts)],
+                                 &[&gc.clr, &"Sync ", &repo],
+                                 &[&FmtDur(real)],
+                                 p,
+                                 e]);
+                    }
+                    if let Some(err) = err {
+                        sync_errs.entry(repo).or_default().push(err);
+                    }
+                    times.insert(real);
+                }
+            },
             e => panic!("Unexpected {e:?}"),
         }
     }
     drop(tbl);
+    window.log(sc.lim);
     if sc.show.tot {
         let mut tbl = Table::new(&gc).align_left(0).header(["Package", "Error"]);
+        for (r, e) in sync_errs {
+            let avg = e.iter().sum::<f64>() / e.len() as f64;
+            tbl.row([&[&gc.clr, &"Sync ", &r], &[&gc.cnt, &format!("{avg:.1}%")]]);
+        }
         for (p, e) in pkg_errs {
             let avg = e.iter().sum::<f64>() / e.len() as f64;
-            tbl.row([&[&gc.pkg, &p], &[&gc.cnt, &format!("{avg:.1}%")]]);
+            tbl.row([&[&FmtPkg(&gc.pkg, &p)], &[&gc.cnt, &format!("{avg:.1}%")]]);
         }
     }
     Ok(found)
@@ -590,18 +1745,21 @@ pub fn cmd_complete(gc: Conf, sc: ConfComplete) -> Result<bool, Error> {
     if let Some(s) = &sc.shell {
         let mut cli = build_cli();
         let shell = clap_complete::Shell::from_str(s).expect("Unsupported shell");
-        clap_complete::generate(shell, &mut cli, "emlop", &mut std::io::stdout());
+        let mut buf = vec![];
+        clap_complete::generate(shell, &mut cli, "emlop", &mut buf);
+        print!("{}", complete_dynamic(shell, String::from_utf8(buf)?));
         return Ok(true);
     }
     // Look for (un)merged matching packages in the log and print each once
     let term: Vec<_> = sc.pkg.iter().cloned().collect();
-    let hist = get_hist(&gc.logfile, gc.from, gc.to, Show::m(), &term, false)?;
+    let hist = get_hist(&gc, Show::m(), &term, &vec![], false, false, None)?;
     let mut pkgs: HashSet<String> = HashSet::new();
+    let sep = if sc.null { '\0' } else { '\n' };
     for p in hist {
         if let Hist::MergeStart { .. } = p {
             let e = p.ebuild();
             if !pkgs.contains(e) {
-                println!("{}", e);
+                print!("{e}{sep}");
                 pkgs.insert(e.to_string());
             }
         }
@@ -609,18 +1767,97 @@ pub fn cmd_complete(gc: Conf, sc: ConfComplete) -> Result<bool, Error> {
     Ok(true)
 }
 
+/// Patch generated completion script to complete `<search>` using `emlop complete`
+#[cfg(feature = "clap_complete")]
+fn complete_dynamic(shell: clap_complete::Shell, script: String) -> String {
+    use clap_complete::Shell;
+    match shell {
+        Shell::Bash => {
+            // Options before the command whose value is a separate word, like `-F <file>`
+            let cli = build_cli();
+            let opts: Vec<String> =
+                cli.get_arguments()
+                   .filter(|a| a.get_action().takes_values() && !a.is_positional())
+                   .flat_map(|a| {
+                       let s = a.get_short().map(|s| format!("-{s}"));
+                       let l = a.get_long().map(|l| format!("--{l}"));
+                       s.into_iter().chain(l)
+                   })
+                   .collect();
+            let f = r#"
+_emlop_dynamic() {
+    _emlop "$@"
+    local cur="${COMP_WORDS[COMP_CWORD]}" w skip=0
+    [[ ${cur} == -* || ${COMP_WORDS[COMP_CWORD-1]} == -* ]] && return 0
+    for w in "${COMP_WORDS[@]:1:COMP_CWORD-1}"; do
+        if (( skip )); then
+            # `--opt=val` is split into three words
+            [[ ${w} == = ]] || skip=0
+            continue
+        fi
+        case "${w}" in
+            OPTS) skip=1;;
+            -*) ;;
+            l*|s*|a*) COMPREPLY+=($(emlop complete -- "${cur}" 2>/dev/null)); return 0;;
+            *) return 0;;
+        esac
+    done
+}
+"#;
+            let f = f.replace("OPTS", &opts.join("|"));
+            script.replace("complete -F _emlop ", "complete -F _emlop_dynamic ")
+                  .replacen("\nif [[ ", &format!("{f}\nif [[ "), 1)
+        },
+        Shell::Zsh => {
+            let f = "(( $+functions[_emlop_pkgs] )) ||\n\
+                     _emlop_pkgs() {\n    \
+                     compadd -- $(emlop complete -- \"$PREFIX\" 2>/dev/null)\n\
+                     }\n\n";
+            script.replace("<search>:' \\", "<search>:_emlop_pkgs' \\")
+                  .replacen("if [ \"$funcstack[1]\"", &format!("{f}if [ \"$funcstack[1]\""), 1)
+        },
+        Shell::Fish => {
+            script
+            + "complete -c emlop -n \"__fish_seen_subcommand_from log stats accuracy\" -f \
+               -a \"(emlop complete -- (commandline -ct) 2>/dev/null)\"\n"
+        },
+        _ => script,
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::parse::procs;
 
+    #[test]
+    fn prometheus_escape() {
+        for (s, e) in [("dev-lang/rust", "dev-lang/rust"),
+                       ("a\"b", "a\\\"b"),
+                       ("a\\b", "a\\\\b"),
+                       ("a\nb", "a\\nb")]
+        {
+            assert_eq!(prom_escape(s), e);
+        }
+    }
+
+    #[test]
+    fn csv_escaping() {
+        for (s, e) in [("dev-lang/rust", "dev-lang/rust"),
+                       ("a,b", "\"a,b\""),
+                       ("a\"b", "\"a\"\"b\""),
+                       ("a\nb", "\"a\nb\"")]
+        {
+            assert_eq!(csv_escape(s), e);
+        }
+    }
+
     #[test]
     fn averages() {
         use crate::Average::*;
         for (a, m, wa, wm, lim, vals) in
-            [(-1, -1, -1, -1, 10, vec![]),
-             (1, 1, 1, 1, 10, vec![1]),
+            [(1, 1, 1, 1, 10, vec![1]),
              (12 / 2, 6, 21 / 3, 10, 10, vec![2, 10]),
              (12 / 2, 6, 14 / 3, 2, 10, vec![10, 2]),
              (15 / 3, 4, (1 + 20 + 12) / (1 + 2 + 3), 4, 10, vec![1, 10, 4]),
@@ -631,10 +1868,46 @@ mod tests {
             for &v in vals.iter() {
                 t.insert(v);
             }
-            assert_eq!(a, t.pred(lim, Arith), "arith {lim} {vals:?}");
-            assert_eq!(m, t.pred(lim, Median), "median {lim} {vals:?}");
-            assert_eq!(wa, t.pred(lim, WeightedArith), "weighted arith {lim} {vals:?}");
-            assert_eq!(wm, t.pred(lim, WeightedMedian), "weighted median {lim} {vals:?}");
+            assert_eq!(Some(a), t.pred(lim, Arith), "arith {lim} {vals:?}");
+            assert_eq!(Some(m), t.pred(lim, Median), "median {lim} {vals:?}");
+            assert_eq!(Some(wa), t.pred(lim, WeightedArith), "weighted arith {lim} {vals:?}");
+            assert_eq!(Some(wm), t.pred(lim, WeightedMedian), "weighted median {lim} {vals:?}");
+        }
+        // Percentiles, most recent values first: 1, 2, ..., 20
+        let mut t = Times::new();
+        for v in (1..=20).rev() {
+            t.insert(v);
+        }
+        for (p, lim, res) in [(1, 20, 1),
+                              (50, 20, 10),
+                              (90, 20, 18),
+                              (95, 20, 19),
+                              (100, 20, 20),
+                              (90, 5, 5),
+                              (95, 1, 1)]
+        {
+            assert_eq!(Some(res), t.pred(lim, Percentile(p)), "p{p} {lim}");
+        }
+        // No data, including when all durations were negative
+        let mut neg = Times::new();
+        neg.insert(-5);
+        for avg in [Arith, Median, WeightedArith, WeightedMedian, Percentile(90)] {
+            assert_eq!(None, Times::new().pred(10, avg));
+            assert_eq!(None, neg.pred(10, avg));
+        }
+    }
+
+    #[test]
+    fn wall_times() {
+        for (w, mut spans) in [(0, vec![]),
+                               (10, vec![(0, 10)]),
+                               (0, vec![(10, 0)]),
+                               (15, vec![(5, 15), (0, 10)]),
+                               (10, vec![(0, 10), (2, 4)]),
+                               (15, vec![(10, 20), (0, 5)]),
+                               (20, vec![(0, 10), (10, 20), (15, 1)])]
+        {
+            assert_eq!(w, wall_time(&mut spans), "{spans:?}");
         }
     }
 
@@ -679,4 +1952,51 @@ mod tests {
         proc_rows(10, &mut tbl, &procs, 1, 0, &gc, &sc);
         assert_eq!(tbl.to_string(), out);
     }
+
+    /// Running merges and merges started by the current emerge complete the resume list
+    #[test]
+    fn resume_running() {
+        let pkg = Pkg::from_atom;
+        let pkgs = |v: &[&str]| v.iter().map(|s| pkg(s)).collect::<Vec<_>>();
+        let started = BTreeMap::from([(pkg("sys-devel/gcc-13.2.0"), 20),
+                                      (pkg("app-shells/bash-5.2_p15"), 5)]);
+        let running =
+            get_emerge(&procs(&[(ProcKind::Emerge, "emerge -u world", 10, 1),
+                                (ProcKind::Python,
+                                 "sandbox [dev-lang/rust-1.65.0] sandbox ebuild.sh compile",
+                                 11,
+                                 10)]));
+        let idle = get_emerge(&procs(&[(ProcKind::Emerge, "emerge -u world", 10, 1)]));
+        for (list, einfo, exp) in [(&["app-portage/emlop-0.5.0"][..],
+                                    &running,
+                                    &["dev-lang/rust-1.65.0", "app-portage/emlop-0.5.0"][..]),
+                                   (&["app-portage/emlop-0.5.0", "dev-lang/rust-1.65.0"],
+                                    &running,
+                                    &["app-portage/emlop-0.5.0", "dev-lang/rust-1.65.0"]),
+                                   (&[], &running, &["dev-lang/rust-1.65.0"]),
+                                   (&["app-portage/emlop-0.5.0"],
+                                    &idle,
+                                    &["app-portage/emlop-0.5.0", "sys-devel/gcc-13.2.0"])]
+        {
+            assert_eq!(resume_pending(pkgs(list), einfo, &started), pkgs(exp), "{list:?}");
+        }
+    }
+
+    #[test]
+    fn elapsed_color() {
+        let gc = Conf::from_str("emlop p --color=y --theme-preset=light");
+        for (elapsed, pred, exp) in [(10, Some(20), "\x1B[0;32m10"),
+                                     (20, Some(20), "\x1B[0;32m20"),
+                                     (30, Some(20), "\x1B[0;31m30"),
+                                     (30, None, "\x1B[0;35m30")]
+        {
+            let mut buf = vec![];
+            assert_eq!(FmtElapsed(elapsed, pred).out(&mut buf, &gc), 2);
+            assert_eq!(String::from_utf8(buf).unwrap(), exp);
+        }
+        let gc = Conf::from_str("emlop p --color=n");
+        let mut buf = vec![];
+        FmtElapsed(30, Some(20)).out(&mut buf, &gc);
+        assert_eq!(String::from_utf8(buf).unwrap(), "30");
+    }
 }
@@ -6,11 +6,17 @@ mod toml;
 mod types;
 
 pub use crate::config::{cli::*, types::*};
-use crate::{config::toml::Toml, parse::AnsiStr, *};
+use crate::{config::toml::Toml,
+            parse::{is_version, AnsiStr},
+            *};
+use anyhow::{ensure, Context};
 use clap::ArgMatches;
-use std::{io::IsTerminal, path::PathBuf};
+use regex::Regex;
+use std::{io::IsTerminal,
+          path::{Path, PathBuf}};
 
 /// Global config, one enum variant per command
+#[derive(Debug)]
 pub enum Configs {
     Log(Conf, ConfLog),
     Stats(Conf, ConfStats),
@@ -21,71 +27,148 @@ pub enum Configs {
 /// Common config
 ///
 /// Using raw `set/clear` ANSI colors instead of some `paint()` method to simplify alignment.
+#[derive(Debug)]
 pub struct Conf {
     pub pkg: AnsiStr,
     pub merge: AnsiStr,
+    pub binmerge: AnsiStr,
     pub unmerge: AnsiStr,
     pub dur: AnsiStr,
     pub cnt: AnsiStr,
+    pub in_time: AnsiStr,
+    pub overtime: AnsiStr,
     pub skip: AnsiStr,
     pub clr: AnsiStr,
     pub lineend: &'static [u8],
+    pub color_category: bool,
     pub header: bool,
+    pub header_repeat: bool,
     pub showskip: bool,
+    pub skip_fmt: Option<String>,
     pub dur_t: DurationStyle,
-    pub date_offset: time::UtcOffset,
+    pub dur_parts: usize,
+    pub tz: Tz,
     pub date_fmt: DateStyle,
     pub out: OutStyle,
+    pub pager: bool,
+    pub progress: bool,
+    pub outfile: Option<PathBuf>,
+    /// Print the resolved configuration instead of running the command
+    pub dump_config: bool,
     pub logfile: String,
+    pub run_re: Option<Regex>,
+    pub staleness_check: bool,
     pub from: TimeBound,
     pub to: TimeBound,
+    pub to_exclusive: bool,
 }
+#[derive(Debug)]
 pub struct ConfLog {
     pub show: Show,
     pub search: Vec<String>,
+    pub repo: Vec<String>,
     pub exact: bool,
+    pub invert: bool,
     pub starttime: bool,
+    pub downgrades: bool,
+    pub coalesce_updates: bool,
+    pub target_root: bool,
+    pub line_buffered: bool,
+    pub json_lines: bool,
+    pub sort: LogSort,
     pub first: usize,
     pub last: usize,
+    pub tail: Option<usize>,
+    pub every: usize,
+    pub skip: usize,
+    pub since_version: Option<String>,
+    pub no_reinstalls: bool,
+    pub installed_only: bool,
+    pub binary: BinFilter,
 }
+#[derive(Debug)]
 pub struct ConfPred {
     pub show: Show,
     pub avg: Average,
     pub first: usize,
     pub last: usize,
     pub lim: u16,
+    pub min_samples: usize,
+    pub scale: f64,
     pub resume: ResumeKind,
+    pub resume_backup: bool,
     pub unknown: i64,
     pub tmpdirs: Vec<PathBuf>,
+    pub pretend: Vec<PathBuf>,
+    pub plan_json: Vec<PathBuf>,
+    pub atoms: Vec<String>,
+    pub pretend_since: TimeBound,
+    pub pred_from: TimeBound,
+    pub pred_to: TimeBound,
     pub pwidth: usize,
     pub pdepth: usize,
+    pub finish_times: bool,
+    pub status_code: bool,
 }
+#[derive(Debug)]
 pub struct ConfStats {
     pub show: Show,
     pub search: Vec<String>,
+    pub repo: Vec<String>,
     pub exact: bool,
+    pub invert: bool,
+    pub preserved: bool,
+    pub no_reinstalls: bool,
+    pub installed_only: bool,
+    pub binary: BinFilter,
+    pub by_version: bool,
+    pub histogram: bool,
+    pub clock: bool,
+    pub overview: bool,
+    pub count: bool,
+    pub active_days: bool,
+    pub compare: Option<[(TimeBound, TimeBound); 2]>,
+    pub csv_series: bool,
+    pub when: Option<When>,
+    pub top: usize,
+    pub first: usize,
+    pub last: usize,
+    pub no_predict: bool,
     pub avg: Average,
     pub lim: u16,
+    pub min_samples: usize,
     pub group: Timespan,
+    pub relative_groups: bool,
+    pub group_regex: Option<Regex>,
+    pub live: bool,
 }
+#[derive(Debug)]
 pub struct ConfAccuracy {
     pub show: Show,
     pub search: Vec<String>,
     pub exact: bool,
+    pub invert: bool,
     pub avg: Average,
     pub last: usize,
     pub lim: u16,
 }
+#[derive(Debug)]
 pub struct ConfComplete {
     #[cfg(feature = "clap_complete")]
     pub shell: Option<String>,
     pub pkg: Option<String>,
+    pub null: bool,
 }
 
 impl Configs {
     pub fn load() -> Result<Configs, Error> {
         let cli = cli::build_cli().get_matches();
+        if cli.get_flag("helpall") {
+            print_help_all();
+            std::process::exit(0)
+        }
         let level = match cli.get_count("verbose") {
+            _ if cli.get_flag("quiet") => LevelFilter::Off,
             0 => LevelFilter::Error,
             1 => LevelFilter::Warn,
             2 => LevelFilter::Info,
@@ -97,14 +180,58 @@ impl Configs {
         let toml = Toml::load()?;
         trace!("{:?}", toml);
         let conf = Conf::try_new(&cli, &toml)?;
-        Ok(match cli.subcommand() {
-            Some(("log", sub)) => Self::Log(conf, ConfLog::try_new(sub, &toml)?),
-            Some(("stats", sub)) => Self::Stats(conf, ConfStats::try_new(sub, &toml)?),
-            Some(("predict", sub)) => Self::Predict(conf, ConfPred::try_new(sub, &toml)?),
+        if conf.out == OutStyle::Prometheus
+           && !matches!(cli.subcommand_name(), Some("stats") | None)
+        {
+            let msg = "Only available for the stats command";
+            return Err(ArgError::new("prometheus", "--output").msg(msg).into());
+        }
+        let confs = match cli.subcommand() {
+            Some(("log", sub)) => {
+                let sc = ConfLog::try_new(sub, &toml)?;
+                // Progress would get mixed with rows streamed by the main thread
+                let progress = conf.progress && !sc.line_buffered && !sc.json_lines;
+                Self::Log(Conf { progress, ..conf }, sc)
+            },
+            Some(("stats", sub)) => {
+                let sc = ConfStats::try_new(sub, &toml, &conf.tz)?;
+                if conf.out == OutStyle::Prometheus
+                   && (!matches!(sc.group, Timespan::None) || sc.compare.is_some() || sc.csv_series)
+                {
+                    let msg = "Cannot be used with --groupby, --compare or --csv-series";
+                    return Err(ArgError::new("prometheus", "--output").msg(msg).into());
+                }
+                Self::Stats(conf, sc)
+            },
+            Some(("predict", sub)) => {
+                let sc = ConfPred::try_new(sub, &toml, &conf.tz)?;
+                Self::Predict(conf, sc)
+            },
             Some(("accuracy", sub)) => Self::Accuracy(conf, ConfAccuracy::try_new(sub, &toml)?),
             Some(("complete", sub)) => Self::Complete(conf, ConfComplete::try_new(sub)?),
-            _ => unreachable!("clap should have exited already"),
-        })
+            Some(_) => unreachable!("clap should have exited already"),
+            None => {
+                // Not using clap's `subcommand_required()`, so that `--help-all` works
+                use clap::error::{ContextKind, ContextValue, ErrorKind};
+                let mut cmd = build_cli();
+                let subs = cmd.get_subcommands().map(|s| s.get_name().to_owned()).collect();
+                let mut e = clap::Error::new(ErrorKind::MissingSubcommand).with_cmd(&cmd);
+                e.insert(ContextKind::InvalidSubcommand, ContextValue::String("emlop".into()));
+                e.insert(ContextKind::ValidSubcommand, ContextValue::Strings(subs));
+                e.insert(ContextKind::Usage, ContextValue::StyledStr(cmd.render_usage()));
+                return Err(e.into());
+            },
+        };
+        Ok(confs)
+    }
+    pub const fn conf(&self) -> &Conf {
+        match self {
+            Self::Log(gc, _)
+            | Self::Stats(gc, _)
+            | Self::Predict(gc, _)
+            | Self::Accuracy(gc, _)
+            | Self::Complete(gc, _) => gc,
+        }
     }
 }
 
@@ -146,32 +273,212 @@ macro_rules! sel {
     }
 }
 
+/// Search terms from the command line and from `--pkg-file`
+///
+/// File entries are exact package names, so they get escaped and anchored for regex searches.
+fn search_terms(cli: &ArgMatches) -> Result<Vec<String>, Error> {
+    let mut terms = search_sets(cli, "/etc/portage/sets")?;
+    let exact = cli.get_flag("exact");
+    for f in cli.get_many::<PathBuf>("pkg_file").unwrap_or_default() {
+        let s = std::fs::read_to_string(f).with_context(|| format!("Cannot open {f:?}"))?;
+        ensure!(push_pkgs(&mut terms, exact, &s) > 0, "No package found in {f:?}");
+    }
+    Ok(terms)
+}
+
+/// Search terms from the command line, with `@set` terms replaced by the set's packages
+fn search_sets(cli: &ArgMatches, setdir: &str) -> Result<Vec<String>, Error> {
+    let exact = cli.get_flag("exact");
+    let glob = cli.get_flag("glob");
+    let mut terms = vec![];
+    for t in cli.get_many::<String>("search").unwrap_or_default() {
+        match t.strip_prefix('@') {
+            Some(set) => {
+                let f = Path::new(setdir).join(set);
+                let s = std::fs::read_to_string(&f).with_context(|| format!("Unknown set {t}"))?;
+                ensure!(push_pkgs(&mut terms, exact, &s) > 0, "No package found in set {t}");
+            },
+            None if glob => terms.push(glob_to_regex(t)),
+            None => terms.push(t.clone()),
+        }
+    }
+    Ok(terms)
+}
+
+/// Repo search terms from the command line
+fn repo_terms(cli: &ArgMatches) -> Vec<String> {
+    let glob = cli.get_flag("glob");
+    cli.get_many::<String>("repo")
+       .unwrap_or_default()
+       .map(|t| if glob { glob_to_regex(t) } else { t.clone() })
+       .collect()
+}
+
+/// Translate a shell glob into an anchored regex
+///
+/// Like `--exact`, a glob without a `/` is matched against the package name only. `*` and `?`
+/// don't match `/`, `[...]` and `[!...]` are character classes.
+fn glob_to_regex(glob: &str) -> String {
+    let mut re = String::from(if glob.contains('/') { "^" } else { "(^|/)" });
+    let mut chars = glob.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            '[' => {
+                let class: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                let (neg, class) = match class.strip_prefix('!') {
+                    Some(class) => ("^", class),
+                    None => ("", class.as_str()),
+                };
+                // Keep `-` unescaped for ranges
+                let class: Vec<_> = class.split('-').map(regex::escape).collect();
+                re.push_str(&format!("[{neg}{}]", class.join("-")));
+            },
+            c => re.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    re.push('$');
+    re
+}
+
+/// Add the packages listed in a pkg/set file to the search terms, returns the number added
+///
+/// Atoms like `>=cat/pkg-1.0:slot` are reduced to `cat/pkg`.
+fn push_pkgs(terms: &mut Vec<String>, exact: bool, s: &str) -> usize {
+    let n = terms.len();
+    let re_ver = Regex::new("-[0-9][^/]*$").unwrap();
+    for l in s.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        let atom = l.trim_start_matches(['<', '>', '=', '~', '!']);
+        let atom = atom.split([':', '[']).next().unwrap_or_default();
+        let l = if atom.len() < l.len() { re_ver.replace(atom, "") } else { atom.into() };
+        terms.push(match (exact, l.contains('/')) {
+                       (true, _) => l.into_owned(),
+                       (false, true) => format!("^{}$", regex::escape(&l)),
+                       (false, false) => format!("(^|/){}$", regex::escape(&l)),
+                   });
+    }
+    terms.len() - n
+}
+
+fn bin_filter(cli: &ArgMatches) -> Result<BinFilter, ArgError> {
+    cli.get_one("binary").map_or(Ok(BinFilter::Both), |b| BinFilter::parse(b, (), "--binary"))
+}
+
+/// Parse `--compare <from>..<to> <from>..<to>`, an empty bound is open-ended
+fn compare_ranges(cli: &ArgMatches,
+                  tz: &Tz)
+                  -> Result<Option<[(TimeBound, TimeBound); 2]>, ArgError> {
+    let Some(vals) = cli.get_many::<String>("compare") else { return Ok(None) };
+    let bound = |b: &str| match b.trim() {
+        "" => Ok(TimeBound::None),
+        b => TimeBound::parse(&b.to_owned(), tz, "--compare"),
+    };
+    let range = |r: &String| match r.split_once("..") {
+        Some((from, to)) => Ok((bound(from)?, bound(to)?)),
+        None => Err(ArgError::new(r, "--compare").msg("Ranges should look like <date>..<date>")),
+    };
+    let ranges = vals.map(range).collect::<Result<Vec<_>, _>>()?;
+    Ok(Some([ranges[0], ranges[1]]))
+}
+
+/// Resolve `--color=auto` using the environment
+///
+/// An explicit `--color` or config value takes precedence. Then a non-empty `NO_COLOR` disables
+/// color, a non-empty `CLICOLOR_FORCE` other than "0" enables it, and `CLICOLOR=0` disables it.
+/// Otherwise color is enabled if stdout is a terminal.
+fn color_env(isterm: bool, var: impl Fn(&str) -> Option<String>) -> bool {
+    if var("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        false
+    } else if var("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+        true
+    } else if var("CLICOLOR").is_some_and(|v| v == "0") {
+        false
+    } else {
+        isterm
+    }
+}
+
 impl Conf {
     pub fn try_new(cli: &ArgMatches, toml: &Toml) -> Result<Self, Error> {
-        let isterm = std::io::stdout().is_terminal();
-        let color = sel!(cli, toml, color, isterm, isterm)?;
+        let outfile = cli.get_one::<PathBuf>("outfile").cloned();
+        let isterm = outfile.is_none() && std::io::stdout().is_terminal();
+        let autocolor = color_env(isterm, |k| std::env::var(k).ok());
         let outdef = if isterm { OutStyle::Columns } else { OutStyle::Tab };
-        let offset = get_offset(sel!(cli, toml, utc, (), false)?);
+        let out = sel!(cli, toml, output, isterm, outdef)?;
+        // Tsv is meant for machine parsing, it never gets colors
+        let theme = match sel!(cli, toml, color, autocolor, autocolor)? && out != OutStyle::Tsv {
+            true => sel!(cli, toml, theme_preset, (), Theme::Dark)?,
+            false => Theme::None,
+        };
+        let color = theme != Theme::None;
+        let [pkg, merge, binmerge, unmerge, dur, skip, cnt, in_time, overtime] = theme.colors();
+        // `--utc` is a deprecated alias for `--tz=utc`, a command-line `--utc` wins over the config
+        let tz = if cli.get_one::<String>("tz").is_none() && cli.get_one::<String>("utc").is_some()
+        {
+            Tz::new(sel!(cli, toml, utc, (), false)?)
+        } else {
+            sel!(cli, toml, tz, (), Tz::new(sel!(cli, toml, utc, (), false)?))?
+        };
+        let run_re = match sel!(cli, toml, run_regex, (), String::new())? {
+            r if r.is_empty() => None,
+            r => Some(Regex::new(&r).map_err(|e| ArgError::new(r, "--run-regex").msg(e))?),
+        };
+        let pager = outfile.is_none()
+                    && match sel!(cli, toml, pager, (), PagerStyle::Never)? {
+                        PagerStyle::Always => true,
+                        PagerStyle::Never => false,
+                        PagerStyle::Auto => isterm,
+                    };
+        // Progress would get mixed with machine-readable output or with the pager's screen
+        let progress = sel!(cli, toml, progress, (), false)?
+                       && out == OutStyle::Columns
+                       && !pager
+                       && std::io::stderr().is_terminal();
+        let header_repeat =
+            matches!(cli.get_one::<String>("header").map(String::as_str), Some("repeat" | "r"));
         Ok(Self { logfile: sel!(cli, toml, logfile, (), String::from("/var/log/emerge.log"))?,
-                  from:
-                      cli.get_one("from")
-                         .map_or(Ok(TimeBound::None), |d| TimeBound::parse(d, offset, "--from"))?,
-                  to: cli.get_one("to")
-                         .map_or(Ok(TimeBound::None), |d| TimeBound::parse(d, offset, "--to"))?,
-                  pkg: AnsiStr::from(if color { "\x1B[1;32m" } else { "" }),
-                  merge: AnsiStr::from(if color { "\x1B[1;32m" } else { ">>> " }),
-                  unmerge: AnsiStr::from(if color { "\x1B[1;31m" } else { "<<< " }),
-                  dur: AnsiStr::from(if color { "\x1B[1;35m" } else { "" }),
-                  skip: AnsiStr::from(if color { "\x1B[37m" } else { "" }),
-                  cnt: AnsiStr::from(if color { "\x1B[33m" } else { "" }),
+                  run_re,
+                  staleness_check: sel!(cli, toml, staleness_check, (), false)?,
+                  from: match cli.get_one("from") {
+                      _ if cli.get_flag("since_sync") => TimeBound::SyncLast,
+                      _ if cli.get_flag("between_syncs") => TimeBound::SyncPrev,
+                      Some(d) => TimeBound::parse(d, &tz, "--from")?,
+                      None => TimeBound::None,
+                  },
+                  to: match cli.get_one("to") {
+                      _ if cli.get_flag("between_syncs") => TimeBound::SyncLast,
+                      Some(d) => TimeBound::parse(d, &tz, "--to")?,
+                      None => TimeBound::None,
+                  },
+                  to_exclusive: cli.get_flag("to_exclusive"),
+                  pkg: AnsiStr::from(pkg),
+                  merge: AnsiStr::from(merge),
+                  binmerge: AnsiStr::from(binmerge),
+                  unmerge: AnsiStr::from(unmerge),
+                  dur: AnsiStr::from(dur),
+                  skip: AnsiStr::from(skip),
+                  cnt: AnsiStr::from(cnt),
+                  in_time: AnsiStr::from(in_time),
+                  overtime: AnsiStr::from(overtime),
                   clr: AnsiStr::from(if color { "\x1B[m" } else { "" }),
                   lineend: if color { b"\x1B[m\n" } else { b"\n" },
-                  header: sel!(cli, toml, header, (), false)?,
+                  color_category: color && sel!(cli, toml, color_category, (), false)?,
+                  header: header_repeat || sel!(cli, toml, header, (), false)?,
+                  header_repeat,
                   showskip: sel!(cli, toml, showskip, (), true)?,
+                  skip_fmt: Some(sel!(cli, toml, skip_fmt, (), String::new())?).filter(|s| {
+                                                                                   !s.is_empty()
+                                                                               }),
                   dur_t: sel!(cli, toml, duration, (), DurationStyle::Hms)?,
-                  date_offset: offset,
+                  dur_parts: sel!(cli, toml, duration_parts, 1..=4, 4)? as usize,
+                  tz,
                   date_fmt: sel!(cli, toml, date, (), DateStyle::default())?,
-                  out: sel!(cli, toml, output, isterm, outdef)? })
+                  out,
+                  pager,
+                  progress,
+                  outfile,
+                  dump_config: cli.get_flag("dump_config") })
     }
     #[cfg(test)]
     pub fn from_str(s: impl AsRef<str>) -> Self {
@@ -182,17 +489,49 @@ impl Conf {
 
 impl ConfLog {
     fn try_new(cli: &ArgMatches, toml: &Toml) -> Result<Self, Error> {
+        let skip = *cli.get_one::<usize>("skip").unwrap_or(&0);
+        let take = *cli.get_one::<usize>("take").unwrap_or(&usize::MAX);
+        let first = *cli.get_one::<usize>("first").unwrap_or(&usize::MAX);
+        let last = *cli.get_one::<usize>("last").unwrap_or(&usize::MAX);
+        let tail = cli.get_one::<u64>("tail").map(|&t| t as usize);
+        let search = search_terms(cli)?;
+        let since_version = cli.get_one::<String>("since_version").cloned();
+        if let Some(v) = &since_version {
+            if !cli.get_flag("exact") || search.len() != 1 {
+                let msg = "Needs a single package name with --exact";
+                return Err(ArgError::new(v, "--since-version").msg(msg).into());
+            }
+            if !is_version(v) {
+                let msg = "Not a valid portage version";
+                return Err(ArgError::new(v, "--since-version").msg(msg).into());
+            }
+        }
         Ok(Self { show: sel!(cli, toml, log, show, "rmusa", Show::m())?,
-                  search: cli.get_many("search").unwrap_or_default().cloned().collect(),
+                  search,
+                  repo: repo_terms(cli),
                   exact: cli.get_flag("exact"),
+                  invert: cli.get_flag("invert"),
                   starttime: sel!(cli, toml, log, starttime, (), false)?,
-                  first: *cli.get_one("first").unwrap_or(&usize::MAX),
-                  last: *cli.get_one("last").unwrap_or(&usize::MAX) })
+                  downgrades: sel!(cli, toml, log, downgrades, (), false)?,
+                  coalesce_updates: sel!(cli, toml, log, coalesce_updates, (), false)?,
+                  target_root: sel!(cli, toml, log, target_root, (), false)?,
+                  line_buffered: cli.get_flag("line_buffered"),
+                  json_lines: cli.get_flag("json_lines"),
+                  sort: *cli.get_one("sort").unwrap_or(&LogSort::Date),
+                  first: first.min(skip.saturating_add(take)),
+                  last: last.min(tail.unwrap_or(usize::MAX)),
+                  tail,
+                  every: *cli.get_one::<u64>("every").unwrap_or(&1) as usize,
+                  skip,
+                  since_version,
+                  no_reinstalls: cli.get_flag("no_reinstalls"),
+                  installed_only: cli.get_flag("installed_only"),
+                  binary: bin_filter(cli)? })
     }
 }
 
 impl ConfPred {
-    fn try_new(cli: &ArgMatches, toml: &Toml) -> Result<Self, Error> {
+    fn try_new(cli: &ArgMatches, toml: &Toml, tz: &Tz) -> Result<Self, Error> {
         let tmpdirs = if let Some(a) = cli.get_many::<PathBuf>("tmpdir") {
             a.cloned().collect()
         } else if let Some(a) = toml.predict.as_ref().and_then(|t| t.tmpdir.as_ref()) {
@@ -200,41 +539,93 @@ impl ConfPred {
         } else {
             vec![PathBuf::from("/var/tmp")]
         };
-        Ok(Self { show: sel!(cli, toml, predict, show, "rmta", Show::rmt())?,
+        let bound =
+            |id, src| cli.get_one(id).map_or(Ok(TimeBound::None), |d| TimeBound::parse(d, tz, src));
+        let pretend_since = bound("pretend_since", "--pretend-since")?;
+        let mut show = sel!(cli, toml, predict, show, "rmsta", Show::rmt())?;
+        if cli.get_flag("no_total") {
+            show.tot = false;
+        }
+        Ok(Self { show,
                   avg: sel!(cli, toml, predict, avg, (), Average::Median)?,
                   lim: sel!(cli, toml, predict, limit, 1..=65000, 10)? as u16,
+                  min_samples: sel!(cli, toml, predict, min_samples, 0..=65000, 0)? as usize,
                   unknown: sel!(cli, toml, predict, unknown, 0..=3600, 10)?,
+                  scale: sel!(cli, toml, predict, scale, 0.01..=100.0, 1.0)?,
                   resume: *cli.get_one("resume").unwrap_or(&ResumeKind::Auto),
+                  resume_backup: sel!(cli, toml, predict, resume_backup, (), true)?,
                   tmpdirs,
+                  pretend: cli.get_many("pretend").unwrap_or_default().cloned().collect(),
+                  plan_json: cli.get_many("plan_json").unwrap_or_default().cloned().collect(),
+                  atoms: cli.get_many("atoms").unwrap_or_default().cloned().collect(),
+                  pretend_since,
+                  pred_from: bound("pred_from", "--pred-from")?,
+                  pred_to: bound("pred_to", "--pred-to")?,
                   first: *cli.get_one("first").unwrap_or(&usize::MAX),
                   last: *cli.get_one("last").unwrap_or(&usize::MAX),
                   pwidth: sel!(cli, toml, predict, pwidth, 10..=1000, 60)? as usize,
-                  pdepth: sel!(cli, toml, predict, pdepth, 0..=100, 3)? as usize })
+                  pdepth: sel!(cli, toml, predict, pdepth, 0..=100, 3)? as usize,
+                  finish_times: sel!(cli, toml, predict, finish_times, (), false)?,
+                  status_code: cli.get_flag("status_code") })
     }
     #[cfg(test)]
     pub fn from_str(s: impl AsRef<str>) -> (Conf, Self) {
         let cli = cli::build_cli().get_matches_from(s.as_ref().split_whitespace());
+        let sub = cli.subcommand().unwrap().1;
         (Conf::try_new(&cli, &Toml::default()).unwrap(),
-         ConfPred::try_new(cli.subcommand().unwrap().1, &Toml::default()).unwrap())
+         ConfPred::try_new(sub, &Toml::default(), &Tz::new(true)).unwrap())
     }
 }
 
 impl ConfStats {
-    fn try_new(cli: &ArgMatches, toml: &Toml) -> Result<Self, Error> {
-        Ok(Self { show: sel!(cli, toml, stats, show, "rptsa", Show::p())?,
-                  search: cli.get_many("search").unwrap_or_default().cloned().collect(),
+    fn try_new(cli: &ArgMatches, toml: &Toml, tz: &Tz) -> Result<Self, Error> {
+        let show = if cli.get_flag("summary") {
+            Show::st()
+        } else {
+            sel!(cli, toml, stats, show, "rptsa", Show::p())?
+        };
+        let group_regex = match sel!(cli, toml, stats, group_regex, (), String::new())? {
+            r if r.is_empty() => None,
+            r => Some(Regex::new(&r).map_err(|e| ArgError::new(r, "--group-regex").msg(e))?),
+        };
+        Ok(Self { show,
+                  search: search_terms(cli)?,
+                  repo: repo_terms(cli),
                   exact: cli.get_flag("exact"),
+                  invert: cli.get_flag("invert"),
+                  preserved: cli.get_flag("preserved"),
+                  no_reinstalls: cli.get_flag("no_reinstalls"),
+                  installed_only: cli.get_flag("installed_only"),
+                  binary: bin_filter(cli)?,
+                  by_version: cli.get_flag("by_version"),
+                  histogram: cli.get_flag("histogram"),
+                  clock: cli.get_flag("clock"),
+                  overview: cli.get_flag("overview"),
+                  count: cli.get_flag("count"),
+                  active_days: cli.get_flag("active_days"),
+                  compare: compare_ranges(cli, tz)?,
+                  csv_series: cli.get_flag("csv_series"),
+                  when: cli.get_one("when").copied(),
+                  top: cli.get_one::<u64>("top").map_or(usize::MAX, |&n| n as usize),
+                  first: *cli.get_one("first").unwrap_or(&usize::MAX),
+                  last: *cli.get_one("last").unwrap_or(&usize::MAX),
+                  no_predict: cli.get_flag("no_predict"),
                   lim: sel!(cli, toml, stats, limit, 1..=65000, 10)? as u16,
+                  min_samples: sel!(cli, toml, stats, min_samples, 0..=65000, 0)? as usize,
                   avg: sel!(cli, toml, stats, avg, (), Average::Median)?,
-                  group: sel!(cli, toml, stats, group, (), Timespan::None)? })
+                  group: sel!(cli, toml, stats, group, (), Timespan::None)?,
+                  relative_groups: sel!(cli, toml, stats, relative_groups, (), false)?,
+                  group_regex,
+                  live: cli.get_flag("live") })
     }
 }
 
 impl ConfAccuracy {
     fn try_new(cli: &ArgMatches, toml: &Toml) -> Result<Self, Error> {
-        Ok(Self { show: sel!(cli, toml, accuracy, show, "mta", Show::mt())?,
-                  search: cli.get_many("search").unwrap_or_default().cloned().collect(),
+        Ok(Self { show: sel!(cli, toml, accuracy, show, "msta", Show::mt())?,
+                  search: search_sets(cli, "/etc/portage/sets")?,
                   exact: cli.get_flag("exact"),
+                  invert: cli.get_flag("invert"),
                   avg: sel!(cli, toml, accuracy, avg, (), Average::Median)?,
                   lim: sel!(cli, toml, accuracy, limit, 1..=65000, 10)? as u16,
                   last: *cli.get_one("last").unwrap_or(&usize::MAX) })
@@ -245,6 +636,74 @@ impl ConfComplete {
     fn try_new(cli: &ArgMatches) -> Result<Self, Error> {
         Ok(Self { #[cfg(feature = "clap_complete")]
                   shell: cli.get_one("shell").cloned(),
-                  pkg: cli.get_one("pkg").cloned() })
+                  pkg: cli.get_one("pkg").cloned(),
+                  null: cli.get_flag("null") })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sets() {
+        let t = [("@toolchain",
+                  Ok(vec!["^sys\\-devel/gcc$", "^sys\\-libs/glibc$", "(^|/)binutils$"])),
+                 ("-e @toolchain", Ok(vec!["sys-devel/gcc", "sys-libs/glibc", "binutils"])),
+                 ("-e foo @toolchain bar",
+                  Ok(vec!["foo", "sys-devel/gcc", "sys-libs/glibc", "binutils", "bar"])),
+                 ("@empty", Err("No package found in set @empty")),
+                 ("@notfound", Err("Unknown set @notfound"))];
+        for (a, r) in t {
+            let cli = build_cli().get_matches_from(format!("emlop l {a}").split_whitespace());
+            let res = search_sets(cli.subcommand().unwrap().1, "tests/sets");
+            assert_eq!(res.map_err(|e| e.to_string()),
+                       r.map(|v| v.into_iter().map(String::from).collect()).map_err(String::from),
+                       "{a}");
+        }
+    }
+
+    #[test]
+    fn glob() {
+        let t = [("*firefox*", "(^|/)[^/]*firefox[^/]*$", "www-client/firefox-bin", true),
+                 ("*firefox*", "(^|/)[^/]*firefox[^/]*$", "www-client/ff", false),
+                 ("kde-*/*", "^kde\\-[^/]*/[^/]*$", "kde-frameworks/baloo", true),
+                 ("kde-*/*", "^kde\\-[^/]*/[^/]*$", "dev-libs/kde-foo", false),
+                 ("rust", "(^|/)rust$", "virtual/rust", true),
+                 ("rust", "(^|/)rust$", "dev-util/rustup", false),
+                 ("gcc-?", "(^|/)gcc\\-[^/]$", "cross/gcc-9", true),
+                 ("*/[pc]ython", "^[^/]*/[pc]ython$", "dev-python/cython", true),
+                 ("*/[!pc]ython", "^[^/]*/[^pc]ython$", "dev-python/cython", false),
+                 ("llvm-[0-9]*", "(^|/)llvm\\-[0-9][^/]*$", "sys-devel/llvm-17", true),
+                 ("c++", "(^|/)c\\+\\+$", "dev/c++", true)];
+        for (g, r, s, m) in t {
+            let re = glob_to_regex(g);
+            assert_eq!(re, r, "{g}");
+            assert_eq!(Regex::new(&re).unwrap().is_match(s), m, "{g} {s}");
+        }
+    }
+
+    #[test]
+    fn color_auto() {
+        let t = [("", false, false),
+                 ("", true, true),
+                 ("NO_COLOR=1", true, false),
+                 ("NO_COLOR=", true, true),
+                 ("CLICOLOR_FORCE=1", false, true),
+                 ("CLICOLOR_FORCE=0", false, false),
+                 ("CLICOLOR_FORCE=1 NO_COLOR=1", false, false),
+                 ("CLICOLOR=0", true, false),
+                 ("CLICOLOR=1", false, false),
+                 ("CLICOLOR=0 CLICOLOR_FORCE=1", false, true)];
+        for (env, isterm, res) in t {
+            let var = |k: &str| {
+                env.split_whitespace().find_map(|kv| {
+                                          kv.split_once('=')
+                                            .filter(|(n, _)| *n == k)
+                                            .map(|(_, v)| v.into())
+                                      })
+            };
+            assert_eq!(color_env(isterm, var), res, "{env} {isterm}");
+        }
     }
 }
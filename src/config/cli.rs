@@ -1,5 +1,5 @@
 use clap::{builder::styling, crate_version, value_parser, Arg, ArgAction::*, Command};
-use std::path::PathBuf;
+use std::{io::Write as _, path::PathBuf};
 
 /// Generate cli argument parser without the `complete` subcommand.
 pub fn build_cli() -> Command {
@@ -13,12 +13,35 @@ pub fn build_cli() -> Command {
                                 .help("    Show only packages/repos matching <search>")
                                 .long_help("Show only packages/repos matching <search>\n\
                                             Multiple terms can be provided\n\
-                                            Matches using a regex unless `--exact` is specified\n\
+                                            Matches using a regex unless `--exact` or `--glob` is specified\n\
                                             See https://docs.rs/regex/*/regex/#syntax\n  \
                                             rust:        Matches `dev-lang/rust`, `dev-util/rustup`, `dev-python/trustme`, etc\n  \
                                             /[pc]ython$: Matches `dev-lang/python` and `dev-python/cython`\n  \
                                             pyqt:        Matches `dev-python/PyQt5` (case-insensitive)\n  \
-                                            guru:        Matches `guru` (repo sync)");
+                                            guru:        Matches `guru` (repo sync)\n  \
+                                            @toolchain:  Matches packages listed in `/etc/portage/sets/toolchain`");
+    let repo = Arg::new("repo").long("repo")
+                               .value_name("search")
+                               .num_args(1)
+                               .action(Append)
+                               .display_order(2)
+                               .help_heading("Filter")
+                               .help("Show only repos matching <search>")
+                               .long_help("Show only repos matching <search>\n\
+                                           Can be given multiple times, uses the same matching as package <search>\n\
+                                           When given, package <search> terms only apply to packages");
+    let h = "Show only packages listed in <file>\n\
+             One package per line, matched exactly as with `--exact`, in addition to <search>\n\
+             Empty lines and lines starting with `#` are ignored";
+    let pkg_file = Arg::new("pkg_file").long("pkg-file")
+                                       .value_name("file")
+                                       .num_args(1)
+                                       .action(Append)
+                                       .value_parser(value_parser!(PathBuf))
+                                       .display_order(2)
+                                       .help_heading("Filter")
+                                       .help(h.split_once('\n').unwrap().0)
+                                       .long_help(h);
     let exact = Arg::new("exact").short('e')
                                  .long("exact")
                                  .action(SetTrue)
@@ -30,6 +53,29 @@ pub fn build_cli() -> Command {
                                              virtual/rust: Matches only `virtual/rust`\n  \
                                              RuSt:         Matches nothing (case-sensitive)\n  \
                                              ru:           Matches nothing (whole name only)");
+    let h = "Show only entries that don't match <search>\n\
+             Repos are also inverted, unless they're matched using `--repo`. Has no effect without \
+             <search>.";
+    let invert = Arg::new("invert").long("invert")
+                                   .action(SetTrue)
+                                   .display_order(2)
+                                   .help_heading("Filter")
+                                   .help(h.split_once('\n').unwrap().0)
+                                   .long_help(h);
+    let glob = Arg::new("glob").long("glob")
+                               .action(SetTrue)
+                               .conflicts_with("exact")
+                               .display_order(2)
+                               .help_heading("Filter")
+                               .help("Match <search> using shell globs")
+                               .long_help("Match <search> using shell globs\n\
+                                           `*` and `?` match any characters except `/`, `[...]` and `[!...]` \
+                                           match a character class. The whole name must match, and a pattern \
+                                           without `/` is matched against the package name only, like `--exact` \
+                                           but unlike the default regex matching.\n  \
+                                           *firefox*: Matches `www-client/firefox` and `www-client/firefox-bin`\n  \
+                                           kde-*/*:   Matches all packages in `kde-*` categories\n  \
+                                           rust:      Matches `dev-lang/rust` and `virtual/rust`, but not `dev-util/rustup`");
     let show_l =
         Arg::new("show").short('s')
                         .long("show")
@@ -56,55 +102,100 @@ pub fn build_cli() -> Command {
                                     t: Total package merges/unmerges\n  \
                                     s: Repository syncs\n  \
                                     a: All of the above");
-    let show_p = Arg::new("show").short('s')
-                                 .long("show")
-                                 .value_name("r,m,t,a")
-                                 .display_order(3)
-                                 .help_heading("Filter")
-                                 .help("Show (r)unning processes, (m)erges, (t)otal, and/or (a)ll")
-                                 .long_help("Show (any combination of)\n  \
+    let show_p =
+        Arg::new("show").short('s')
+                        .long("show")
+                        .value_name("r,m,s,t,a")
+                        .display_order(3)
+                        .help_heading("Filter")
+                        .help("Show (r)unning processes, (m)erges, (s)yncs, (t)otal, and/or (a)ll")
+                        .long_help("Show (any combination of)\n  \
                                              r: Running emerge processes\n  \
                                              m: Package merges\n  \
+                                             s: Sync estimate for the repos of the last sync\n  \
                                              t: Total estimate\n  \
                                              a: All of the above");
+    let no_total = Arg::new("no_total").long("no-total")
+                                       .action(SetTrue)
+                                       .display_order(3)
+                                       .help_heading("Filter")
+                                       .help("Don't show the total estimate, overriding `--show`");
     let show_a = Arg::new("show").short('s')
                                  .long("show")
-                                 .value_name("m,t,a")
+                                 .value_name("m,s,t,a")
                                  .display_order(3)
                                  .help_heading("Filter")
-                                 .help("Show (m)erges, (t)otals, and/or (a)ll")
+                                 .help("Show (m)erges, (s)yncs, (t)otals, and/or (a)ll")
                                  .long_help("Show (any combination of)\n  \
                                              m: Package merges\n  \
+                                             s: Repository syncs\n  \
                                              t: Totals\n  \
                                              a: All of the above");
     let h = "Only parse log entries after <date/command>\n  \
              2018-03-04|2018-03-04 12:34:56|2018-03-04T12:34: Absolute ISO date\n  \
              123456789:                                       Absolute unix timestamp\n  \
              1 year, 2 months|10d:                            Relative date\n  \
-             1c|2 commands|c                                  Nth emerge command";
+             1c|2 commands|c:                                 Nth emerge command\n  \
+             -1c|-2 commands|1C|C:                            Nth-last emerge command";
     let from = Arg::new("from").short('f')
                                .long("from")
                                .value_name("date")
                                .global(true)
                                .num_args(1)
+                               .allow_hyphen_values(true)
                                .display_order(4)
                                .help_heading("Filter")
                                .help(h.split_once('\n').unwrap().0)
                                .long_help(h);
+    let h = "Only parse log entries after the last sync\n\
+             Shorthand for `--from` with the date of the last completed sync, can't be combined with `--from`";
+    let since_sync = Arg::new("since_sync").long("since-sync")
+                                           .global(true)
+                                           .action(SetTrue)
+                                           .conflicts_with("from")
+                                           .display_order(4)
+                                           .help_heading("Filter")
+                                           .help(h.split_once('\n').unwrap().0)
+                                           .long_help(h);
+    let h = "Only parse log entries between the last two syncs\n\
+             Shorthand for `--from` and `--to` with the dates of the two last completed syncs, can't \
+             be combined with them. Repos synced by the same emerge command count as one sync.";
+    let between_syncs = Arg::new("between_syncs").long("between-syncs")
+                                                 .global(true)
+                                                 .action(SetTrue)
+                                                 .conflicts_with_all(["from", "to", "since_sync"])
+                                                 .display_order(4)
+                                                 .help_heading("Filter")
+                                                 .help(h.split_once('\n').unwrap().0)
+                                                 .long_help(h);
     let h = "Only parse log entries before <date/command>\n  \
              2018-03-04|2018-03-04 12:34:56|2018-03-04T12:34: Absolute ISO date\n  \
              123456789:                                       Absolute unix timestamp\n  \
              1 year, 2 months|10d:                            Relative date\n  \
-             1c|2 commands|c                                  Nth-last emerge command";
+             1c|2 commands|c:                                 Nth emerge command\n  \
+             -1c|-2 commands|1C|C:                            Nth-last emerge command";
     let to = Arg::new("to").short('t')
                            .long("to")
                            .value_name("date")
                            .global(true)
                            .num_args(1)
+                           .allow_hyphen_values(true)
                            .display_order(5)
                            .help_heading("Filter")
                            .help(h.split_once('\n').unwrap().0)
                            .long_help(h);
+    let h = "Exclude entries at exactly the --to date\n\
+             By default both --from and --to are inclusive. Use this to split the log into \
+             adjacent ranges, like `--to X --to-exclusive` and `--from X`, without counting \
+             entries twice.";
+    let to_exclusive = Arg::new("to_exclusive").long("to-exclusive")
+                                               .global(true)
+                                               .action(SetTrue)
+                                               .requires("to")
+                                               .display_order(5)
+                                               .help_heading("Filter")
+                                               .help(h.split_once('\n').unwrap().0)
+                                               .long_help(h);
     let first = Arg::new("first").short('N')
                                  .long("first")
                                  .value_name("num")
@@ -129,9 +220,137 @@ pub fn build_cli() -> Command {
                                .long_help("Show only the last <num> entries\n  \
                                            (empty)|1: last entry\n  \
                                            5:         last 5 entries\n");
+    let h = "Only show (un)merges of <version> or later\n\
+             Versions are compared using portage rules. Needs a single package name with --exact.";
+    let since_version = Arg::new("since_version").long("since-version")
+                                                 .value_name("version")
+                                                 .num_args(1)
+                                                 .display_order(7)
+                                                 .help_heading("Filter")
+                                                 .help(h.split_once('\n').unwrap().0)
+                                                 .long_help(h);
+    let h = "Skip the first <num> entries\n\
+             Applied after --every, use with --take to show a page of entries";
+    let skip = Arg::new("skip").long("skip")
+                               .value_name("num")
+                               .num_args(1)
+                               .value_parser(value_parser!(usize))
+                               .conflicts_with("last")
+                               .display_order(7)
+                               .help_heading("Filter")
+                               .help(h.split_once('\n').unwrap().0)
+                               .long_help(h);
+    let h = "Show only <num> entries after the skipped ones\n\
+             Same as --first, but counted after --skip";
+    let take = Arg::new("take").long("take")
+                               .value_name("num")
+                               .num_args(1)
+                               .value_parser(value_parser!(usize))
+                               .conflicts_with("last")
+                               .display_order(7)
+                               .help_heading("Filter")
+                               .help(h.split_once('\n').unwrap().0)
+                               .long_help(h);
+    let h = "Show only every <num>th entry\n\
+             Applied to all matching entries, before --first and --last";
+    let every = Arg::new("every").long("every")
+                                 .value_name("num")
+                                 .num_args(1)
+                                 .value_parser(clap::value_parser!(u64).range(1..))
+                                 .display_order(7)
+                                 .help_heading("Filter")
+                                 .help(h.split_once('\n').unwrap().0)
+                                 .long_help(h);
+    let h = "Show only the last <num> entries, reading only the end of the log\n\
+             Much faster than `--last` on big logs. The log is read backward until <num> events \
+             are found, before applying search terms, so fewer entries may be displayed. \
+             Compressed and remote logs, or logs with a `--to` bound, are read in full.";
+    let tail = Arg::new("tail").long("tail")
+                               .value_name("num")
+                               .num_args(1)
+                               .value_parser(clap::value_parser!(u64).range(1..))
+                               .display_order(7)
+                               .help_heading("Filter")
+                               .help(h.split_once('\n').unwrap().0)
+                               .long_help(h);
+    let h = "Read pretended merges from <file> instead of STDIN\n\
+             <file> should contain the output of `emerge -p`\n\
+             Multiple files can be provided, packages present in more than one are predicted once";
+    let pretend = Arg::new("pretend").long("pretend-file")
+                                     .value_name("file")
+                                     .num_args(1)
+                                     .action(Append)
+                                     .value_parser(value_parser!(PathBuf))
+                                     .display_order(8)
+                                     .help_heading("Filter")
+                                     .help(h.split_once('\n').unwrap().0)
+                                     .long_help(h);
+    let h = "Read pretended merges from a JSON <file> instead of STDIN\n\
+             <file> should contain an array of `category/name-version` atoms, for example \
+             `[\"sys-devel/gcc-13.2.1\", \"=dev-lang/rust-1.75.0\"]`\n\
+             Can be combined with `--pretend-file`, packages present in more than one are predicted once";
+    let plan_json = Arg::new("plan_json").long("plan-json")
+                                         .value_name("file")
+                                         .num_args(1)
+                                         .action(Append)
+                                         .value_parser(value_parser!(PathBuf))
+                                         .display_order(8)
+                                         .help_heading("Filter")
+                                         .help(h.split_once('\n').unwrap().0)
+                                         .long_help(h);
+    let h = "Predict merge times for the given atoms\n\
+             Atoms are `category/name`, optionally with a `-version`, a version operator like `=` or \
+             `>=` (ignored), and a `:slot` suffix. \
+             Takes precedence over STDIN and the resume list, can be combined with `--pretend-file` \
+             and `--plan-json`. Completed merges are not skipped.";
+    let atoms = Arg::new("atoms").long("atoms")
+                                 .value_name("atom")
+                                 .num_args(1..)
+                                 .action(Append)
+                                 .display_order(8)
+                                 .help_heading("Filter")
+                                 .help(h.split_once('\n').unwrap().0)
+                                 .long_help(h);
+    let h = "Ignore pretended merges that completed after <date/command>\n\
+             Defaults to the --pretend-file/--plan-json modification time, or to the current emerge start time\n  \
+             2018-03-04|2018-03-04 12:34:56|2018-03-04T12:34: Absolute ISO date\n  \
+             123456789:                                       Absolute unix timestamp\n  \
+             1 year, 2 months|10d:                            Relative date\n  \
+             1c|2 commands|c:                                 Nth emerge command\n  \
+             -1c|-2 commands|1C|C:                            Nth-last emerge command";
+    let pretend_since = Arg::new("pretend_since").long("pretend-since")
+                                                 .value_name("date")
+                                                 .num_args(1)
+                                                 .allow_hyphen_values(true)
+                                                 .display_order(8)
+                                                 .help_heading("Filter")
+                                                 .help(h.split_once('\n').unwrap().0)
+                                                 .long_help(h);
+    let h = "Only use merges that completed after <date/command> for predictions\n\
+             Unlike `--from`, this doesn't affect which merges are pending or running. Accepts the \
+             same formats as `--from`.";
+    let pred_from = Arg::new("pred_from").long("pred-from")
+                                         .value_name("date")
+                                         .num_args(1)
+                                         .allow_hyphen_values(true)
+                                         .display_order(8)
+                                         .help_heading("Filter")
+                                         .help(h.split_once('\n').unwrap().0)
+                                         .long_help(h);
+    let h = "Only use merges that completed before <date/command> for predictions\n\
+             Unlike `--to`, this doesn't affect which merges are pending or running. Accepts the \
+             same formats as `--to`.";
+    let pred_to = Arg::new("pred_to").long("pred-to")
+                                     .value_name("date")
+                                     .num_args(1)
+                                     .allow_hyphen_values(true)
+                                     .display_order(8)
+                                     .help_heading("Filter")
+                                     .help(h.split_once('\n').unwrap().0)
+                                     .long_help(h);
     let h = "Use main, backup, either, or no portage resume list\n\
-             This is ignored if STDIN is a piped `emerge -p` output\n  \
-             (default)|auto|a: Use main or backup resume list, if currently emerging\n  \
+             This is ignored if STDIN is a piped `emerge -p` output or if --pretend-file is used\n  \
+             (default)|auto|a: Use main or backup resume list, if currently emerging or if /proc is unavailable\n  \
              (empty)|either|e: Use main or backup resume list\n  \
              main|m:           Use main resume list\n  \
              backup|b:         Use backup resume list\n  \
@@ -146,6 +365,50 @@ pub fn build_cli() -> Command {
                                    .help_heading("Filter")
                                    .help(h.split_once('\n').unwrap().0)
                                    .long_help(h);
+    let h = "Fall back to the backup resume list when the main one is empty\n\
+             Applies to `--resume auto` and `--resume either`. Disable it if your backup resume list \
+             is often stale: only the main list will be used, and no list is shown if it is missing. \
+             This differs from `--resume main`, which is always used even when not emerging.";
+    let resume_backup = Arg::new("resume_backup").long("resume-backup")
+                                                 .value_name("bool")
+                                                 .num_args(..=1)
+                                                 .default_missing_value("y")
+                                                 .display_order(9)
+                                                 .help_heading("Filter")
+                                                 .help(h.split_once('\n').unwrap().0)
+                                                 .long_help(h);
+    let preserved =
+        Arg::new("preserved").long("preserved-rebuild")
+                             .action(SetTrue)
+                             .display_order(9)
+                             .help_heading("Filter")
+                             .help("Only count (un)merges from `@preserved-rebuild` runs");
+    let reinstalls =
+        Arg::new("no_reinstalls").long("no-reinstalls")
+                                 .action(SetTrue)
+                                 .display_order(9)
+                                 .help_heading("Filter")
+                                 .help("Skip merges of the same version as the previous merge");
+    let h = "Show only packages that are currently installed\n\
+             Installed packages are read from `/var/db/pkg`. Syncs and emerge commands are not affected.";
+    let installed = Arg::new("installed_only").long("installed-only")
+                                              .action(SetTrue)
+                                              .display_order(9)
+                                              .help_heading("Filter")
+                                              .help(h.split_once('\n').unwrap().0)
+                                              .long_help(h);
+
+    let h = "Filter binary merges\n  \
+             (default)|both|b: Show binary and source merges\n  \
+             only|o:           Show only binary merges\n  \
+             exclude|e:        Show only source merges";
+    let binary = Arg::new("binary").long("binary")
+                                   .value_name("bin")
+                                   .num_args(1)
+                                   .display_order(9)
+                                   .help_heading("Filter")
+                                   .help(h.split_once('\n').unwrap().0)
+                                   .long_help(h);
 
     ////////////////////////////////////////////////////////////
     // Stats arguments
@@ -160,23 +423,215 @@ pub fn build_cli() -> Command {
                                              The grouping key is displayed in the first column.\n\
                                              Weeks start on monday and are formated as \
                                              'year-weeknumber'.");
+    let h = "Label the current and previous groups relatively\n\
+             For example `this month` and `last month` instead of `2024-02` and `2024-01`. Older \
+             groups keep their absolute label.";
+    let relative_groups = Arg::new("relative_groups").long("relative-groups")
+                                                     .value_name("bool")
+                                                     .num_args(..=1)
+                                                     .default_missing_value("y")
+                                                     .display_order(10)
+                                                     .help_heading("Stats")
+                                                     .help(h.split_once('\n').unwrap().0)
+                                                     .long_help(h);
+    let h = "Group packages by the first capture of <regex>\n\
+             The regex is applied to each `category/name`, packages that don't match are grouped as \
+             `other`. For example `^([a-z]+)-` groups by category prefix. Groups are used for \
+             predictions, binary savings, `--live`, `--compare` and `--csv-series` too.";
+    let group_regex = Arg::new("group_regex").long("group-regex")
+                                             .value_name("regex")
+                                             .num_args(1)
+                                             .conflicts_with("by_version")
+                                             .display_order(10)
+                                             .help_heading("Stats")
+                                             .help(h.split_once('\n').unwrap().0)
+                                             .long_help(h);
+    let summary = Arg::new("summary").long("summary-only")
+                                     .action(SetTrue)
+                                     .display_order(10)
+                                     .help_heading("Stats")
+                                     .help("Only show totals and sync tables, overriding `--show`");
+    let by_version = Arg::new("by_version").long("by-version")
+                                           .action(SetTrue)
+                                           .display_order(10)
+                                           .help_heading("Stats")
+                                           .help("Also show per-version package stats");
+    let h = "Also show a histogram of merge times below each package\n\
+             Displayed as a sparkline of up to 10 buckets, between the shortest and longest merge times.";
+    let histogram = Arg::new("histogram").long("histogram")
+                                         .action(SetTrue)
+                                         .display_order(10)
+                                         .help_heading("Stats")
+                                         .help(h.split_once('\n').unwrap().0)
+                                         .long_help(h);
+    let h = "Also show the number of syncs by hour of the day\n\
+             Uses the sync start time, useful to check cron schedules at a glance.";
+    let clock = Arg::new("clock").long("clock")
+                                 .action(SetTrue)
+                                 .display_order(10)
+                                 .help_heading("Stats")
+                                 .help(h.split_once('\n').unwrap().0)
+                                 .long_help(h);
+    let h = "Also show an overview line with the number of distinct packages and merges\n\
+             Counts distinct packages, distinct versions and total merges, and the dates of the first \
+             and last merge. Useful to sanity-check filters.";
+    let overview = Arg::new("overview").long("overview")
+                                       .action(SetTrue)
+                                       .display_order(10)
+                                       .help_heading("Stats")
+                                       .help(h.split_once('\n').unwrap().0)
+                                       .long_help(h);
+    let h = "Also show the number of active days and the (un)merge time per active day\n\
+             Days without any (un)merge are not counted, which gives a better idea of a typical \
+             emerge day than dividing by the length of the --groupby period.";
+    let active_days = Arg::new("active_days").long("active-days")
+                                             .action(SetTrue)
+                                             .display_order(10)
+                                             .help_heading("Stats")
+                                             .help(h.split_once('\n').unwrap().0)
+                                             .long_help(h);
+    let h = "Compare package merge times between two date ranges\n\
+             Ranges are written `<from>..<to>`, for example \
+             `--compare 2024-01-01..2024-07-01 2024-07-01..`. Each bound accepts the same values as \
+             --from and --to, and can be left empty. Merges are counted in a range using their start \
+             time, the end of a range is exclusive.\n\
+             Shows the merge count and predicted time of each range, and the change in percent.";
+    let compare = Arg::new("compare").long("compare")
+                                     .value_names(["range", "range"])
+                                     .num_args(2)
+                                     .allow_hyphen_values(true)
+                                     .conflicts_with_all(["group",
+                                                          "top",
+                                                          "show",
+                                                          "no_predict",
+                                                          "count",
+                                                          "histogram",
+                                                          "overview",
+                                                          "active_days",
+                                                          "by_version",
+                                                          "clock"])
+                                     .display_order(10)
+                                     .help_heading("Stats")
+                                     .help(h.split_once('\n').unwrap().0)
+                                     .long_help(h);
+    let h = "Export merges as a CSV time series\n\
+             Writes the number of merges and their total time in seconds for each `--groupby` \
+             period (day by default) and package. Periods without merges are included, from the \
+             first to the last merge or within `--from`/`--to`, so that the series is continuous. \
+             Without `--show=p`, only the totals of each period are written.";
+    let csv_series = Arg::new("csv_series").long("csv-series")
+                                           .action(SetTrue)
+                                           .conflicts_with_all(["compare",
+                                                                "count",
+                                                                "top",
+                                                                "first",
+                                                                "last",
+                                                                "histogram",
+                                                                "by_version",
+                                                                "overview",
+                                                                "active_days",
+                                                                "clock",
+                                                                "live"])
+                                           .display_order(10)
+                                           .help_heading("Stats")
+                                           .help(h.split_once('\n').unwrap().0)
+                                           .long_help(h);
+    let h = "Only print the date of the first or last merge\n\
+             A quick answer for scripts, like `emlop s -e dev-lang/rust --when first`. Uses the \
+             earliest or latest merge of all matching packages. Exits with code 1 if no merge \
+             was found.\n  \
+             first|f: Date of the first merge\n  \
+             last|l:  Date of the last merge";
+    let when = Arg::new("when").long("when")
+                               .value_name("first|last")
+                               .value_parser(value_parser!(crate::config::When))
+                               .hide_possible_values(true)
+                               .num_args(1)
+                               .conflicts_with_all(["compare", "count", "csv_series"])
+                               .display_order(10)
+                               .help_heading("Stats")
+                               .help(h.split_once('\n').unwrap().0)
+                               .long_help(h);
+    let h = "Only print the number of package rows that would be shown\n\
+             Useful to check filters before printing a large report. Takes `--groupby` and `--top` \
+             into account, but not the extra rows of `--by-version` and `--histogram`.";
+    let count = Arg::new("count").long("count")
+                                 .action(SetTrue)
+                                 .display_order(10)
+                                 .help_heading("Stats")
+                                 .help(h.split_once('\n').unwrap().0)
+                                 .long_help(h);
+    let h = "Only show the <num> packages with the most merge time\n\
+             Packages are sorted by total merge time instead of by name. \
+             The remaining packages are summed up in an `others` row, unless `--showskip=n`.";
+    let top = Arg::new("top").long("top")
+                             .value_name("num")
+                             .num_args(1)
+                             .value_parser(clap::value_parser!(u64).range(1..))
+                             .display_order(10)
+                             .help_heading("Stats")
+                             .help(h.split_once('\n').unwrap().0)
+                             .long_help(h);
+    let h = "Show packages currently being merged\n\
+             Adds an `(in progress)` row for each ongoing merge, with the elapsed time so far. Needs \
+             access to `/proc`, like `predict`.";
+    let live = Arg::new("live").long("live")
+                               .action(SetTrue)
+                               .display_order(10)
+                               .help_heading("Stats")
+                               .help(h.split_once('\n').unwrap().0)
+                               .long_help(h);
+    let h = "Don't compute predicted times\n\
+             Faster when only counts and totals are needed. `--avg` and `--limit` are ignored.";
+    let no_predict = Arg::new("no_predict").long("no-predict")
+                                           .action(SetTrue)
+                                           .display_order(10)
+                                           .help_heading("Stats")
+                                           .help(h.split_once('\n').unwrap().0)
+                                           .long_help(h);
+    let h = "Use the last <num> merge times to predict durations\n\
+             With `-v`, `stats` and `accuracy` report how many predictions had fewer merge times \
+             available.";
     let limit = Arg::new("limit").long("limit")
                                  .value_name("num")
                                  .num_args(1)
                                  .display_order(11)
                                  .help_heading("Stats")
-                                 .help("Use the last <num> merge times to predict durations");
+                                 .help(h.split_once('\n').unwrap().0)
+                                 .long_help(h);
+    let h = "Mark predictions based on fewer than <num> merges with a `~`\n\
+             Estimates from few samples are less reliable. Takes `--limit` into account, \
+             default 0 (disabled).";
+    let min_samples = Arg::new("min_samples").long("min-samples")
+                                             .value_name("num")
+                                             .num_args(1)
+                                             .display_order(11)
+                                             .help_heading("Stats")
+                                             .help(h.split_once('\n').unwrap().0)
+                                             .long_help(h);
     let h = "Select function used to predict durations\n  \
              arith|a:            simple 'sum/count' average\n  \
              (defaut)|median|m:  middle value, mitigates outliers\n  \
              weighted-arith|wa:  'sum/count' with more weight for recent values\n  \
-             weighted-median|wm: \"middle\" value shifted toward recent values";
+             weighted-median|wm: \"middle\" value shifted toward recent values\n  \
+             p<num>:             <num>th percentile (1-100), eg p90 gives safer (longer) estimates";
     let avg = Arg::new("avg").long("avg")
                              .value_name("fn")
                              .display_order(12)
                              .help_heading("Stats")
                              .help(h.split_once('\n').unwrap().0)
                              .long_help(h);
+    let h = "Multiply predicted merge times by <factor>\n\
+             Compensates for a known speed change, for example `0.7` after a hardware upgrade. This \
+             is a blunt instrument until emlop can detect speed trends by itself: all past merge \
+             times are scaled the same way. Unknown packages and syncs are not scaled.";
+    let scale = Arg::new("scale").long("scale")
+                                 .value_name("factor")
+                                 .num_args(1)
+                                 .display_order(13)
+                                 .help_heading("Stats")
+                                 .help(h.split_once('\n').unwrap().0)
+                                 .long_help(h);
     let unknown = Arg::new("unknown").long("unknown")
                                      .num_args(1)
                                      .value_name("secs")
@@ -195,8 +650,13 @@ pub fn build_cli() -> Command {
                                    .default_missing_value("y")
                                    .display_order(20)
                                    .help_heading("Format")
-                                   .help("Show table header");
+                                   .help("Show table header")
+                                   .long_help("Show table header\n  \
+                                               yes|y:    Show the header once per table\n  \
+                                               no|n:     Don't show the header\n  \
+                                               repeat|r: Repeat the header before each group of `stats -g`");
     let duration = Arg::new("duration").long("duration")
+                                       .alias("dur")
                                        .value_name("format")
                                        .global(true)
                                        .display_order(21)
@@ -206,7 +666,19 @@ pub fn build_cli() -> Command {
                                                    hms|(default): 10:30\n  \
                                                    hmsfixed:      0:10:30\n  \
                                                    secs|s:        630\n  \
-                                                   human|h:       10 minutes, 30 seconds");
+                                                   human|h:       10 minutes, 30 seconds\n  \
+                                                   iso:           PT10M30S\n  \
+                                                   hours|h2:      0.17");
+    let duration_parts = Arg::new("duration_parts").long("duration-parts")
+                                                   .value_name("num")
+                                                   .global(true)
+                                                   .num_args(1)
+                                                   .display_order(21)
+                                                   .help_heading("Format")
+                                                   .help("Show at most <num> parts of `human` durations")
+                                                   .long_help("Show at most <num> parts of `human` durations\n\
+                                                               For example `2 days, 1 second` becomes `2 days` with `--duration-parts=1`\n\
+                                                               Smaller parts are truncated, not rounded");
     let h = "Output dates in different formats\n  \
              ymd|d:               2022-01-31\n  \
              (default)|ymdhms|dt: 2022-01-31 08:59:46\n  \
@@ -229,7 +701,20 @@ pub fn build_cli() -> Command {
                              .default_missing_value("y")
                              .display_order(23)
                              .help_heading("Format")
-                             .help("Parse/display dates in UTC instead of local time");
+                             .help("Parse/display dates in UTC instead of local time")
+                             .long_help("Parse/display dates in UTC instead of local time\n\
+                                         Deprecated alias for `--tz=utc`");
+    let tz = Arg::new("tz").long("tz")
+                           .value_name("tz")
+                           .global(true)
+                           .num_args(1)
+                           .display_order(23)
+                           .help_heading("Format")
+                           .help("Timezone to parse/display dates with")
+                           .long_help("Timezone to parse/display dates with\n  \
+                                       (default)|local|l: `$TZ` or system timezone\n  \
+                                       utc|u:             UTC\n  \
+                                       +HH:MM|-HH:MM:     fixed offset from UTC");
     let starttime = Arg::new("starttime").long("starttime")
                                          .value_name("bool")
                                          .num_args(..=1)
@@ -237,6 +722,87 @@ pub fn build_cli() -> Command {
                                          .display_order(24)
                                          .help_heading("Format")
                                          .help("Display start time instead of end time");
+    let h = "Mark merges of a lower version than the previous merge of that package\n\
+             Slots are not taken into account, so switching to an older slot counts as a downgrade.";
+    let downgrades = Arg::new("downgrades").long("mark-downgrades")
+                                           .value_name("bool")
+                                           .num_args(..=1)
+                                           .default_missing_value("y")
+                                           .display_order(24)
+                                           .help_heading("Format")
+                                           .help(h.split_once('\n').unwrap().0)
+                                           .long_help(h);
+    let h = "Fold the unmerge of the old version into the merge row during updates\n\
+             When a package is unmerged while a merge of the same package is in progress in the same \
+             emerge command, hide the unmerge row and mark the merge row with the replaced version.";
+    let coalesce = Arg::new("coalesce_updates").long("coalesce-updates")
+                                               .value_name("bool")
+                                               .num_args(..=1)
+                                               .default_missing_value("y")
+                                               .display_order(24)
+                                               .help_heading("Format")
+                                               .help(h.split_once('\n').unwrap().0)
+                                               .long_help(h);
+    let h = "Display the target root of merges\n\
+             Shows ` to <root>` after each merge, useful to tell apart merges into different \
+             `ROOT`s. Older logs don't record the root, it is assumed to be `/`.";
+    let target_root = Arg::new("target_root").long("target-root")
+                                             .value_name("bool")
+                                             .num_args(..=1)
+                                             .default_missing_value("y")
+                                             .display_order(24)
+                                             .help_heading("Format")
+                                             .help(h.split_once('\n').unwrap().0)
+                                             .long_help(h);
+    let h = "Write each row as soon as it is ready\n\
+             Useful when piping into a program that processes rows as they come. Columns are \
+             aligned for each row individually and empty columns are not hidden. \
+             `--last` still waits for the end of the log.";
+    let line_buffered = Arg::new("line_buffered").long("line-buffered")
+                                                 .action(SetTrue)
+                                                 .display_order(24)
+                                                 .help_heading("Format")
+                                                 .help(h.split_once('\n').unwrap().0)
+                                                 .long_help(h);
+    let h = "Output one JSON object per line\n\
+             Each event is written as soon as it is found, for processing with tools like `jq -c`. \
+             Durations are in seconds, dates follow `--date` (a number with `--date=unix`). Header \
+             and skip rows are not displayed.";
+    let json_lines = Arg::new("json_lines").long("json-lines")
+                                           .action(SetTrue)
+                                           .display_order(24)
+                                           .help_heading("Format")
+                                           .help(h.split_once('\n').unwrap().0)
+                                           .long_help(h);
+    let h = "Sort rows by date or by duration\n  \
+             (default)|date|t:   Chronological order\n  \
+             duration|dur|d:     Longest first, emerge runs last\n\
+             Rows are selected by `--first`, `--last`, `--skip`, etc in chronological order, then \
+             sorted. Skip rows are not displayed.";
+    let sort = Arg::new("sort").long("sort")
+                               .value_name("key")
+                               .value_parser(value_parser!(crate::config::LogSort))
+                               .hide_possible_values(true)
+                               .num_args(1)
+                               .display_order(24)
+                               .help_heading("Format")
+                               .help(h.split_once('\n').unwrap().0)
+                               .long_help(h);
+    let finish_times = Arg::new("finish_times").long("finish-times")
+                                               .value_name("bool")
+                                               .num_args(..=1)
+                                               .default_missing_value("y")
+                                               .display_order(24)
+                                               .help_heading("Format")
+                                               .help("Display predicted finish time of each merge");
+    let h = "Exit with code 3 if an emerge process is running\n\
+             This deviates from the usual exit codes (0: found something, 1: found nothing, 2: error), \
+             so that scripts can detect an ongoing merge without parsing the output.";
+    let status_code = Arg::new("status_code").long("status-code")
+                                             .action(SetTrue)
+                                             .display_order(26)
+                                             .help(h.split_once('\n').unwrap().0)
+                                             .long_help(h);
     let pwidth = Arg::new("pwidth").long("pwidth")
                                    .value_name("num")
                                    .num_args(1)
@@ -258,20 +824,86 @@ pub fn build_cli() -> Command {
                                  .help_heading("Format")
                                  .help("Enable color (yes/no/auto)")
                                  .long_help("Enable color (yes/no/auto)\n  \
-                                             (default)|auto|a: colored if on tty\n  \
+                                             (default)|auto|a: colored if on tty, see below\n  \
                                              (empty)|yes|y:    colored\n  \
-                                             no|n:             not colored");
+                                             no|n:             not colored\n\
+                                             In auto mode, a non-empty `NO_COLOR` disables color, then a non-empty \
+                                             `CLICOLOR_FORCE` (other than `0`) enables it, then `CLICOLOR=0` disables it.");
+    let theme_preset = Arg::new("theme_preset").long("theme-preset")
+                                               .value_name("theme")
+                                               .global(true)
+                                               .display_order(27)
+                                               .help_heading("Format")
+                                               .help("Color theme (dark/light/none)")
+                                               .long_help("Color theme (dark/light/none)\n  \
+                                                           (default)|dark|d: bright colors for dark terminals\n  \
+                                                           light|l:          darker colors for light terminals\n  \
+                                                           none|n:           same as `--color=no`\n\
+                                                           Ignored if color is disabled.");
+    let pager = Arg::new("pager").long("pager")
+                                 .value_name("when")
+                                 .global(true)
+                                 .num_args(..=1)
+                                 .default_missing_value("always")
+                                 .display_order(27)
+                                 .help_heading("Format")
+                                 .help("Page output through $PAGER (always/never/auto)")
+                                 .long_help("Page output through $PAGER (always/never/auto)\n  \
+                                             (default)|never|n:  write directly to stdout\n  \
+                                             (empty)|always|y:   page output\n  \
+                                             auto|a:             page output if on tty\n\
+                                             Uses `less` if $PAGER is unset. $LESS defaults to `FRX`, \
+                                             to keep colors and exit if the output fits on screen.");
+    let h = "Show the number of parsed lines while reading the log (yes/no)\n\
+             Displayed on stderr after half a second, and cleared before results are printed. \
+             Only used if stderr is a terminal, with `--output=columns`, without pager, and if rows \
+             aren't streamed by `log --line-buffered` or `--json-lines`.";
+    let progress = Arg::new("progress").long("progress")
+                                       .value_name("bool")
+                                       .global(true)
+                                       .num_args(..=1)
+                                       .default_missing_value("y")
+                                       .display_order(27)
+                                       .help_heading("Format")
+                                       .help(h.split_once('\n').unwrap().0)
+                                       .long_help(h);
+    let color_category = Arg::new("color_category").long("color-category")
+                                                   .value_name("bool")
+                                                   .global(true)
+                                                   .num_args(..=1)
+                                                   .default_missing_value("y")
+                                                   .display_order(27)
+                                                   .help_heading("Format")
+                                                   .help("Color package categories by name (yes/no)")
+                                                   .long_help("Color package categories by name (yes/no)\n\
+                                                               Each category always gets the same color.\n\
+                                                               Ignored if color is disabled.");
     let output = Arg::new("output").short('o')
                                    .long("output")
                                    .value_name("format")
                                    .global(true)
                                    .display_order(28)
                                    .help_heading("Format")
-                                   .help("Ouput format (columns/tab/auto)")
-                                   .long_help("Ouput format (columns/tab/auto)\n  \
+                                   .help("Ouput format (columns/tab/tsv/auto)")
+                                   .long_help("Ouput format (columns/tab/tsv/auto)\n  \
                                                (default)|auto|a: columns on tty, tab otherwise\n  \
                                                columns|c:        space-aligned columns\n  \
-                                               tab|t:            tab-separated values");
+                                               tab|t:            tab-separated values\n  \
+                                               tsv:              tab-separated values, never colored\n  \
+                                               prometheus|p:     metrics for node_exporter's \
+                                               textfile collector (stats only)");
+    let h = "Write output to <file> instead of stdout\n\
+             The file is truncated first. Color, pager, and output format default to their non-tty \
+             behavior.";
+    let outfile = Arg::new("outfile").long("out")
+                                     .value_name("file")
+                                     .global(true)
+                                     .num_args(1)
+                                     .value_parser(value_parser!(PathBuf))
+                                     .display_order(28)
+                                     .help_heading("Format")
+                                     .help(h.split_once('\n').unwrap().0)
+                                     .long_help(h);
     let h = "Show placeholder for skipped rows (yes/no)\n  \
              (empty)|yes|y: Show 'skip <num>' placeholder\n  \
              no|n:          Skip rows silently";
@@ -284,6 +916,17 @@ pub fn build_cli() -> Command {
                                        .help_heading("Format")
                                        .help(h.split_once('\n').unwrap().0)
                                        .long_help(h);
+    let h = "Template of the placeholder for skipped rows\n\
+             `{n}` is replaced by the number of skipped rows, and `{kind}` by `first`, `last`, or \
+             `below`. Defaults to `(skip {kind} {n})`, or `(skip {n} below)` for process trees.";
+    let skip_fmt = Arg::new("skip_fmt").long("skip-fmt")
+                                       .value_name("template")
+                                       .global(true)
+                                       .num_args(1)
+                                       .display_order(29)
+                                       .help_heading("Format")
+                                       .help(h.split_once('\n').unwrap().0)
+                                       .long_help(h);
 
     ////////////////////////////////////////////////////////////
     // Misc arguments
@@ -294,7 +937,32 @@ pub fn build_cli() -> Command {
                                      .global(true)
                                      .num_args(1)
                                      .display_order(30)
-                                     .help("Location of emerge log file");
+                                     .help("Location of emerge log file")
+                                     .long_help("Location of emerge log file\n\
+                                                 Files ending in `.gz` are decompressed. With the `ssh` \
+                                                 cargo feature, `ssh://<host>/<path>` reads a remote file.");
+    let h = "Also treat lines matching <regex> as emerge command starts\n\
+             Useful when emerge is called by a wrapper that logs its own marker. \
+             Matched against the log line without its timestamp. \
+             A capture group named `args` is used as the command arguments.\n\
+             See https://docs.rs/regex/*/regex/#syntax";
+    let run_regex = Arg::new("run_regex").long("run-regex")
+                                         .value_name("regex")
+                                         .global(true)
+                                         .num_args(1)
+                                         .display_order(30)
+                                         .help(h.split_once('\n').unwrap().0)
+                                         .long_help(h);
+    let h = "Warn if the newest log entry is more than 30 days old\n\
+             Helps notice a stale or rotated log file. Not checked when `--to` is given.";
+    let staleness_check = Arg::new("staleness_check").long("staleness-check")
+                                                     .value_name("bool")
+                                                     .global(true)
+                                                     .num_args(..=1)
+                                                     .default_missing_value("y")
+                                                     .display_order(30)
+                                                     .help(h.split_once('\n').unwrap().0)
+                                                     .long_help(h);
     let tmpdir = Arg::new("tmpdir").long("tmpdir")
                                    .value_name("dir")
                                    .num_args(1)
@@ -314,6 +982,29 @@ pub fn build_cli() -> Command {
                                                  -v:   show warnings\n  \
                                                  -vv:  show info\n  \
                                                  -vvv: show debug");
+    let quiet = Arg::new("quiet").short('q')
+                                 .long("quiet")
+                                 .global(true)
+                                 .action(SetTrue)
+                                 .display_order(33)
+                                 .help("Silence all log messages, including errors")
+                                 .long_help("Silence all log messages, including errors\n\
+                                             Takes precedence over `-v`. Failures are still \
+                                             reported by the exit code.");
+    let h = "Print the resolved configuration and exit\n\
+             Shows the result of merging defaults, the config file, and the command line, to \
+             debug option interactions.";
+    let dump_config = Arg::new("dump_config").long("dump-config")
+                                             .global(true)
+                                             .action(SetTrue)
+                                             .display_order(36)
+                                             .help(h.split_once('\n').unwrap().0)
+                                             .long_help(h);
+    let helpall = Arg::new("helpall").long("help-all")
+                                     .action(SetTrue)
+                                     .exclusive(true)
+                                     .display_order(36)
+                                     .help("Print long help of all commands");
     #[cfg(feature = "clap_complete")]
     let shell =
         Arg::new("shell").long("shell")
@@ -327,6 +1018,11 @@ pub fn build_cli() -> Command {
                                 .long_help(h)
                                 .num_args(1)
                                 .display_order(35);
+    let null = Arg::new("null").short('0')
+                               .long("null")
+                               .action(SetTrue)
+                               .display_order(36)
+                               .help("Separate package names with NUL instead of newline");
 
     ////////////////////////////////////////////////////////////
     // Subcommands
@@ -337,54 +1033,117 @@ pub fn build_cli() -> Command {
     let cmd_log = Command::new("log").about(h.split_once('\n').unwrap().0)
                                      .long_about(h)
                                      .arg(starttime)
+                                     .arg(downgrades)
+                                     .arg(coalesce)
+                                     .arg(target_root)
+                                     .arg(line_buffered)
+                                     .arg(json_lines)
+                                     .arg(sort)
                                      .arg(&first)
                                      .arg(&last)
+                                     .arg(tail)
+                                     .arg(every)
+                                     .arg(since_version)
+                                     .arg(skip)
+                                     .arg(take)
+                                     .arg(&reinstalls)
+                                     .arg(&installed)
+                                     .arg(&binary)
                                      .arg(show_l)
                                      .arg(&exact)
+                                     .arg(&glob)
+                                     .arg(&invert)
+                                     .arg(&repo)
+                                     .arg(&pkg_file)
                                      .arg(&pkg);
     let h = "Predict merge times for current or pretended merges\n\
              * If input is a terminal, predict times for the current merges (if any)\n\
              * If input is a pipe (for example by running `emerge -rOp|emlop p`), \
-             predict times for those merges.";
+             predict times for those merges.\n\
+             * If `--pretend-file` or `--plan-json` is given, predict times for the merges listed in \
+             those files.";
     let cmd_pred = Command::new("predict").about(h.split_once('\n').unwrap().0)
                                           .long_about(h)
                                           .arg(show_p)
-                                          .arg(first)
+                                          .arg(no_total)
+                                          .arg(&first)
                                           .arg(&last)
                                           .arg(tmpdir)
+                                          .arg(pretend)
+                                          .arg(plan_json)
+                                          .arg(atoms)
+                                          .arg(pretend_since)
+                                          .arg(pred_from)
+                                          .arg(pred_to)
                                           .arg(resume)
+                                          .arg(resume_backup)
                                           .arg(unknown)
+                                          .arg(scale)
+                                          .arg(finish_times)
+                                          .arg(status_code)
                                           .arg(pwidth)
                                           .arg(pdepth)
                                           .arg(&avg)
-                                          .arg(&limit);
+                                          .arg(&limit)
+                                          .arg(&min_samples);
     let h = "Show statistics about syncs, per-package (un)merges, and total (un)merges\n\
              * Sync:      count,       total time, predicted time\n\
              * <package>: merge count, total time, predicted time, unmerge count, total time, predicted time\n\
-             * Total:     merge count, total time, average time,   unmerge count, total time, average time";
+             * Total:     merge count, [source/binary merge count], total time, average time, \
+             unmerge count, total time, average time, wall time, [active days, daily time], \
+             [binary savings]";
     let cmd_stats = Command::new("stats").about(h.split_once('\n').unwrap().0)
                                          .long_about(h)
                                          .arg(show_s)
+                                         .arg(summary)
+                                         .arg(group_regex)
+                                         .arg(by_version)
+                                         .arg(histogram)
+                                         .arg(clock)
+                                         .arg(overview)
+                                         .arg(count)
+                                         .arg(active_days)
+                                         .arg(compare)
+                                         .arg(csv_series)
+                                         .arg(when)
+                                         .arg(top)
+                                         .arg(first)
+                                         .arg(&last)
+                                         .arg(no_predict)
+                                         .arg(live)
                                          .arg(group)
+                                         .arg(relative_groups)
                                          .arg(&exact)
+                                         .arg(&glob)
+                                         .arg(&invert)
+                                         .arg(repo)
+                                         .arg(pkg_file)
+                                         .arg(preserved)
+                                         .arg(reinstalls)
+                                         .arg(installed)
+                                         .arg(binary)
                                          .arg(&pkg)
                                          .arg(&avg)
-                                         .arg(&limit);
+                                         .arg(&limit)
+                                         .arg(min_samples);
     let h = "Compare actual merge time against predicted merge time\n\
              Use this to gauge the effect of the --limit and --avg options";
     let cmd_accuracy = Command::new("accuracy").about(h.split_once('\n').unwrap().0)
                                                .long_about(h)
                                                .arg(pkg)
                                                .arg(exact)
+                                               .arg(glob)
+                                               .arg(invert)
                                                .arg(show_a)
                                                .arg(last)
                                                .arg(avg)
                                                .arg(limit);
     #[cfg(feature = "clap_complete")]
     let cmd_complete =
-        Command::new("complete").about("Shell completion helper").arg(shell).arg(onepkg);
+        Command::new("complete").about("Shell completion helper").arg(shell).arg(onepkg).arg(null);
     #[cfg(not(feature = "clap_complete"))]
-    let cmd_complete = Command::new("complete").about("Shell completion helper").arg(onepkg);
+    let cmd_complete =
+        Command::new("complete").about("Shell completion helper").arg(onepkg).arg(null);
 
     ////////////////////////////////////////////////////////////
     // Main command
@@ -395,6 +1154,7 @@ pub fn build_cli() -> Command {
         concat!("Commands and long args can be abbreviated (eg `emlop l -ss --head -f1w`)\n\
                  Commands have their own -h / --help\n\
                  Exit code is 0 if sucessful, 1 if search found nothing, 2 in case of other errors\n\
+                 (and 3 if `predict --status-code` found a running emerge)\n\
                  Config can be set in $HOME/.config/emlop.toml\n\
                  See readme, changelog, and sample config in /usr/share/doc/emlop-",
                 crate_version!(), "/");
@@ -410,21 +1170,37 @@ pub fn build_cli() -> Command {
                          .infer_subcommands(true)
                          .infer_long_args(true)
                          .arg_required_else_help(true)
+                         .override_usage("emlop [OPTIONS] <COMMAND>")
                          .styles(styles)
-                         .subcommand_required(true)
                          .about(about)
                          .after_help(after_help)
                          .arg(from)
+                         .arg(since_sync)
+                         .arg(between_syncs)
                          .arg(to)
+                         .arg(to_exclusive)
                          .arg(header)
                          .arg(duration)
+                         .arg(duration_parts)
                          .arg(date)
                          .arg(utc)
+                         .arg(tz)
                          .arg(color)
+                         .arg(color_category)
+                         .arg(theme_preset)
+                         .arg(pager)
+                         .arg(progress)
                          .arg(output)
+                         .arg(outfile)
                          .arg(logfile)
+                         .arg(run_regex)
+                         .arg(staleness_check)
                          .arg(verbose)
+                         .arg(quiet)
                          .arg(showskip)
+                         .arg(skip_fmt)
+                         .arg(dump_config)
+                         .arg(helpall)
                          .subcommand(cmd_log)
                          .subcommand(cmd_pred)
                          .subcommand(cmd_stats)
@@ -432,6 +1208,18 @@ pub fn build_cli() -> Command {
                          .subcommand(cmd_complete)
 }
 
+/// Print long help of main command and all subcommands, for `--help-all`.
+pub fn print_help_all() {
+    let mut cli = build_cli();
+    cli.build();
+    let mut out = std::io::stdout().lock();
+    write!(out, "{}", cli.render_long_help()).unwrap_or(());
+    for sub in cli.get_subcommands_mut() {
+        let title = format!("emlop {}", sub.get_name());
+        write!(out, "\n\n{title}\n{}\n\n{}", "=".repeat(title.len()), sub.render_long_help())
+            .unwrap_or(());
+    }
+}
 
 #[cfg(test)]
 mod test {
@@ -440,7 +1228,7 @@ mod test {
     fn matches(args: &str) -> clap::ArgMatches {
         build_cli().get_matches_from(format!("emlop {args}").split_whitespace())
                    .subcommand()
-                   .expect(&format!("Failed parsing {args:?}"))
+                   .unwrap_or_else(|| panic!("Failed parsing {args:?}"))
                    .1
                    .clone()
     }
@@ -472,5 +1260,7 @@ mod test {
         let pathvec = |s: &str| Some(s.split_whitespace().map(PathBuf::from).collect());
         assert_eq!(many!(PathBuf, "tmpdir", "p --tmpdir a"), pathvec("a"));
         assert_eq!(many!(PathBuf, "tmpdir", "p --tmpdir a --tmpdir b"), pathvec("a b"));
+        assert_eq!(many!(PathBuf, "pretend", "p --pretend-file a --pretend-file b"),
+                   pathvec("a b"));
     }
 }
@@ -6,23 +6,33 @@ use std::{env::var, fs::File, io::Read, path::PathBuf};
 pub struct TomlLog {
     pub show: Option<String>,
     pub starttime: Option<bool>,
+    pub downgrades: Option<bool>,
+    pub coalesce_updates: Option<bool>,
+    pub target_root: Option<bool>,
 }
 #[derive(Deserialize, Debug)]
 pub struct TomlPred {
     pub show: Option<String>,
     pub avg: Option<String>,
     pub limit: Option<i64>,
+    pub min_samples: Option<i64>,
     pub unknown: Option<i64>,
+    pub scale: Option<f64>,
     pub tmpdir: Option<Vec<PathBuf>>,
     pub pwidth: Option<i64>,
     pub pdepth: Option<i64>,
+    pub finish_times: Option<bool>,
+    pub resume_backup: Option<bool>,
 }
 #[derive(Deserialize, Debug)]
 pub struct TomlStats {
     pub show: Option<String>,
     pub avg: Option<String>,
     pub limit: Option<i64>,
+    pub min_samples: Option<i64>,
     pub group: Option<String>,
+    pub relative_groups: Option<bool>,
+    pub group_regex: Option<String>,
 }
 #[derive(Deserialize, Debug)]
 pub struct TomlAccuracy {
@@ -33,13 +43,22 @@ pub struct TomlAccuracy {
 #[derive(Deserialize, Debug, Default)]
 pub struct Toml {
     pub logfile: Option<String>,
+    pub run_regex: Option<String>,
+    pub staleness_check: Option<bool>,
     pub date: Option<String>,
     pub duration: Option<String>,
+    pub duration_parts: Option<i64>,
     pub header: Option<bool>,
     pub showskip: Option<bool>,
+    pub skip_fmt: Option<String>,
     pub utc: Option<bool>,
+    pub tz: Option<String>,
     pub color: Option<String>,
+    pub color_category: Option<bool>,
+    pub theme_preset: Option<String>,
     pub output: Option<String>,
+    pub pager: Option<String>,
+    pub progress: Option<bool>,
     pub log: Option<TomlLog>,
     pub predict: Option<TomlPred>,
     pub stats: Option<TomlStats>,
@@ -43,6 +43,23 @@ impl ArgParse<i64, RangeInclusive<i64>> for i64 {
         }
     }
 }
+impl ArgParse<String, RangeInclusive<f64>> for f64 {
+    fn parse(s: &String, r: RangeInclusive<f64>, src: &'static str) -> Result<Self, ArgError> {
+        let f = f64::from_str(s).map_err(|_| ArgError::new(s, src).msg("Not a number"))?;
+        Self::parse(&f, r, src)
+    }
+}
+impl ArgParse<f64, RangeInclusive<f64>> for f64 {
+    fn parse(f: &f64, r: RangeInclusive<f64>, src: &'static str) -> Result<Self, ArgError> {
+        if r.contains(f) {
+            Ok(*f)
+        } else {
+            Err(ArgError::new(f, src).msg(format!("Should be between {} and {}",
+                                                  r.start(),
+                                                  r.end())))
+        }
+    }
+}
 
 
 /// Argument parsing error
@@ -102,12 +119,13 @@ impl std::fmt::Display for ArgError {
 }
 
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum Average {
     Arith,
     Median,
     WeightedArith,
     WeightedMedian,
+    Percentile(u8),
 }
 impl ArgParse<String, ()> for Average {
     fn parse(v: &String, _: (), s: &'static str) -> Result<Self, ArgError> {
@@ -116,7 +134,14 @@ impl ArgParse<String, ()> for Average {
             "m" | "median" => Ok(Self::Median),
             "wa" | "weighted-arith" => Ok(Self::WeightedArith),
             "wm" | "weighted-median" => Ok(Self::WeightedMedian),
-            _ => Err(ArgError::new(v, s).pos("(a)rith (m)edian wa/weightedarith wm/weigtedmedian")),
+            p if p.starts_with('p') => match p[1..].parse() {
+                Ok(n @ 1..=100) => Ok(Self::Percentile(n)),
+                _ => Err(ArgError::new(v, s).msg("Percentile should be between p1 and p100")),
+            },
+            _ => {
+                let pos = "(a)rith (m)edian wa/weightedarith wm/weigtedmedian p<num>";
+                Err(ArgError::new(v, s).pos(pos))
+            },
         }
     }
 }
@@ -135,12 +160,30 @@ pub enum ResumeKind {
     No,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogSort {
+    #[clap(alias("t"))]
+    Date,
+    #[clap(alias("d"), alias("dur"))]
+    Duration,
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum When {
+    #[clap(alias("f"))]
+    First,
+    #[clap(alias("l"))]
+    Last,
+}
+
+#[derive(Clone, Copy, Debug)]
 pub enum DurationStyle {
     Hms,
     HmsFixed,
     Secs,
     Human,
+    Iso8601,
+    Hours,
 }
 impl ArgParse<String, ()> for DurationStyle {
     fn parse(v: &String, _: (), s: &'static str) -> Result<Self, ArgError> {
@@ -149,16 +192,18 @@ impl ArgParse<String, ()> for DurationStyle {
             "hmsfixed" => Ok(Self::HmsFixed),
             "s" | "secs" => Ok(Self::Secs),
             "h" | "human" => Ok(Self::Human),
-            _ => Err(ArgError::new(v, s).pos("hms hmsfixed (s)ecs (h)uman")),
+            "iso" => Ok(Self::Iso8601),
+            "hours" | "h2" => Ok(Self::Hours),
+            _ => Err(ArgError::new(v, s).pos("hms hmsfixed (s)ecs (h)uman iso hours")),
         }
     }
 }
 
 pub type ColorStyle = bool;
 impl ArgParse<String, bool> for ColorStyle {
-    fn parse(v: &String, isterm: bool, s: &'static str) -> Result<Self, ArgError> {
+    fn parse(v: &String, auto: bool, s: &'static str) -> Result<Self, ArgError> {
         match v.as_str() {
-            "auto" | "a" => Ok(isterm),
+            "auto" | "a" => Ok(auto),
             "yes" | "y" => Ok(true),
             "no" | "n" => Ok(false),
             _ => Err(ArgError::new(v, s).pos("(y)es (n)o (a)uto")),
@@ -166,10 +211,99 @@ impl ArgParse<String, bool> for ColorStyle {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PagerStyle {
+    Always,
+    Never,
+    Auto,
+}
+impl ArgParse<String, ()> for PagerStyle {
+    fn parse(v: &String, _: (), s: &'static str) -> Result<Self, ArgError> {
+        match v.as_str() {
+            "always" | "yes" | "y" => Ok(Self::Always),
+            "never" | "no" | "n" => Ok(Self::Never),
+            "auto" | "a" => Ok(Self::Auto),
+            _ => Err(ArgError::new(v, s).pos("always|(y)es never|(n)o (a)uto")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum BinFilter {
+    Both,
+    Only,
+    Exclude,
+}
+impl ArgParse<String, ()> for BinFilter {
+    fn parse(v: &String, _: (), s: &'static str) -> Result<Self, ArgError> {
+        match v.as_str() {
+            "both" | "b" => Ok(Self::Both),
+            "only" | "o" => Ok(Self::Only),
+            "exclude" | "e" => Ok(Self::Exclude),
+            _ => Err(ArgError::new(v, s).pos("(b)oth (o)nly (e)xclude")),
+        }
+    }
+}
+impl BinFilter {
+    /// Whether a binary (or source) merge should be kept
+    pub const fn keep(self, bin: bool) -> bool {
+        match self {
+            Self::Both => true,
+            Self::Only => bin,
+            Self::Exclude => !bin,
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    Dark,
+    Light,
+    None,
+}
+impl ArgParse<String, ()> for Theme {
+    fn parse(v: &String, _: (), s: &'static str) -> Result<Self, ArgError> {
+        match v.as_str() {
+            "dark" | "d" => Ok(Self::Dark),
+            "light" | "l" => Ok(Self::Light),
+            "none" | "n" => Ok(Self::None),
+            _ => Err(ArgError::new(v, s).pos("(d)ark (l)ight (n)one")),
+        }
+    }
+}
+impl Theme {
+    /// Colors for pkg, merge, binmerge, unmerge, dur, skip, cnt, in_time and overtime
+    pub const fn colors(self) -> [&'static str; 9] {
+        match self {
+            Self::Dark => ["\x1B[1;32m",
+                           "\x1B[1;32m",
+                           "\x1B[1;36m",
+                           "\x1B[1;31m",
+                           "\x1B[1;35m",
+                           "\x1B[37m",
+                           "\x1B[33m",
+                           "\x1B[32m",
+                           "\x1B[31m"],
+            Self::Light => ["\x1B[0;32m",
+                            "\x1B[0;32m",
+                            "\x1B[0;36m",
+                            "\x1B[0;31m",
+                            "\x1B[0;35m",
+                            "\x1B[90m",
+                            "\x1B[34m",
+                            "\x1B[0;32m",
+                            "\x1B[0;31m"],
+            Self::None => ["", ">>> ", ">>> ", "<<< ", "", "", "", "", ""],
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum OutStyle {
     Columns,
     Tab,
+    Tsv,
+    Prometheus,
 }
 impl ArgParse<String, bool> for OutStyle {
     fn parse(v: &String, isterm: bool, s: &'static str) -> Result<Self, ArgError> {
@@ -177,12 +311,14 @@ impl ArgParse<String, bool> for OutStyle {
             "auto" | "a" => Ok(if isterm { Self::Columns } else { Self::Tab }),
             "tab" | "t" => Ok(Self::Tab),
             "columns" | "c" => Ok(Self::Columns),
-            _ => Err(ArgError::new(v, s).pos("(c)olumns (t)ab (a)uto")),
+            "tsv" => Ok(Self::Tsv),
+            "prometheus" | "p" => Ok(Self::Prometheus),
+            _ => Err(ArgError::new(v, s).pos("(c)olumns (t)ab tsv (p)rometheus (a)uto")),
         }
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct Show {
     pub run: bool,
     pub pkg: bool,
@@ -204,6 +340,9 @@ impl Show {
     pub const fn mt() -> Self {
         Self { run: false, pkg: false, tot: true, sync: false, merge: true, unmerge: false }
     }
+    pub const fn st() -> Self {
+        Self { run: false, pkg: false, tot: true, sync: true, merge: false, unmerge: false }
+    }
 }
 impl ArgParse<String, &'static str> for Show {
     fn parse(show: &String, valid: &'static str, src: &'static str) -> Result<Self, ArgError> {
@@ -10,16 +10,85 @@ use std::{io::Write as _,
 use time::{macros::format_description, parsing::Parsed, Date, Duration, Month, OffsetDateTime,
            UtcOffset, Weekday};
 
-/// Get the UtcOffset to parse/display datetimes with.
-/// Needs to be called before starting extra threads.
-pub fn get_offset(utc: bool) -> UtcOffset {
-    if utc {
-        UtcOffset::UTC
-    } else {
-        UtcOffset::current_local_offset().unwrap_or_else(|e| {
-                                             warn!("Falling back to UTC: {}", e);
-                                             UtcOffset::UTC
-                                         })
+/// Timezone to parse/display datetimes with.
+///
+/// Unlike a plain `UtcOffset`, this gives the correct offset for each timestamp in timezones with
+/// daylight saving time.
+pub struct Tz(tz::TimeZone);
+impl Tz {
+    /// Load the local timezone from `$TZ` or `/etc/localtime`, or use UTC.
+    pub fn new(utc: bool) -> Self {
+        if utc {
+            return Self(tz::TimeZone::utc());
+        }
+        let res = match std::env::var("TZ") {
+            Ok(s) if !s.is_empty() => tz::TimeZone::from_posix_tz(&s),
+            _ => tz::TimeZone::local(),
+        };
+        Self(res.unwrap_or_else(|e| {
+                    warn!("Falling back to UTC: {}", e);
+                    tz::TimeZone::utc()
+                }))
+    }
+
+    /// Offset at the given timestamp.
+    pub fn offset(&self, ts: i64) -> UtcOffset {
+        self.0
+            .find_local_time_type(ts)
+            .ok()
+            .and_then(|t| UtcOffset::from_whole_seconds(t.ut_offset()).ok())
+            .unwrap_or(UtcOffset::UTC)
+    }
+
+    /// Local hour of the day (0-23) at the given timestamp.
+    pub fn hour(&self, ts: i64) -> usize {
+        OffsetDateTime::from_unix_timestamp(ts).unwrap().to_offset(self.offset(ts)).hour() as usize
+    }
+
+    /// Local day number (days since the epoch) at the given timestamp.
+    pub fn day(&self, ts: i64) -> i64 {
+        (ts + self.offset(ts).whole_seconds() as i64).div_euclid(86400)
+    }
+
+    /// Convert a local time (expressed as if it was UTC) to a timestamp.
+    ///
+    /// Ambiguous or skipped times around DST changes resolve to one of the possible offsets.
+    fn local_to_ts(&self, local: i64) -> i64 {
+        let guess = local - self.offset(local).whole_seconds() as i64;
+        local - self.offset(guess).whole_seconds() as i64
+    }
+}
+/// Show the current offset, the full timezone rules are too verbose
+impl std::fmt::Debug for Tz {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Tz").field(&self.offset(epoch_now())).finish()
+    }
+}
+impl ArgParse<String, ()> for Tz {
+    fn parse(v: &String, _: (), s: &'static str) -> Result<Self, ArgError> {
+        match v.as_str() {
+            "local" | "l" => Ok(Self::new(false)),
+            "utc" | "u" => Ok(Self::new(true)),
+            o => {
+                let err = || ArgError::new(v, s).pos("(l)ocal (u)tc +HH:MM -HH:MM");
+                let (sign, hm) = match (o.strip_prefix('+'), o.strip_prefix('-')) {
+                    (Some(hm), _) => (1, hm),
+                    (_, Some(hm)) => (-1, hm),
+                    _ => return Err(err()),
+                };
+                let (h, m) = hm.split_once(':').unwrap_or((hm, "0"));
+                let num =
+                    |n: &str| n.bytes().all(|b| b.is_ascii_digit()).then(|| i8::from_str(n).ok());
+                let (Some(Some(h)), Some(Some(m))) = (num(h), num(m)) else { return Err(err()) };
+                let off = UtcOffset::from_hms(sign * h, sign * m, 0).map_err(|_| err())?;
+                Ok(Self::from(off))
+            },
+        }
+    }
+}
+impl From<UtcOffset> for Tz {
+    fn from(o: UtcOffset) -> Self {
+        Self(tz::TimeZone::fixed(o.whole_seconds()).unwrap())
     }
 }
 
@@ -32,6 +101,24 @@ impl Default for DateStyle {
         Self(format_description!("[year]-[month]-[day] [hour]:[minute]:[second]"))
     }
 }
+/// Show an example date, the format description is too verbose
+impl std::fmt::Debug for DateStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.is_unix() {
+            true => f.write_str("DateStyle(unix)"),
+            false => {
+                let d = OffsetDateTime::UNIX_EPOCH.format(&self.0).map_err(|_| std::fmt::Error)?;
+                f.debug_tuple("DateStyle").field(&d).finish()
+            },
+        }
+    }
+}
+impl DateStyle {
+    /// Whether dates are displayed as unix timestamps
+    pub const fn is_unix(&self) -> bool {
+        self.0.is_empty()
+    }
+}
 impl ArgParse<String, ()> for DateStyle {
     fn parse(s: &String, _: (), src: &'static str) -> Result<Self, ArgError> {
         Ok(Self(match s.as_str() {
@@ -62,7 +149,7 @@ impl Disp for FmtDate {
             write!(buf, "{}", self.0).expect("write to buf");
         } else {
             OffsetDateTime::from_unix_timestamp(self.0).expect("unix from i64")
-                                                       .to_offset(conf.date_offset)
+                                                       .to_offset(conf.tz.offset(self.0))
                                                        .format_into(buf, &conf.date_fmt.0)
                                                        .expect("write to buf");
         }
@@ -74,31 +161,37 @@ pub fn epoch_now() -> i64 {
     SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64
 }
 
-#[cfg_attr(test, derive(PartialEq, Debug))]
-#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Clone, Copy, Debug)]
 pub enum TimeBound {
     /// Unbounded
     None,
     /// Bound by unix timestamp
     Unix(i64),
-    /// Bound by time of nth fist/last emerge run
-    Run(usize),
+    /// Bound by time of nth first emerge run
+    RunFirst(usize),
+    /// Bound by time of nth last emerge run
+    RunLast(usize),
+    /// Bound by time of the last completed sync
+    SyncLast,
+    /// Bound by time of the completed sync before the last one
+    SyncPrev,
 }
 
 /// Parse datetime in various formats, returning unix timestamp
-impl ArgParse<String, UtcOffset> for TimeBound {
-    fn parse(val: &String, offset: UtcOffset, src: &'static str) -> Result<Self, ArgError> {
+impl ArgParse<String, &Tz> for TimeBound {
+    fn parse(val: &String, tz: &Tz, src: &'static str) -> Result<Self, ArgError> {
         let s = val.trim();
         let et = match i64::from_str(s) {
             Ok(i) => return Ok(Self::Unix(i)),
             Err(et) => et,
         };
-        let ea = match parse_date_yyyymmdd(s, offset) {
+        let ea = match parse_date_yyyymmdd(s, tz) {
             Ok(i) => return Ok(Self::Unix(i)),
             Err(ea) => ea,
         };
         let ec = match parse_command_num(s) {
-            Ok(i) => return Ok(Self::Run(i)),
+            Ok(i) => return Ok(i),
             Err(ea) => ea,
         };
         match parse_date_ago(s) {
@@ -112,14 +205,24 @@ impl ArgParse<String, UtcOffset> for TimeBound {
 }
 
 /// Parse a command index (parse as 1-based, return as 0-based)
-fn parse_command_num(s: &str) -> Result<usize, Error> {
+///
+/// Count from the start (`1c`), or from the end with a `-` prefix or an uppercase `C` (`-1c`,
+/// `1C`). The direction is the same for `--from` and `--to`.
+fn parse_command_num(s: &str) -> Result<TimeBound, Error> {
     use atoi::FromRadix10;
+    let (neg, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
     let (num, pos) = usize::from_radix_10(s.as_bytes());
-    match s[pos..].trim() {
-        "c" | "command" | "commands" if num > 0 => Ok(num - 1),
-        "c" | "command" if pos == 0 => Ok(0),
+    let (num, last) = match s[pos..].trim() {
+        "c" | "command" | "commands" if num > 0 => (num - 1, neg),
+        "C" if num > 0 => (num - 1, true),
+        "c" | "command" if pos == 0 => (0, neg),
+        "C" if pos == 0 => (0, true),
         _ => bail!("bad span {:?}", &s[pos..]),
-    }
+    };
+    Ok(if last { TimeBound::RunLast(num) } else { TimeBound::RunFirst(num) })
 }
 
 /// Parse a number of day/years/hours/etc in the past, relative to current time
@@ -172,7 +275,7 @@ fn parse_date_span(num: i32, span: &str, now: OffsetDateTime) -> Result<OffsetDa
 }
 
 /// Parse rfc3339-like format with added flexibility
-fn parse_date_yyyymmdd(s: &str, offset: UtcOffset) -> Result<i64, Error> {
+fn parse_date_yyyymmdd(s: &str, tz: &Tz) -> Result<i64, Error> {
     use time::format_description::{modifier::*, Component, FormatItem::*};
     let mut p = Parsed::new().with_hour_24(0)
                              .unwrap()
@@ -180,11 +283,7 @@ fn parse_date_yyyymmdd(s: &str, offset: UtcOffset) -> Result<i64, Error> {
                              .unwrap()
                              .with_second(0)
                              .unwrap()
-                             .with_offset_hour(offset.whole_hours())
-                             .unwrap()
-                             .with_offset_minute_signed(offset.minutes_past_hour())
-                             .unwrap()
-                             .with_offset_second_signed(offset.seconds_past_minute())
+                             .with_offset_hour(0)
                              .unwrap();
     // See <https://github.com/time-rs/time/issues/428>
     let rest = p.parse_items(s.as_bytes(), &[
@@ -210,10 +309,10 @@ fn parse_date_yyyymmdd(s: &str, offset: UtcOffset) -> Result<i64, Error> {
     if !rest.is_empty() {
         bail!("junk at end")
     }
-    Ok(OffsetDateTime::try_from(p)?.unix_timestamp())
+    Ok(tz.local_to_ts(OffsetDateTime::try_from(p)?.unix_timestamp()))
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum Timespan {
     Year,
     Month,
@@ -235,8 +334,8 @@ impl ArgParse<String, ()> for Timespan {
 }
 impl Timespan {
     /// Given a unix timestamp, advance to the beginning of the next year/month/week/day.
-    pub fn next(&self, ts: i64, offset: UtcOffset) -> i64 {
-        let d = OffsetDateTime::from_unix_timestamp(ts).unwrap().to_offset(offset).date();
+    pub fn next(&self, ts: i64, tz: &Tz) -> i64 {
+        let d = OffsetDateTime::from_unix_timestamp(ts).unwrap().to_offset(tz.offset(ts)).date();
         let d2 = match self {
             Self::Year => Date::from_calendar_date(d.year() + 1, Month::January, 1).unwrap(),
             Self::Month => {
@@ -258,13 +357,13 @@ impl Timespan {
             Self::Day => d.checked_add(Duration::DAY).unwrap(),
             Self::None => panic!("Called next() on a Timespan::None"),
         };
-        let res = d2.with_hms(0, 0, 0).unwrap().assume_offset(offset).unix_timestamp();
+        let res = tz.local_to_ts(d2.midnight().assume_utc().unix_timestamp());
         debug!("{} + {} = {}", fmt_utctime(ts), self.name(), fmt_utctime(res));
         res
     }
 
-    pub fn at(&self, ts: i64, offset: UtcOffset) -> String {
-        let d = OffsetDateTime::from_unix_timestamp(ts).unwrap().to_offset(offset);
+    pub fn at(&self, ts: i64, tz: &Tz) -> String {
+        let d = OffsetDateTime::from_unix_timestamp(ts).unwrap().to_offset(tz.offset(ts));
         match self {
             Self::Year => d.format(format_description!("[year]")).unwrap(),
             Self::Month => d.format(format_description!("[year]-[month]")).unwrap(),
@@ -274,6 +373,25 @@ impl Timespan {
         }
     }
 
+    /// Like `at()`, but the current and previous groups (relative to `now`) get a relative label
+    pub fn at_relative(&self, ts: i64, now: i64, tz: &Tz) -> String {
+        let (this, last) = match self {
+            Self::Year => ("this year", "last year"),
+            Self::Month => ("this month", "last month"),
+            Self::Week => ("this week", "last week"),
+            Self::Day => ("today", "yesterday"),
+            Self::None => return String::new(),
+        };
+        let (at, at_now) = (self.at(ts, tz), self.at(now, tz));
+        if at == at_now {
+            this.into()
+        } else if self.at(self.next(ts, tz), tz) == at_now {
+            last.into()
+        } else {
+            at
+        }
+    }
+
     pub const fn name(&self) -> &'static str {
         match self {
             Self::Year => "Year",
@@ -289,10 +407,15 @@ impl Timespan {
 pub struct FmtDur(pub i64);
 impl crate::table::Disp for FmtDur {
     fn out(&self, buf: &mut Vec<u8>, conf: &Conf) -> usize {
+        self.out_color(buf, conf, conf.dur.val)
+    }
+}
+impl FmtDur {
+    /// Like `Disp::out()`, using a specific color instead of the duration color
+    pub fn out_color(&self, buf: &mut Vec<u8>, conf: &Conf, dur: &str) -> usize {
         use std::io::Write;
         use DurationStyle::*;
         let sec = self.0;
-        let dur = conf.dur.val;
         let start = buf.len();
         match conf.dur_t {
             _ if sec < 0 => wtb!(buf, "{dur}?"),
@@ -309,13 +432,38 @@ impl crate::table::Disp for FmtDur {
                          (sec % 3600 / 60, "minute"),
                          (sec % 60, "second")];
                 let mut prefix = dur;
-                for (num, what) in a.into_iter().filter(|(n, _)| *n > 0) {
+                for (num, what) in a.into_iter().filter(|(n, _)| *n > 0).take(conf.dur_parts) {
                     wtb!(buf, "{prefix}{num} {what}{}", if num > 1 { "s" } else { "" });
                     prefix = ", ";
                 }
             },
+            Iso8601 if sec == 0 => wtb!(buf, "{dur}PT0S"),
+            Iso8601 => {
+                wtb!(buf, "{dur}PT");
+                for (num, what) in [(sec / 3600, 'H'), (sec % 3600 / 60, 'M'), (sec % 60, 'S')] {
+                    if num > 0 {
+                        wtb!(buf, "{num}{what}");
+                    }
+                }
+            },
+            Hours => wtb!(buf, "{dur}{:.2}", sec as f64 / 3600.0),
+        }
+        buf.len() - start - dur.len()
+    }
+}
+
+/// Wrapper around an optional duration, displayed as `?` if unknown
+pub struct FmtMaybeDur(pub Option<i64>);
+impl crate::table::Disp for FmtMaybeDur {
+    fn out(&self, buf: &mut Vec<u8>, conf: &Conf) -> usize {
+        use std::io::Write;
+        match self.0 {
+            Some(sec) => FmtDur(sec).out(buf, conf),
+            None => {
+                wtb!(buf, "{}?", conf.dur.val);
+                1
+            },
         }
-        buf.len() - start - conf.dur.val.len()
     }
 }
 
@@ -328,7 +476,7 @@ mod test {
         OffsetDateTime::parse(s, &Rfc3339).expect(s)
     }
     fn parse_date(s: &str, o: UtcOffset) -> Result<TimeBound, ArgError> {
-        TimeBound::parse(&String::from(s), o, "")
+        TimeBound::parse(&String::from(s), &o.into(), "")
     }
     fn ts(t: OffsetDateTime) -> i64 {
         t.unix_timestamp()
@@ -351,9 +499,8 @@ mod test {
         assert_eq!(Ok(TimeBound::Unix(then + hour + min + 1)),
                    parse_date("2018-04-03T01:01:01", tz_utc));
 
-        // Different timezone (not calling `get_utcoffset()` because tests are threaded, which makes
-        // `UtcOffset::current_local_offset()` error out)
-        for secs in [hour, -1 * hour, 90 * min, -90 * min] {
+        // Different timezone
+        for secs in [hour, -hour, 90 * min, -90 * min] {
             let offset = dbg!(UtcOffset::from_whole_seconds(secs.try_into().unwrap()).unwrap());
             assert_eq!(Ok(TimeBound::Unix(then - secs)), parse_date("2018-04-03T00:00", offset));
         }
@@ -378,15 +525,26 @@ mod test {
 
     #[test]
     fn command_num() {
-        assert_eq!(parse_command_num("1c").unwrap(), 0);
-        assert_eq!(parse_command_num("5c").unwrap(), 4);
-        assert_eq!(parse_command_num("c").unwrap(), 0);
-        assert_eq!(parse_command_num("1 commands ").unwrap(), 0);
+        use TimeBound::*;
+        assert_eq!(parse_command_num("1c").unwrap(), RunFirst(0));
+        assert_eq!(parse_command_num("5c").unwrap(), RunFirst(4));
+        assert_eq!(parse_command_num("c").unwrap(), RunFirst(0));
+        assert_eq!(parse_command_num("1 commands ").unwrap(), RunFirst(0));
+        assert_eq!(parse_command_num("-1c").unwrap(), RunLast(0));
+        assert_eq!(parse_command_num("-c").unwrap(), RunLast(0));
+        assert_eq!(parse_command_num("-3 commands").unwrap(), RunLast(2));
+        assert_eq!(parse_command_num("1C").unwrap(), RunLast(0));
+        assert_eq!(parse_command_num("C").unwrap(), RunLast(0));
+        assert_eq!(parse_command_num("5C").unwrap(), RunLast(4));
+        assert_eq!(parse_command_num("-2C").unwrap(), RunLast(1));
         assert!(parse_command_num("").is_err());
         assert!(parse_command_num("0c").is_err());
+        assert!(parse_command_num("0C").is_err());
         assert!(parse_command_num("0").is_err());
         assert!(parse_command_num("1cool").is_err());
-        assert!(parse_command_num("-1c").is_err());
+        assert!(parse_command_num("1 Commands").is_err());
+        assert!(parse_command_num("+1c").is_err());
+        assert!(parse_command_num("--1c").is_err());
     }
 
     #[test]
@@ -421,35 +579,143 @@ mod test {
                 assert_eq!(1, month.day());
                 assert_eq!(Weekday::Monday, week.weekday());
                 // Check the tested code is correct
-                assert_eq!(ts(year), Timespan::Year.next(ts(base), offset), "{base} Y {year}");
-                assert_eq!(ts(month), Timespan::Month.next(ts(base), offset), "{base} M {month}");
-                assert_eq!(ts(week), Timespan::Week.next(ts(base), offset), "{base} W {week}");
-                assert_eq!(ts(day), Timespan::Day.next(ts(base), offset), "{base} D {day}");
+                let tz = Tz::from(offset);
+                assert_eq!(ts(year), Timespan::Year.next(ts(base), &tz), "{base} Y {year}");
+                assert_eq!(ts(month), Timespan::Month.next(ts(base), &tz), "{base} M {month}");
+                assert_eq!(ts(week), Timespan::Week.next(ts(base), &tz), "{base} W {week}");
+                assert_eq!(ts(day), Timespan::Day.next(ts(base), &tz), "{base} D {day}");
             }
         }
     }
 
     #[test]
-    fn duration() {
-        for (hms, fixed, secs, human, i) in
-            [("0", "0:00:00", "0", "0 second", 0),
-             ("1", "0:00:01", "1", "1 second", 1),
-             ("59", "0:00:59", "59", "59 seconds", 59),
-             ("1:00", "0:01:00", "60", "1 minute", 60),
-             ("1:01", "0:01:01", "61", "1 minute, 1 second", 61),
-             ("59:59", "0:59:59", "3599", "59 minutes, 59 seconds", 3599),
-             ("1:00:00", "1:00:00", "3600", "1 hour", 3600),
-             ("48:00:01", "48:00:01", "172801", "2 days, 1 second", 172801),
-             ("99:59:59", "99:59:59", "359999", "4 days, 3 hours, 59 minutes, 59 seconds", 359999),
-             ("100:00:00", "100:00:00", "360000", "4 days, 4 hours", 360000),
-             ("?", "?", "?", "?", -1),
-             ("?", "?", "?", "?", -123456)]
+    fn timespan_relative() {
+        let tz = Tz::new(true);
+        let now = ts(parse_3339("2019-03-05T12:00:00+00:00"));
+        for (t, y, m, w, d) in
+            [("2019-03-05T00:00:00", "this year", "this month", "this week", "today"),
+             ("2019-03-04T23:59:59", "this year", "this month", "this week", "yesterday"),
+             ("2019-03-03T12:00:00", "this year", "this month", "last week", "2019-03-03"),
+             ("2019-02-25T00:00:00", "this year", "last month", "last week", "2019-02-25"),
+             ("2019-01-31T00:00:00", "this year", "2019-01", "2019-05", "2019-01-31"),
+             ("2018-12-20T00:00:00", "last year", "2018-12", "2018-51", "2018-12-20"),
+             ("2017-03-05T12:00:00", "2017", "2017-03", "2017-09", "2017-03-05")]
+        {
+            let t = ts(parse_3339(&format!("{t}+00:00")));
+            assert_eq!(Timespan::Year.at_relative(t, now, &tz), y, "{t} Y");
+            assert_eq!(Timespan::Month.at_relative(t, now, &tz), m, "{t} M");
+            assert_eq!(Timespan::Week.at_relative(t, now, &tz), w, "{t} W");
+            assert_eq!(Timespan::Day.at_relative(t, now, &tz), d, "{t} D");
+        }
+    }
+
+    #[test]
+    fn dst() {
+        let tz = Tz(tz::TimeZone::from_posix_tz("CET-1CEST,M3.5.0,M10.5.0/3").unwrap());
+        let winter = parse_3339("2018-03-24T12:00:00+01:00");
+        let summer = parse_3339("2018-03-25T12:00:00+02:00");
+        assert_eq!(tz.offset(ts(winter)).whole_hours(), 1);
+        assert_eq!(tz.offset(ts(summer)).whole_hours(), 2);
+        // Parsing
+        assert_eq!(Ok(TimeBound::Unix(ts(winter))),
+                   TimeBound::parse(&String::from("2018-03-24 12:00"), &tz, ""));
+        assert_eq!(Ok(TimeBound::Unix(ts(summer))),
+                   TimeBound::parse(&String::from("2018-03-25 12:00"), &tz, ""));
+        // Grouping
+        let midnight = parse_3339("2018-03-25T00:00:00+01:00");
+        assert_eq!(ts(midnight), Timespan::Day.next(ts(winter), &tz));
+        assert_eq!(parse_3339("2018-03-26T00:00:00+02:00"), {
+            let t = Timespan::Day.next(ts(summer), &tz);
+            OffsetDateTime::from_unix_timestamp(t).unwrap()
+        });
+        assert_eq!("2018-03-25", Timespan::Day.at(ts(midnight), &tz));
+        // Display
+        let mut conf = Conf::from_str("emlop log --date rfc3339");
+        conf.tz = tz;
+        for (t, s) in [(winter, "2018-03-24T12:00:00+01:00"), (summer, "2018-03-25T12:00:00+02:00")]
+        {
+            let mut buf = vec![];
+            FmtDate(ts(t)).out(&mut buf, &conf);
+            assert_eq!(String::from_utf8(buf).unwrap(), s);
+        }
+    }
+
+    #[test]
+    fn tz_arg() {
+        let ts = 1522713600;
+        for (s, off) in [("u", Some(0)),
+                         ("+02:00", Some(7200)),
+                         ("+2", Some(7200)),
+                         ("-09:30", Some(-34200)),
+                         ("2", None),
+                         ("+", None),
+                         ("+-2", None),
+                         ("+02:", None),
+                         ("+99:00", None),
+                         ("foo", None)]
         {
-            for (st, exp) in [("hms", hms), ("hmsfixed", fixed), ("secs", secs), ("human", human)] {
+            let tz = Tz::parse(&String::from(s), (), "");
+            assert_eq!(tz.ok().map(|t| t.offset(ts).whole_seconds()), off, "{s}");
+        }
+    }
+
+    #[test]
+    fn duration() {
+        #[rustfmt::skip]
+        let t = [("0", "0:00:00", "0", "0 second", "PT0S", "0.00", 0),
+                 ("1", "0:00:01", "1", "1 second", "PT1S", "0.00", 1),
+                 ("59", "0:00:59", "59", "59 seconds", "PT59S", "0.02", 59),
+                 ("1:00", "0:01:00", "60", "1 minute", "PT1M", "0.02", 60),
+                 ("1:01", "0:01:01", "61", "1 minute, 1 second", "PT1M1S", "0.02", 61),
+                 ("10:30", "0:10:30", "630", "10 minutes, 30 seconds", "PT10M30S", "0.17", 630),
+                 ("59:59", "0:59:59", "3599", "59 minutes, 59 seconds", "PT59M59S", "1.00", 3599),
+                 ("1:00:00", "1:00:00", "3600", "1 hour", "PT1H", "1.00", 3600),
+                 ("48:00:01", "48:00:01", "172801", "2 days, 1 second", "PT48H1S", "48.00", 172801),
+                 ("99:59:59", "99:59:59", "359999", "4 days, 3 hours, 59 minutes, 59 seconds", "PT99H59M59S", "100.00", 359999),
+                 ("100:00:00", "100:00:00", "360000", "4 days, 4 hours", "PT100H", "100.00", 360000),
+                 ("?", "?", "?", "?", "?", "?", -1),
+                 ("?", "?", "?", "?", "?", "?", -123456)];
+        for (hms, fixed, secs, human, iso, hours, i) in t {
+            for (st, exp) in [("hms", hms),
+                              ("hmsfixed", fixed),
+                              ("secs", secs),
+                              ("human", human),
+                              ("iso", iso),
+                              ("hours", hours)]
+            {
                 let mut buf = vec![];
                 FmtDur(i).out(&mut buf, &Conf::from_str(format!("emlop l --color=n --dur {st}")));
                 assert_eq!(exp, &String::from_utf8(buf).unwrap());
             }
         }
     }
+
+    #[test]
+    fn maybe_duration() {
+        let conf = Conf::from_str("emlop l --color=n");
+        for (d, exp) in [(Some(61), "1:01"), (Some(-1), "?"), (None, "?")] {
+            let mut buf = vec![];
+            assert_eq!(FmtMaybeDur(d).out(&mut buf, &conf), exp.len());
+            assert_eq!(exp, &String::from_utf8(buf).unwrap());
+        }
+    }
+
+    #[test]
+    fn duration_parts() {
+        for (parts, i, exp) in [(1, 172801, "2 days"),
+                                (2, 172801, "2 days, 1 second"),
+                                (1, 359999, "4 days"),
+                                (2, 359999, "4 days, 3 hours"),
+                                (3, 359999, "4 days, 3 hours, 59 minutes"),
+                                (4, 359999, "4 days, 3 hours, 59 minutes, 59 seconds"),
+                                (1, 0, "0 second"),
+                                (1, -1, "?")]
+        {
+            let mut buf = vec![];
+            let conf =
+                Conf::from_str(format!("emlop l --color=n --dur h --duration-parts {parts}"));
+            FmtDur(i).out(&mut buf, &conf);
+            assert_eq!(exp, &String::from_utf8(buf).unwrap());
+        }
+    }
 }
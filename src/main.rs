@@ -6,23 +6,45 @@ mod datetime;
 mod parse;
 mod table;
 
-use crate::{config::*, datetime::*};
+use crate::{commands::Status, config::*, datetime::*};
 use anyhow::Error;
 use log::*;
-use std::str::FromStr;
+use std::{fs::File,
+          io::Write,
+          os::fd::AsRawFd,
+          path::Path,
+          process::{Child, Command, Stdio},
+          str::FromStr};
 
 fn main() {
-    let res = match Configs::load() {
-        Ok(Configs::Log(gc, sc)) => commands::cmd_log(gc, sc),
-        Ok(Configs::Stats(gc, sc)) => commands::cmd_stats(gc, sc),
+    // Exit quietly instead of panicking when stdout gets closed early, like `emlop l | head`
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+    let confs = Configs::load().and_then(|c| match &c.conf().outfile {
+                                   Some(f) => redirect_out(f).map(|_| c),
+                                   None => Ok(c),
+                               });
+    let pager = match &confs {
+        Ok(c) if c.conf().pager => spawn_pager(),
+        _ => None,
+    };
+    let res = match confs {
+        Ok(c) if c.conf().dump_config => {
+            println!("{c:#?}");
+            Ok(Status::Found)
+        },
+        Ok(Configs::Log(gc, sc)) => commands::cmd_log(gc, sc).map(Status::from),
+        Ok(Configs::Stats(gc, sc)) => commands::cmd_stats(gc, sc).map(Status::from),
         Ok(Configs::Predict(gc, sc)) => commands::cmd_predict(gc, sc),
-        Ok(Configs::Accuracy(gc, sc)) => commands::cmd_accuracy(gc, sc),
-        Ok(Configs::Complete(gc, sc)) => commands::cmd_complete(gc, sc),
+        Ok(Configs::Accuracy(gc, sc)) => commands::cmd_accuracy(gc, sc).map(Status::from),
+        Ok(Configs::Complete(gc, sc)) => commands::cmd_complete(gc, sc).map(Status::from),
         Err(e) => Err(e),
     };
-    match res {
-        Ok(true) => std::process::exit(0),
-        Ok(false) => std::process::exit(1),
+    let code = match res {
+        Ok(Status::Found) => 0,
+        Ok(Status::NotFound) => 1,
+        Ok(Status::Running) => 3,
         Err(e) => {
             match e.downcast::<clap::Error>() {
                 Ok(ce) => ce.format(&mut build_cli()).print().unwrap_or(()),
@@ -31,8 +53,56 @@ fn main() {
                     Err(e) => log_err(e),
                 },
             }
-            std::process::exit(2)
+            2
         },
+    };
+    if let Some(p) = pager {
+        wait_pager(p);
+    }
+    std::process::exit(code)
+}
+
+/// Start `$PAGER` and redirect our stdout to its stdin
+///
+/// Going through the file descriptor keeps all our stdout writers and the tty detection unchanged.
+fn spawn_pager() -> Option<Child> {
+    let cmd = std::env::var("PAGER").ok().filter(|p| !p.trim().is_empty());
+    let cmd = cmd.as_deref().unwrap_or("less");
+    let mut pager = Command::new("sh");
+    pager.arg("-c").arg(cmd).stdin(Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        pager.env("LESS", "FRX");
+    }
+    let mut child = pager.spawn().map_err(|e| warn!("Cannot start pager {cmd:?}: {e}")).ok()?;
+    let stdin = child.stdin.take()?;
+    if unsafe { libc::dup2(stdin.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+        warn!("Cannot redirect output to pager {cmd:?}");
+        let _ = child.kill();
+        return None;
+    }
+    Some(child)
+}
+
+/// Redirect our stdout to a file, for `--out`
+///
+/// Like the pager, this works at the file descriptor level so that all stdout writers follow.
+fn redirect_out(path: &Path) -> Result<(), Error> {
+    let err = |e| ArgError::new(path.display(), "--out").msg(e);
+    let file = File::create(path).map_err(err)?;
+    if unsafe { libc::dup2(file.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+        return Err(err(std::io::Error::last_os_error()).into());
+    }
+    Ok(())
+}
+
+/// Close our stdout so that the pager sees the end of input, and wait for the user to exit it
+fn wait_pager(mut pager: Child) {
+    let _ = std::io::stdout().flush();
+    unsafe {
+        libc::close(libc::STDOUT_FILENO);
+    }
+    if let Err(e) = pager.wait() {
+        warn!("Pager failed: {e}");
     }
 }
 
@@ -2,10 +2,13 @@ mod ansi;
 mod current;
 mod history;
 mod proces;
+mod version;
 
-pub use ansi::{Ansi, AnsiStr};
-pub use current::{get_buildlog, get_emerge, get_pretend, get_resume, Pkg};
-pub use history::{get_hist, Hist};
+pub use ansi::{Ansi, AnsiStr, FmtPkg};
+pub use current::{get_buildlog, get_emerge, get_plan_json, get_pretend, get_resume, get_vdb,
+                  EmergeInfo, Pkg};
+pub use history::{get_hist, resolve_range, resolve_ts, Hist};
 #[cfg(test)]
 pub use proces::tests::procs;
-pub use proces::{get_all_proc, FmtProc, ProcKind, ProcList};
+pub use proces::{get_all_proc, get_loadavg, has_procfs, FmtProc, ProcKind, ProcList};
+pub use version::{is_version, version_cmp};
@@ -81,9 +81,44 @@ impl From<&'static str> for AnsiStr {
         Self { val, len: Ansi::len(val.as_bytes()) }
     }
 }
+impl std::fmt::Debug for AnsiStr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self.val, f)
+    }
+}
 impl crate::table::Disp for AnsiStr {
     fn out(&self, buf: &mut Vec<u8>, _conf: &crate::Conf) -> usize {
         buf.extend_from_slice(self.val.as_bytes());
         self.len
     }
 }
+
+/// Package name, optionally with the category colored according to its hash
+///
+/// The first field is the color used for the whole name (or just the package part if
+/// `--color-category` is enabled).
+pub struct FmtPkg<'a>(pub &'a AnsiStr, pub &'a str);
+impl crate::table::Disp for FmtPkg<'_> {
+    fn out(&self, buf: &mut Vec<u8>, conf: &crate::Conf) -> usize {
+        const COLORS: [&str; 6] =
+            ["\x1B[0;31m", "\x1B[0;32m", "\x1B[0;33m", "\x1B[0;34m", "\x1B[0;35m", "\x1B[0;36m"];
+        match self.1.split_once('/') {
+            Some((cat, _)) if conf.color_category => {
+                // FNV-1a, good enough and stable across runs/versions
+                let hash =
+                    cat.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+                                   (h ^ b as u64).wrapping_mul(0x100000001b3)
+                               });
+                buf.extend_from_slice(COLORS[(hash % COLORS.len() as u64) as usize].as_bytes());
+                buf.extend_from_slice(cat.as_bytes());
+                buf.extend_from_slice(self.0.val.as_bytes());
+                buf.extend_from_slice(&self.1.as_bytes()[cat.len()..]);
+            },
+            _ => {
+                buf.extend_from_slice(self.0.val.as_bytes());
+                buf.extend_from_slice(self.1.as_bytes());
+            },
+        }
+        self.0.len + self.1.len()
+    }
+}
@@ -7,9 +7,11 @@ use log::*;
 use regex::Regex;
 use serde::Deserialize;
 use serde_json::from_reader;
-use std::{fs::File,
+use std::{collections::HashSet,
+          fs::{read_dir, File},
           io::{BufRead, BufReader, Read},
-          path::PathBuf};
+          path::PathBuf,
+          sync::OnceLock};
 
 /// Package name and version
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -32,12 +34,20 @@ impl Pkg {
             pos += 1;
         }
     }
+    /// Parse a `[op]category/name[-version][:slot]` atom, the version is empty if absent
+    ///
+    /// The version operator (`=`, `~`, `>=`, etc) is ignored, the version is used as-is.
+    pub fn from_atom(atom: &str) -> Self {
+        let atom = atom.trim_start_matches(['<', '>', '=', '~']);
+        let atom = atom.split(':').next().unwrap_or_default();
+        Self::try_new(atom).unwrap_or_else(|| Self { key: atom.to_owned(), pos: atom.len() + 1 })
+    }
     pub fn ebuild(&self) -> &str {
         &self.key[..(self.pos - 1)]
     }
     #[cfg(test)]
     pub fn version(&self) -> &str {
-        &self.key[self.pos..]
+        self.key.get(self.pos..).unwrap_or_default()
     }
     pub fn ebuild_version(&self) -> &str {
         &self.key
@@ -67,6 +77,23 @@ pub fn get_pretend<R: Read>(reader: R, filename: &str) -> Vec<Pkg> {
     out
 }
 
+/// Parse a JSON array of `category/name-version` atoms
+///
+/// A leading `=` is allowed, invalid atoms are skipped.
+pub fn get_plan_json<R: Read>(reader: R, filename: &str) -> Result<Vec<Pkg>, serde_json::Error> {
+    debug!("get_plan_json input={}", filename);
+    let atoms: Vec<String> = from_reader(BufReader::new(reader))?;
+    Ok(atoms.iter()
+            .filter_map(|a| {
+                let p = Pkg::try_new(a.strip_prefix('=').unwrap_or(a));
+                if p.is_none() {
+                    warn!("{filename}: Invalid atom {a:?}");
+                }
+                p
+            })
+            .collect())
+}
+
 #[derive(Deserialize)]
 struct Resume {
     mergelist: Vec<Vec<String>>,
@@ -78,18 +105,21 @@ struct Mtimedb {
 }
 
 /// Parse resume list from portage mtimedb
-pub fn get_resume(kind: ResumeKind) -> Vec<Pkg> {
-    let r = get_resume_priv(kind, "/var/cache/edb/mtimedb").unwrap_or_default();
+///
+/// With `backup == false`, `Auto` and `Either` only look at the main list.
+pub fn get_resume(kind: ResumeKind, backup: bool) -> Vec<Pkg> {
+    let r = get_resume_priv(kind, backup, "/var/cache/edb/mtimedb").unwrap_or_default();
     debug!("Loaded {kind:?} resume list: {r:?}");
     r
 }
-fn get_resume_priv(kind: ResumeKind, file: &str) -> Option<Vec<Pkg>> {
+fn get_resume_priv(kind: ResumeKind, backup: bool, file: &str) -> Option<Vec<Pkg>> {
     if matches!(kind, ResumeKind::No) {
         return Some(vec![]);
     }
     let reader = File::open(file).map_err(|e| warn!("Cannot open {file:?}: {e}")).ok()?;
     let db: Mtimedb = from_reader(reader).map_err(|e| warn!("Cannot parse {file:?}: {e}")).ok()?;
     let r = match kind {
+        ResumeKind::Either | ResumeKind::Auto if !backup => db.resume?,
         ResumeKind::Either | ResumeKind::Auto => {
             db.resume.filter(|o| !o.mergelist.is_empty()).or(db.resume_backup)?
         },
@@ -100,6 +130,31 @@ fn get_resume_priv(kind: ResumeKind, file: &str) -> Option<Vec<Pkg>> {
     Some(r.mergelist.iter().filter_map(|v| v.get(2).and_then(|s| Pkg::try_new(s))).collect())
 }
 
+/// List installed packages (`category/name`) from the portage vdb
+pub fn get_vdb() -> HashSet<String> {
+    let r = get_vdb_priv("/var/db/pkg");
+    debug!("Loaded {} installed packages", r.len());
+    r
+}
+fn get_vdb_priv(dir: &str) -> HashSet<String> {
+    let mut out = HashSet::new();
+    let Ok(cats) = read_dir(dir).map_err(|e| warn!("Cannot read {dir:?}: {e}")) else {
+        return out;
+    };
+    for cat in cats.flatten() {
+        let Ok(pkgs) = read_dir(cat.path()) else { continue };
+        for pkg in pkgs.flatten() {
+            let key = format!("{}/{}",
+                              cat.file_name().to_string_lossy(),
+                              pkg.file_name().to_string_lossy());
+            // Skip temporary entries of ongoing merges
+            if let Some(p) = Pkg::try_new(&key).filter(|_| !key.contains("/-MERGING-")) {
+                out.insert(p.ebuild().to_owned());
+            }
+        }
+    }
+    out
+}
 
 /// Retrieve summary info from the build log
 pub fn get_buildlog(pkg: &Pkg, portdirs: &Vec<PathBuf>) -> Option<String> {
@@ -114,18 +169,25 @@ pub fn get_buildlog(pkg: &Pkg, portdirs: &Vec<PathBuf>) -> Option<String> {
 }
 fn read_buildlog(file: File, max: usize) -> String {
     let mut last = String::new();
+    let mut percent = None;
     for line in rev_lines::RevLines::new(BufReader::new(file)).map_while(Result::ok) {
         if line.starts_with(">>>") {
             let tag = line.split_ascii_whitespace().skip(1).take(2).collect::<Vec<_>>().join(" ");
-            return if last.is_empty() {
-                format!(" ({})", tag.trim_matches('.'))
-            } else {
-                format!(" ({}: {})", tag.trim_matches('.'), last)
+            let tag = match percent {
+                Some(p) => format!("{} {p}%", tag.trim_matches('.')),
+                None => tag.trim_matches('.').to_owned(),
             };
+            return if last.is_empty() { format!(" ({tag})") } else { format!(" ({tag}: {last})") };
         }
-        if last.is_empty() {
+        // Progress lines start with a `[`, possibly after a color code
+        let progress =
+            percent.is_none() && matches!(line.trim_start().as_bytes().first(), Some(b'[' | 0x1b));
+        if last.is_empty() || progress {
             let stripped = Ansi::strip(&line, max);
-            if stripped.chars().any(char::is_alphanumeric) {
+            if progress {
+                percent = parse_progress(&stripped);
+            }
+            if last.is_empty() && stripped.chars().any(char::is_alphanumeric) {
                 last = stripped;
             }
         }
@@ -133,12 +195,36 @@ fn read_buildlog(file: File, max: usize) -> String {
     format!(" ({last})")
 }
 
+/// Progress percentage of `[ 42%] Building foo` (cmake) or `[21/50] Building foo` (ninja) lines
+fn parse_progress(line: &str) -> Option<u8> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"^\[ *(?:([0-9]{1,3})%|([0-9]+)/([0-9]+))\]").unwrap());
+    let c = re.captures(line)?;
+    if let Some(p) = c.get(1) {
+        return p.as_str().parse().ok();
+    }
+    let (done, total) = (c[2].parse::<u64>().ok()?, c[3].parse::<u64>().ok()?);
+    (done <= total && total > 0).then(|| (done * 100 / total) as u8)
+}
+
 #[derive(Debug)]
 pub struct EmergeInfo {
     pub start: i64,
     pub roots: Vec<pid_t>,
     pub pkgs: Vec<Pkg>,
 }
+impl EmergeInfo {
+    /// Time elapsed since `pkg` started merging at `start`, or 0 if it isn't currently merging
+    ///
+    /// Without sandbox info, assume that any merge started after emerge itself is ongoing.
+    pub fn elapsed(&self, pkg: &Pkg, start: i64, now: i64) -> i64 {
+        if self.pkgs.contains(pkg) || (self.pkgs.is_empty() && start > self.start) {
+            now - start
+        } else {
+            0
+        }
+    }
+}
 
 /// Get info from currently running emerge processes
 ///
@@ -194,10 +280,9 @@ mod tests {
 
     /// Check that `get_pretend()` has the expected output
     fn check_pretend(file: &str, expect: &[(&str, &str)]) {
-        let mut n = 0;
-        for p in get_pretend(File::open(&format!("tests/{file}")).unwrap(), file) {
+        let pkgs = get_pretend(File::open(format!("tests/{file}")).unwrap(), file);
+        for (n, p) in pkgs.iter().enumerate() {
             assert_eq!((p.ebuild(), p.version()), expect[n], "Mismatch for {file}:{n}");
-            n += 1;
         }
     }
 
@@ -212,6 +297,21 @@ mod tests {
         check_pretend("emerge-pv.basic.out", &out);
     }
 
+    #[test]
+    fn plan_json() {
+        let pkgs = |s: &str| -> Result<Vec<String>, serde_json::Error> {
+            let v = get_plan_json(s.as_bytes(), "test")?;
+            Ok(v.iter().map(|p| format!("{} {}", p.ebuild(), p.version())).collect())
+        };
+        assert_eq!(pkgs(r#"["sys-devel/gcc-6.4.0-r1", "=app-shells/bash-4.4_p12"]"#).unwrap(),
+                   vec!["sys-devel/gcc 6.4.0-r1", "app-shells/bash 4.4_p12"]);
+        assert_eq!(pkgs(r#"["dev-lang/php", "dev-db/postgresql-10.3"]"#).unwrap(),
+                   vec!["dev-db/postgresql 10.3"]);
+        assert_eq!(pkgs("[]").unwrap(), Vec::<String>::new());
+        assert!(pkgs(r#"{"mergelist": []}"#).is_err());
+        assert!(pkgs("[1, 2]").is_err());
+    }
+
     #[test]
     fn pretend_blocker() {
         let out = [("app-admin/syslog-ng", "3.13.2"), ("dev-lang/php", "7.1.13")];
@@ -219,9 +319,9 @@ mod tests {
     }
 
     /// Check that `get_resume()` has the expected output
-    fn check_resume(kind: ResumeKind, file: &str, expect: Option<&[&str]>) {
-        let expect_pkg = expect.map(|o| o.into_iter().map(|s| Pkg::try_new(s).unwrap()).collect());
-        let res = get_resume_priv(kind, &format!("tests/{file}"));
+    fn check_resume(kind: ResumeKind, backup: bool, file: &str, expect: Option<&[&str]>) {
+        let expect_pkg = expect.map(|o| o.iter().map(|s| Pkg::try_new(s).unwrap()).collect());
+        let res = get_resume_priv(kind, backup, &format!("tests/{file}"));
         assert_eq!(expect_pkg, res, "Mismatch for {file}");
     }
 
@@ -229,15 +329,27 @@ mod tests {
     fn resume() {
         let main = &["dev-lang/rust-1.65.0", "app-portage/emlop-0.5.0"];
         let bkp = &["app-portage/dummybuild-0.1.600", "app-portage/dummybuild-0.1.60"];
-        check_resume(ResumeKind::Main, "mtimedb.ok", Some(main));
-        check_resume(ResumeKind::Backup, "mtimedb.ok", Some(bkp));
-        check_resume(ResumeKind::No, "mtimedb.ok", Some(&[]));
-        check_resume(ResumeKind::Either, "mtimedb.ok", Some(main));
-        check_resume(ResumeKind::Either, "mtimedb.backuponly", Some(bkp));
-        check_resume(ResumeKind::Either, "mtimedb.empty", None);
-        check_resume(ResumeKind::Either, "mtimedb.mainempty", Some(bkp));
-        check_resume(ResumeKind::Either, "mtimedb.noresume", None);
-        check_resume(ResumeKind::Either, "mtimedb.badjson", None);
+        check_resume(ResumeKind::Main, true, "mtimedb.ok", Some(main));
+        check_resume(ResumeKind::Backup, true, "mtimedb.ok", Some(bkp));
+        check_resume(ResumeKind::No, true, "mtimedb.ok", Some(&[]));
+        check_resume(ResumeKind::Either, true, "mtimedb.ok", Some(main));
+        check_resume(ResumeKind::Either, true, "mtimedb.backuponly", Some(bkp));
+        check_resume(ResumeKind::Either, true, "mtimedb.empty", None);
+        check_resume(ResumeKind::Either, true, "mtimedb.mainempty", Some(bkp));
+        check_resume(ResumeKind::Either, true, "mtimedb.noresume", None);
+        check_resume(ResumeKind::Either, true, "mtimedb.badjson", None);
+        check_resume(ResumeKind::Either, false, "mtimedb.ok", Some(main));
+        check_resume(ResumeKind::Either, false, "mtimedb.backuponly", None);
+        check_resume(ResumeKind::Auto, false, "mtimedb.mainempty", Some(&[]));
+        check_resume(ResumeKind::Backup, false, "mtimedb.ok", Some(bkp));
+    }
+
+    #[test]
+    fn vdb() {
+        let mut pkgs: Vec<_> = get_vdb_priv("tests/vdb").into_iter().collect();
+        pkgs.sort();
+        assert_eq!(pkgs, vec!["kde-frameworks/kio", "sys-devel/gcc", "sys-devel/llvm"]);
+        assert!(get_vdb_priv("tests/notfound").is_empty());
     }
 
     #[test]
@@ -248,6 +360,24 @@ mod tests {
         assert_eq!("foo-1.2", Pkg::new("foo", "1.2").ebuild_version());
     }
 
+    #[test]
+    fn pkg_from_atom() {
+        for (a, e, v) in [("cat/foo", "cat/foo", ""),
+                          ("cat/foo-1.2", "cat/foo", "1.2"),
+                          ("=cat/foo-1.2-r1", "cat/foo", "1.2-r1"),
+                          (">=cat/foo-1.2", "cat/foo", "1.2"),
+                          ("<=cat/foo-1.2", "cat/foo", "1.2"),
+                          (">cat/foo-1.2", "cat/foo", "1.2"),
+                          ("<cat/foo-1.2", "cat/foo", "1.2"),
+                          ("~cat/foo-1.2", "cat/foo", "1.2"),
+                          ("cat/foo:3", "cat/foo", ""),
+                          ("cat/foo-bar-2:0/2.1", "cat/foo-bar", "2")]
+        {
+            let p = Pkg::from_atom(a);
+            assert_eq!((p.ebuild(), p.version()), (e, v), "{a}");
+        }
+    }
+
     #[test]
     fn buildlog() {
         for (file, lim, res) in
@@ -256,10 +386,14 @@ mod tests {
              ("build.log.onlytag", 30, "Unpacking source"),
              ("build.log.trim", 20, "Unpacking source: 102 |         HTTP2W..."),
              ("build.log.short", 20, "Configuring source: done"),
+             ("build.log.percent", 30, "Compiling source 45%: warning: unused variable 'x' [..."),
+             ("build.log.ninja", 30, "Compiling source 42%: [17/40] Linking CXX shared lib..."),
              ("build.log.color", 100, "Unpacking source: 0:57.55    Compiling syn v1.0.99"),
              ("build.log.color", 15, "Unpacking source: 0:57.55    Comp...")]
         {
-            let f = File::open(&format!("tests/{file}")).expect(&format!("can't open {file:?}"));
+            let f = File::open(format!("tests/{file}")).unwrap_or_else(|_| {
+                                                           panic!("can't open {file:?}")
+                                                       });
             assert_eq!(format!(" ({res})"), read_buildlog(f, lim));
         }
     }
@@ -277,4 +411,17 @@ mod tests {
         let einfo = get_emerge(&procs);
         assert_eq!(einfo.roots, vec![1, 5]);
     }
+
+    #[test]
+    fn elapsed() {
+        let (a, b) = (Pkg::new("cat/a", "1"), Pkg::new("cat/b", "1"));
+        let mut einfo = EmergeInfo { start: i64::MAX, roots: vec![], pkgs: vec![] };
+        assert_eq!(einfo.elapsed(&a, 10, 100), 0);
+        einfo.start = 50;
+        assert_eq!(einfo.elapsed(&a, 10, 100), 0);
+        assert_eq!(einfo.elapsed(&a, 60, 100), 40);
+        einfo.pkgs.push(a.clone());
+        assert_eq!(einfo.elapsed(&a, 10, 100), 90);
+        assert_eq!(einfo.elapsed(&b, 60, 100), 0);
+    }
 }
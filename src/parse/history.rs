@@ -2,16 +2,19 @@
 //!
 //! Use `new_hist()` to start parsing and retrieve `Hist` enums.
 
-use crate::{datetime::fmt_utctime, Show, TimeBound};
+use crate::{datetime::{epoch_now, fmt_utctime},
+            ArgError, Conf, Show, TimeBound};
 use anyhow::{bail, ensure, Context, Error};
 use crossbeam_channel::{bounded, Receiver, Sender};
 use flate2::read::GzDecoder;
 use log::*;
 use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
-use std::{fs::File,
-          io::{BufRead, BufReader},
+use std::{collections::HashMap,
+          fs::File,
+          io::{BufRead, BufReader, Seek, SeekFrom},
           str::from_utf8,
-          thread};
+          thread,
+          time::{Duration, Instant}};
 
 /// Items sent on the channel returned by `new_hist()`.
 #[derive(Debug)]
@@ -20,9 +23,17 @@ pub enum Hist {
     // There's no RunStop, because matching a Stop to the correct Start is too unreliable
     RunStart { ts: i64, args: String },
     /// Merge started (might never complete).
+    ///
+    /// Merges are paired using `key`, which is the ebuild and version followed by the target
+    /// root if it isn't `/`, so that concurrent emerges into different roots don't get mixed up.
+    ///
+    /// The merge duration covers all phases: fetch, build, and merge to the live filesystem.
     MergeStart { ts: i64, key: String, pos: usize },
-    /// Merge completed.
-    MergeStop { ts: i64, key: String, pos: usize },
+    /// Merge completed, from a binary package or not.
+    ///
+    /// `merging` is the time of the `=== (n of m) Merging (...)` line, if it was logged. It
+    /// separates the build phase (fetch to install into the image) from the merge phase.
+    MergeStop { ts: i64, key: String, pos: usize, bin: bool, merging: Option<i64> },
     /// Unmerge started (might never complete).
     UnmergeStart { ts: i64, key: String, pos: usize },
     /// Unmerge completed.
@@ -44,8 +55,8 @@ impl Hist {
     }
     pub fn version(&self) -> &str {
         match self {
-            Self::MergeStart { key, pos, .. } => &key[*pos..],
-            Self::MergeStop { key, pos, .. } => &key[*pos..],
+            Self::MergeStart { key, pos, .. } => strip_root(&key[*pos..]),
+            Self::MergeStop { key, pos, .. } => strip_root(&key[*pos..]),
             Self::UnmergeStart { key, pos, .. } => &key[*pos..],
             Self::UnmergeStop { key, pos, .. } => &key[*pos..],
             _ => unreachable!("No version for {:?}", self),
@@ -53,13 +64,22 @@ impl Hist {
     }
     pub fn ebuild_version(&self) -> &str {
         match self {
-            Self::MergeStart { key, .. } => key,
-            Self::MergeStop { key, .. } => key,
+            Self::MergeStart { key, .. } => strip_root(key),
+            Self::MergeStop { key, .. } => strip_root(key),
             Self::UnmergeStart { key, .. } => key,
             Self::UnmergeStop { key, .. } => key,
             _ => unreachable!("No ebuild/version for {:?}", self),
         }
     }
+    /// Target root of a merge, `/` if none was logged
+    pub fn root(&self) -> &str {
+        match self {
+            Self::MergeStart { key, .. } | Self::MergeStop { key, .. } => {
+                key.split_once(' ').map_or("/", |(_, r)| r)
+            },
+            _ => unreachable!("No root for {:?}", self),
+        }
+    }
     pub const fn ts(&self) -> i64 {
         match self {
             Self::RunStart { ts, .. } => *ts,
@@ -74,9 +94,42 @@ impl Hist {
 }
 
 
-/// Open maybe-compressed file, returning a BufReader
-fn open_any_buffered(name: &str) -> Result<BufReader<Box<dyn std::io::Read + Send>>, Error> {
-    let reader = File::open(name).with_context(|| format!("Cannot open {name:?}"))?;
+/// Remove the target root from a merge key
+fn strip_root(key: &str) -> &str {
+    key.split_once(' ').map_or(key, |(k, _)| k)
+}
+
+/// Merge key, with the target root if it's not the default one
+///
+/// The line ends with `<atom> to <root>`, older logs might not have the root.
+fn merge_key<'a>(atom: &str, mut tokens: impl Iterator<Item = &'a str>) -> String {
+    match (tokens.next(), tokens.next()) {
+        (Some("to"), Some(root)) if root != "/" => format!("{atom} {root}"),
+        _ => atom.to_owned(),
+    }
+}
+
+/// Open maybe-compressed, maybe-remote file, returning a BufReader
+///
+/// Plain files start reading at `offset`, which should be the start of a line.
+fn open_any_buffered(name: &str,
+                     offset: u64)
+                     -> Result<BufReader<Box<dyn std::io::Read + Send>>, Error> {
+    let reader: Box<dyn std::io::Read + Send> = match name.strip_prefix("ssh://") {
+        #[cfg(feature = "ssh")]
+        Some(url) => {
+            Box::new(SshReader::new(url).with_context(|| format!("Cannot open {name:?}"))?)
+        },
+        #[cfg(not(feature = "ssh"))]
+        Some(_) => bail!("Cannot open {name:?}: emlop was built without the `ssh` feature"),
+        None => {
+            let mut f = File::open(name).with_context(|| format!("Cannot open {name:?}"))?;
+            if offset > 0 && !name.ends_with(".gz") {
+                f.seek(SeekFrom::Start(offset)).with_context(|| format!("Cannot seek {name:?}"))?;
+            }
+            Box::new(f)
+        },
+    };
     if name.ends_with(".gz") {
         let gz = GzDecoder::new(reader);
         ensure!(gz.header().is_some(), "Cannot open {name:?}: invalid gzip header");
@@ -86,66 +139,194 @@ fn open_any_buffered(name: &str) -> Result<BufReader<Box<dyn std::io::Read + Sen
     }
 }
 
+/// Remote file read by running `ssh <host> cat <path>`
+///
+/// Opening fails if ssh exited with an error before sending anything (unreachable host, missing
+/// file...). Later errors make reading fail at the end of output.
+#[cfg(feature = "ssh")]
+struct SshReader {
+    /// Taken once we reached the end of output, so that errors are only reported once
+    child: Option<std::process::Child>,
+    out: BufReader<std::process::ChildStdout>,
+}
+#[cfg(feature = "ssh")]
+impl SshReader {
+    /// Spawn ssh for a `host/path/to/file` url
+    fn new(url: &str) -> Result<Self, Error> {
+        use std::process::{Command, Stdio};
+        let (host, path) = url.split_once('/').context("Expected ssh://<host>/<path>")?;
+        ensure!(!host.is_empty(), "Expected ssh://<host>/<path>");
+        // The remote command goes through a shell, so quote the path
+        let cmd = format!("cat -- '/{}'", path.replace('\'', "'\\''"));
+        debug!("Running ssh {host} {cmd}");
+        let mut child = Command::new("ssh").args(["--", host, &cmd])
+                                           .stdin(Stdio::null())
+                                           .stdout(Stdio::piped())
+                                           .spawn()
+                                           .context("Cannot run ssh")?;
+        let mut out = BufReader::new(child.stdout.take().expect("piped stdout"));
+        if out.fill_buf().context("Cannot read ssh output")?.is_empty() {
+            let status = child.wait().context("Cannot wait for ssh")?;
+            ensure!(status.success(), "ssh failed: {status}");
+            return Ok(Self { child: None, out });
+        }
+        Ok(Self { child: Some(child), out })
+    }
+}
+#[cfg(feature = "ssh")]
+impl std::io::Read for SshReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.out.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            if let Some(status) = self.child.take().map(|mut c| c.wait()).transpose()? {
+                if !status.success() {
+                    return Err(std::io::Error::other(format!("ssh failed: {status}")));
+                }
+            }
+        }
+        Ok(n)
+    }
+}
+
 /// Parse emerge log into a channel of `Parsed` enums.
-pub fn get_hist(file: &str,
-                min: TimeBound,
-                max: TimeBound,
+///
+/// Syncs are filtered by `repo_terms` if given, or by `search_terms` otherwise. With `tail`, only
+/// the end of the file is read, enough to find that many events of the given kinds.
+pub fn get_hist(gc: &Conf,
                 show: Show,
                 search_terms: &Vec<String>,
-                search_exact: bool)
+                repo_terms: &Vec<String>,
+                search_exact: bool,
+                search_invert: bool,
+                tail: Option<(usize, Show)>)
                 -> Result<Receiver<Hist>, Error> {
+    let file = gc.logfile.as_str();
     debug!("File: {file}");
     debug!("Show: {show}");
-    let (ts_min, ts_max) = filter_ts(file, min, max)?;
-    let filter = FilterStr::try_new(search_terms, search_exact)?;
-    let mut buf = open_any_buffered(file)?;
+    // Command and sync bounds are found while parsing, instead of scanning the file beforehand
+    let scan = needs_runs(gc.from) || needs_runs(gc.to);
+    let (ts_min, ts_max) = match scan {
+        true => (i64::MIN, i64::MAX),
+        false => filter_ts(file, gc.from, gc.to, None)?,
+    };
+    // Timestamps have a one-second resolution, so `< max` is the same as `<= max - 1`
+    let exclusive = |max: i64| if gc.to_exclusive { max.saturating_sub(1) } else { max };
+    let filter = FilterStr::try_new(search_terms, search_exact, search_invert, "[search]")?;
+    // `--invert` applies to repos only if they're matched using the search terms
+    let repo_filter = match repo_terms.is_empty() {
+        true => FilterStr::try_new(search_terms, search_exact, search_invert, "[search]")?,
+        false => FilterStr::try_new(repo_terms, search_exact, false, "--repo")?,
+    };
+    // With an upper bound, the tail of the file may be out of range, so read it all.
+    // Command bounds count from the start of the file, so read it all too.
+    let offset = match tail {
+        Some((num, tail_show)) if !scan && matches!(gc.to, TimeBound::None) => {
+            tail_offset(file, tail_show, num, gc.run_re.as_ref())?
+        },
+        _ => 0,
+    };
+    let buf = open_any_buffered(file, offset)?;
+    let parser = Parser { show,
+                          filter,
+                          repo_filter,
+                          run_re: gc.run_re.clone(),
+                          ts_min,
+                          ts_max: exclusive(ts_max),
+                          // Entries newer than `--to` are ignored anyway, so staleness would be
+                          // meaningless
+                          stale_check: gc.staleness_check && matches!(gc.to, TimeBound::None),
+                          fname: file.to_owned(),
+                          progress: gc.progress.then(Progress::default) };
     let (tx, rx): (Sender<Hist>, Receiver<Hist>) = bounded(256);
-    thread::spawn(move || {
+    if scan {
+        // Bounds are only known at the end of the file, keep the events until then
+        let mut runs = Runs::default();
+        let mut found = vec![];
+        parser.run(buf, Some(&mut runs), |h| {
+                  found.push(h);
+                  true
+              });
+        let (min, max) = runs.resolve(file, gc.from, gc.to)?;
+        let max = exclusive(max);
+        thread::spawn(move || {
+            for h in found.into_iter().filter(|h| min <= h.ts() && h.ts() <= max) {
+                if tx.send(h).is_err() {
+                    break;
+                }
+            }
+        });
+    } else {
+        thread::spawn(move || parser.run(buf, None, |h| tx.send(h).is_ok()));
+    }
+    Ok(rx)
+}
+
+/// Parsing options and state, see `get_hist()`
+struct Parser {
+    show: Show,
+    filter: FilterStr,
+    repo_filter: FilterStr,
+    run_re: Option<Regex>,
+    ts_min: i64,
+    ts_max: i64,
+    stale_check: bool,
+    fname: String,
+    progress: Option<Progress>,
+}
+impl Parser {
+    /// Parse the whole file, passing each event to `send` until it returns false
+    ///
+    /// Emerge commands and syncs are recorded into `runs` if given, regardless of `show`.
+    fn run(mut self,
+           mut buf: BufReader<Box<dyn std::io::Read + Send>>,
+           mut runs: Option<&mut Runs>,
+           mut send: impl FnMut(Hist) -> bool) {
+        let show = self.show;
         let show_merge = show.merge || show.pkg || show.tot;
         let show_unmerge = show.unmerge || show.pkg || show.tot;
+        let (filter, repo_filter, run_re) = (&self.filter, &self.repo_filter, self.run_re.as_ref());
         let mut prev_t = 0;
         let mut curline = 1;
+        // Time of the `Merging` line and binary flag of ongoing merges, sent with the merge stop
+        let mut merging: HashMap<String, (i64, bool)> = HashMap::new();
+        let mut stats = ParseStats::default();
         let mut line = Vec::with_capacity(255);
         loop {
             match buf.read_until(b'\n', &mut line) {
                 // End of file
                 Ok(0) => break,
                 // Got a line, see if one of the funs match it
-                Ok(_) => {
-                    if let Some((t, s)) = parse_ts(&line, ts_min, ts_max) {
+                Ok(n) => {
+                    stats.bytes += n;
+                    if let Some((t, s)) = parse_ts(&line, self.ts_min, self.ts_max) {
                         if prev_t > t {
                             warn!("logfile:{curline}: System clock jump: {} -> {}",
                                   fmt_utctime(prev_t),
                                   fmt_utctime(t));
                         }
                         prev_t = t;
-                        if let Some(found) = parse_mergestart(show_merge, t, s, &filter) {
-                            if tx.send(found).is_err() {
-                                break;
-                            }
-                        } else if let Some(found) = parse_mergestop(show_merge, t, s, &filter) {
-                            if tx.send(found).is_err() {
-                                break;
-                            }
-                        } else if let Some(found) = parse_unmergestart(show_unmerge, t, s, &filter)
+                        if let Some(r) = &mut runs {
+                            r.add(t, s, run_re);
+                        }
+                        let found = if let Some(found) = parse_mergestart(show_merge, t, s, filter)
                         {
-                            if tx.send(found).is_err() {
-                                break;
-                            }
-                        } else if let Some(found) = parse_unmergestop(show_unmerge, t, s, &filter) {
-                            if tx.send(found).is_err() {
-                                break;
-                            }
-                        } else if let Some(found) = parse_syncstart(show.sync, t, s) {
-                            if tx.send(found).is_err() {
-                                break;
-                            }
-                        } else if let Some(found) = parse_syncstop(show.sync, t, s, &filter) {
-                            if tx.send(found).is_err() {
-                                break;
-                            }
-                        } else if let Some(found) = parse_runstart(show.run, t, s) {
-                            if tx.send(found).is_err() {
+                            // Forget about previous failed merges
+                            merging.remove(found.ebuild_version());
+                            Some(found)
+                        } else if let Some((atom, bin)) = parse_merging(show_merge, s, filter) {
+                            merging.insert(atom.to_owned(), (t, bin));
+                            None
+                        } else {
+                            parse_mergestop(show_merge, t, s, filter, &mut merging)
+                            .or_else(|| parse_unmergestart(show_unmerge, t, s, filter))
+                            .or_else(|| parse_unmergestop(show_unmerge, t, s, filter))
+                            .or_else(|| parse_syncstart(show.sync, t, s))
+                            .or_else(|| parse_syncstop(show.sync, t, s, repo_filter))
+                            .or_else(|| parse_runstart(show.run, t, s, run_re))
+                        };
+                        if let Some(found) = found {
+                            stats.add(&found);
+                            if !send(found) {
                                 break;
                             }
                         }
@@ -156,60 +337,263 @@ pub fn get_hist(file: &str,
             }
             line.clear();
             curline += 1;
+            if curline % 4096 == 0 {
+                if let Some(p) = &mut self.progress {
+                    p.tick(&self.fname, curline);
+                }
+            }
         }
-    });
-    Ok(rx)
+        drop(self.progress.take());
+        stats.log(curline - 1);
+        if self.stale_check {
+            warn_stale(&self.fname, prev_t, epoch_now());
+        }
+    }
 }
 
-/// Return min/max timestamp depending on options.
-fn filter_ts(file: &str, min: TimeBound, max: TimeBound) -> Result<(i64, i64), Error> {
-    // Parse emerge log into a Vec of emerge command starts
-    // This is a specialized version of get_hist(), about 20% faster for this usecase
-    let mut runs = vec![];
-    if matches!(min, TimeBound::Run(_)) || matches!(max, TimeBound::Run(_)) {
-        let mut buf = open_any_buffered(file)?;
-        let mut line = Vec::with_capacity(255);
-        loop {
-            match buf.read_until(b'\n', &mut line) {
-                Ok(0) => break,
-                Ok(_) => {
-                    if let Some((t, s)) = parse_ts(&line, i64::MIN, i64::MAX) {
-                        if s.starts_with(b"*** emerge") {
-                            runs.push(t)
-                        }
-                    }
+/// Offset of a line early enough to find the last `num` events of the `show` kinds
+///
+/// Reads the file backward, counting completed events regardless of search terms. Once found, keep
+/// going back to the start of that emerge run, so that stop events can be paired with their start.
+/// Compressed and remote files aren't seekable, and are read from the start.
+fn tail_offset(name: &str, show: Show, num: usize, run_re: Option<&Regex>) -> Result<u64, Error> {
+    if name.starts_with("ssh://") || name.ends_with(".gz") {
+        return Ok(0);
+    }
+    let file = File::open(name).with_context(|| format!("Cannot open {name:?}"))?;
+    let mut pos = file.metadata()?.len();
+    let show_merge = show.merge || show.pkg || show.tot;
+    let show_unmerge = show.unmerge || show.pkg || show.tot;
+    let mut found = 0;
+    for line in rev_lines::RawRevLines::new(file) {
+        let line = line.with_context(|| format!("Cannot read {name:?}"))?;
+        pos = pos.saturating_sub(line.len() as u64 + 1);
+        let Some((_, s)) = parse_ts(&line, i64::MIN, i64::MAX) else { continue };
+        let runstart = is_runstart(s, run_re);
+        if (show.run && runstart)
+           || (show_merge && s.starts_with(b"::: comp"))
+           || (show_unmerge && s.starts_with(b">>> unmerge success"))
+           || (show.sync && s.starts_with(b"=== Sync completed"))
+        {
+            found += 1;
+        }
+        if found >= num && runstart {
+            debug!("Tail: {num} events starting at offset {pos}");
+            return Ok(pos);
+        }
+    }
+    Ok(0)
+}
+
+/// Complain if the newest entry (`0` if there was none) is more than 30 days older than `now`
+fn warn_stale(file: &str, newest: i64, now: i64) -> bool {
+    let days = (now - newest) / (60 * 60 * 24);
+    let stale = newest > 0 && days > 30;
+    // The check is opt-in, so make it visible at the default verbosity
+    if stale {
+        error!("Newest entry in {file} is {days} days old ({}), is this the right log file ?",
+               fmt_utctime(newest));
+    }
+    stale
+}
+
+/// Counters about the parsing work, to help diagnose performance issues.
+struct ParseStats {
+    start: Instant,
+    bytes: usize,
+    /// Runs, merge start/stop, unmerge start/stop, sync start/stop
+    found: [usize; 7],
+}
+impl Default for ParseStats {
+    fn default() -> Self {
+        Self { start: Instant::now(), bytes: 0, found: [0; 7] }
+    }
+}
+impl ParseStats {
+    fn add(&mut self, h: &Hist) {
+        let i = match h {
+            Hist::RunStart { .. } => 0,
+            Hist::MergeStart { .. } => 1,
+            Hist::MergeStop { .. } => 2,
+            Hist::UnmergeStart { .. } => 3,
+            Hist::UnmergeStop { .. } => 4,
+            Hist::SyncStart { .. } => 5,
+            Hist::SyncStop { .. } => 6,
+        };
+        self.found[i] += 1;
+    }
+
+    fn log(&self, lines: usize) {
+        let el = self.start.elapsed();
+        let mbps = self.bytes as f64 / 1_000_000.0 / el.as_secs_f64().max(1e-9);
+        info!("Parsed {lines} lines ({} bytes) in {el:?}, {mbps:.1}MB/s", self.bytes);
+        let [r, ms, me, us, ue, ss, se] = self.found;
+        info!("Found {r} runs, {ms}/{me} merge start/stop, {us}/{ue} unmerge start/stop, \
+               {ss}/{se} sync start/stop");
+    }
+}
+
+/// Number of parsed lines, shown on stderr when parsing takes a while
+///
+/// The line is cleared when dropped, before the results get printed.
+struct Progress {
+    start: Instant,
+    shown: Option<Instant>,
+}
+impl Default for Progress {
+    fn default() -> Self {
+        Self { start: Instant::now(), shown: None }
+    }
+}
+impl Progress {
+    fn tick(&mut self, fname: &str, lines: usize) {
+        let now = Instant::now();
+        let due = match self.shown {
+            Some(t) => now - t > Duration::from_millis(100),
+            None => now - self.start > Duration::from_millis(500),
+        };
+        if due {
+            eprint!("\rParsing {fname}: {lines} lines");
+            self.shown = Some(now);
+        }
+    }
+}
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if self.shown.is_some() {
+            eprint!("\r\x1B[K");
+        }
+    }
+}
+
+/// Whether the bound depends on emerge commands or syncs, see `Runs`
+const fn needs_runs(b: TimeBound) -> bool {
+    matches!(b,
+             TimeBound::RunFirst(_)
+             | TimeBound::RunLast(_)
+             | TimeBound::SyncLast
+             | TimeBound::SyncPrev)
+}
+
+/// Timestamps of emerge command starts and of the last two sync stops
+///
+/// Consecutive sync stops with no emerge command in between (one per repo) count as one sync.
+#[derive(Debug, Default)]
+struct Runs {
+    runs: Vec<i64>,
+    last_sync: Option<i64>,
+    prev_sync: Option<i64>,
+}
+impl Runs {
+    /// Record the line if it's an emerge command start or a sync stop
+    fn add(&mut self, t: i64, s: &[u8], run_re: Option<&Regex>) {
+        if is_runstart(s, run_re) {
+            self.runs.push(t)
+        } else if s.starts_with(b"=== Sync completed") {
+            if self.last_sync.is_some_and(|l| self.runs.last().map_or(true, |&r| r > l)) {
+                self.prev_sync = self.last_sync;
+            }
+            self.last_sync = Some(t)
+        }
+    }
+
+    /// Resolve a pair of bounds to min/max timestamps, failing if a bound isn't in the log
+    fn resolve(&self, file: &str, min: TimeBound, max: TimeBound) -> Result<(i64, i64), Error> {
+        let Self { runs, last_sync, prev_sync } = self;
+        // Convert to Option<int>
+        let resolve = |b| match b {
+            TimeBound::RunFirst(n) => runs.get(n).copied(),
+            TimeBound::RunLast(n) => runs.iter().rev().nth(n).copied(),
+            TimeBound::SyncLast => *last_sync,
+            TimeBound::SyncPrev => *prev_sync,
+            TimeBound::Unix(n) => Some(n),
+            TimeBound::None => None,
+        };
+        // A command/sync bound that isn't in the log shouldn't silently become unbounded
+        for (b, arg) in [(min, "--from"), (max, "--to")] {
+            match b {
+                TimeBound::RunFirst(_) | TimeBound::RunLast(_) if resolve(b).is_none() => {
+                    bail!("No such emerge command for {arg} in {file}")
+                },
+                TimeBound::SyncLast if resolve(b).is_none() => {
+                    bail!("No completed sync for {arg} in {file}")
                 },
-                Err(_) => (),
+                TimeBound::SyncPrev if resolve(b).is_none() => {
+                    bail!("No previous completed sync for {arg} in {file}")
+                },
+                _ => (),
             }
-            line.clear();
         }
+        let backward = matches!(min, TimeBound::RunLast(_)) || matches!(max, TimeBound::RunLast(_));
+        let (min, max) = (resolve(min), resolve(max));
+        // Check and log bounds, return result
+        match (min, max) {
+            (None, None) => debug!("Date: None"),
+            (Some(a), None) => debug!("Date: after {}", fmt_utctime(a)),
+            (None, Some(b)) => debug!("Date: before {}", fmt_utctime(b)),
+            (Some(a), Some(b)) if a < b => {
+                debug!("Date: between {} and {}", fmt_utctime(a), fmt_utctime(b))
+            },
+            (Some(a), Some(b)) => {
+                bail!("Invalid date filter: {} <= {}, did you swap --to and --from ?{}",
+                      fmt_utctime(a),
+                      fmt_utctime(b),
+                      if backward {
+                          " Note that `<num>c` counts commands back from the end"
+                      } else {
+                          ""
+                      })
+            },
+        }
+        Ok((min.unwrap_or(i64::MIN), max.unwrap_or(i64::MAX)))
     }
-    // Convert to Option<int>
-    let min = match min {
-        TimeBound::Run(n) => runs.iter().rev().nth(n).copied(),
-        TimeBound::Unix(n) => Some(n),
-        TimeBound::None => None,
-    };
-    let max = match max {
-        TimeBound::Run(n) => runs.get(n).copied(),
-        TimeBound::Unix(n) => Some(n),
-        TimeBound::None => None,
-    };
-    // Check and log bounds, return result
-    match (min, max) {
-        (None, None) => debug!("Date: None"),
-        (Some(a), None) => debug!("Date: after {}", fmt_utctime(a)),
-        (None, Some(b)) => debug!("Date: before {}", fmt_utctime(b)),
-        (Some(a), Some(b)) if a < b => {
-            debug!("Date: between {} and {}", fmt_utctime(a), fmt_utctime(b))
-        },
-        (Some(a), Some(b)) => {
-            bail!("Invalid date filter: {} <= {}, did you swap --to and --from ?",
-                  fmt_utctime(a),
-                  fmt_utctime(b))
-        },
+}
+
+/// Parse emerge log into a list of emerge command starts and the last two sync stops
+///
+/// This is a specialized version of get_hist(), about 20% faster for this usecase.
+fn scan_runs(file: &str, run_re: Option<&Regex>) -> Result<Runs, Error> {
+    let mut res = Runs::default();
+    let mut buf = open_any_buffered(file, 0)?;
+    let mut line = Vec::with_capacity(255);
+    loop {
+        match buf.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => {
+                if let Some((t, s)) = parse_ts(&line, i64::MIN, i64::MAX) {
+                    res.add(t, s, run_re);
+                }
+            },
+            Err(_) => (),
+        }
+        line.clear();
+    }
+    Ok(res)
+}
+
+/// Return min/max timestamp depending on options, scanning the file if needed.
+fn filter_ts(file: &str,
+             min: TimeBound,
+             max: TimeBound,
+             run_re: Option<&Regex>)
+             -> Result<(i64, i64), Error> {
+    match needs_runs(min) || needs_runs(max) {
+        true => scan_runs(file, run_re)?.resolve(file, min, max),
+        false => Runs::default().resolve(file, min, max),
     }
-    Ok((min.unwrap_or(i64::MIN), max.unwrap_or(i64::MAX)))
+}
+
+/// Resolve a single date/command bound to a timestamp.
+pub fn resolve_ts(gc: &Conf, bound: TimeBound) -> Result<i64, Error> {
+    match filter_ts(&gc.logfile, bound, TimeBound::None, gc.run_re.as_ref())? {
+        (i64::MIN, _) => bail!("No such emerge command in {}", gc.logfile),
+        (t, _) => Ok(t),
+    }
+}
+
+/// Resolve a pair of bounds to timestamps, like `--from` and `--to`
+pub fn resolve_range(gc: &Conf, min: TimeBound, max: TimeBound) -> Result<(i64, i64), Error> {
+    filter_ts(&gc.logfile, min, max, gc.run_re.as_ref())
 }
 
 /// Matches package/repo depending on options.
@@ -218,10 +602,21 @@ enum FilterStr {
     Eq { a: Vec<String>, b: Vec<String>, c: Vec<String> },
     Re1 { r: Regex },
     Re { r: RegexSet },
+    Not { f: Box<Self> },
 }
 impl FilterStr {
-    fn try_new(terms: &Vec<String>, exact: bool) -> Result<Self, regex::Error> {
-        debug!("Search: {terms:?} {exact}");
+    /// Build the filter, a regex error points at the offending term using `src` as the arg name
+    ///
+    /// Inverting an empty filter has no effect.
+    fn try_new(terms: &Vec<String>,
+               exact: bool,
+               invert: bool,
+               src: &'static str)
+               -> Result<Self, ArgError> {
+        debug!("Search: {terms:?} {exact} {invert}");
+        if invert && !terms.is_empty() {
+            return Ok(Self::Not { f: Box::new(Self::try_new(terms, exact, false, src)?) });
+        }
         Ok(match (terms.len(), exact) {
             (0, _) => Self::True,
             (_, true) => {
@@ -231,10 +626,16 @@ impl FilterStr {
                            c: c.into_iter().map(|s| format!("/{s}")).collect() }
             },
             (1, false) => {
-                Self::Re1 { r: RegexBuilder::new(&terms[0]).case_insensitive(true).build()? }
+                let r = RegexBuilder::new(&terms[0]).case_insensitive(true).build();
+                Self::Re1 { r: r.map_err(|e| ArgError::new(&terms[0], src).msg(e))? }
             },
-            (_, false) => {
-                Self::Re { r: RegexSetBuilder::new(terms).case_insensitive(true).build()? }
+            (_, false) => match RegexSetBuilder::new(terms).case_insensitive(true).build() {
+                Ok(r) => Self::Re { r },
+                // The set error doesn't say which term is wrong, find it by building them one by one
+                Err(e) => match terms.iter().find_map(|t| Regex::new(t).err().map(|e| (t, e))) {
+                    Some((t, e)) => return Err(ArgError::new(t, src).msg(e)),
+                    None => return Err(ArgError::new(terms.join(" "), src).msg(e)),
+                },
             },
         })
     }
@@ -244,6 +645,7 @@ impl FilterStr {
             Self::Eq { b, c, .. } => b.iter().any(|e| e == s) || c.iter().any(|e| s.ends_with(e)),
             Self::Re1 { r } => r.is_match(s),
             Self::Re { r } => r.is_match(s),
+            Self::Not { f } => !f.match_pkg(s),
         }
     }
     fn match_str(&self, s: &str) -> bool {
@@ -252,6 +654,7 @@ impl FilterStr {
             Self::Eq { a, .. } => a.iter().any(|e| e == s),
             Self::Re1 { r } => r.is_match(s),
             Self::Re { r } => r.is_match(s),
+            Self::Not { f } => !f.match_str(s),
         }
     }
 }
@@ -285,11 +688,33 @@ fn parse_ts(line: &[u8], min: i64, max: i64) -> Option<(i64, &[u8])> {
     }
 }
 
-fn parse_runstart(enabled: bool, ts: i64, line: &[u8]) -> Option<Hist> {
-    if !enabled || !line.starts_with(b"*** emerge") {
+fn parse_runstart(enabled: bool, ts: i64, line: &[u8], re: Option<&Regex>) -> Option<Hist> {
+    if !enabled {
         return None;
     }
-    Some(Hist::RunStart { ts, args: from_utf8(&line[11..]).ok()?.trim().to_owned() })
+    let args = if line.starts_with(b"*** emerge") {
+        from_utf8(&line[11..]).ok()?
+    } else {
+        match_run(re, line)?
+    };
+    Some(Hist::RunStart { ts, args: args.trim().to_owned() })
+}
+
+/// Whether the line starts a run, using the same rules as `parse_runstart()`
+fn is_runstart(line: &[u8], re: Option<&Regex>) -> bool {
+    line.starts_with(b"*** emerge") || match_run(re, line).is_some()
+}
+
+/// Match a custom run start, returning the `args` capture group or the whole line
+fn match_run<'a>(re: Option<&Regex>, line: &'a [u8]) -> Option<&'a str> {
+    let line = from_utf8(line).ok()?;
+    let c = re?.captures(line)?;
+    Some(c.name("args").map_or(line, |m| m.as_str()))
+}
+
+/// Strip the trailing `::repo` or `::/path/to/binpkg` that some atoms are logged with
+fn strip_atom(atom: &str) -> &str {
+    atom.split_once("::").map_or(atom, |(a, _)| a)
 }
 
 fn parse_mergestart(enabled: bool, ts: i64, line: &[u8], filter: &FilterStr) -> Option<Hist> {
@@ -297,19 +722,44 @@ fn parse_mergestart(enabled: bool, ts: i64, line: &[u8], filter: &FilterStr) ->
         return None;
     }
     let mut tokens = from_utf8(line).ok()?.split_ascii_whitespace();
-    let t6 = tokens.nth(5)?;
+    let t6 = strip_atom(tokens.nth(5)?);
     let pos = find_version(t6, filter)?;
-    Some(Hist::MergeStart { ts, key: t6.to_owned(), pos })
+    Some(Hist::MergeStart { ts, key: merge_key(t6, tokens), pos })
+}
+
+/// Binary merges are logged between merge start and stop, return the atom if it passes the filter
+///
+/// Format is `=== (1 of 2) Merging Binary (atom::/path/to/binpkg)`, where the path might be a
+/// tbz2, xpak, or gpkg file.
+/// Parse `=== (n of m) Merging [Binary ](<atom>::<path>)`, returning the atom and binary flag
+fn parse_merging<'a>(enabled: bool, line: &'a [u8], filter: &FilterStr) -> Option<(&'a str, bool)> {
+    if !enabled || !line.starts_with(b"=== (") {
+        return None;
+    }
+    let (_, rest) = from_utf8(line).ok()?.split_once(") Merging ")?;
+    let (bin, atom) = match rest.strip_prefix("Binary (") {
+        Some(atom) => (true, atom),
+        None => (false, rest.strip_prefix('(')?),
+    };
+    let atom = strip_atom(atom.trim_end().trim_end_matches(')'));
+    find_version(atom, filter)?;
+    Some((atom, bin))
 }
 
-fn parse_mergestop(enabled: bool, ts: i64, line: &[u8], filter: &FilterStr) -> Option<Hist> {
+fn parse_mergestop(enabled: bool,
+                   ts: i64,
+                   line: &[u8],
+                   filter: &FilterStr,
+                   merging: &mut HashMap<String, (i64, bool)>)
+                   -> Option<Hist> {
     if !enabled || !line.starts_with(b"::: comp") {
         return None;
     }
     let mut tokens = from_utf8(line).ok()?.split_ascii_whitespace();
-    let t7 = tokens.nth(6)?;
+    let t7 = strip_atom(tokens.nth(6)?);
     let pos = find_version(t7, filter)?;
-    Some(Hist::MergeStop { ts, key: t7.to_owned(), pos })
+    let (merging, bin) = merging.remove(t7).map_or((None, false), |(t, b)| (Some(t), b));
+    Some(Hist::MergeStop { ts, key: merge_key(t7, tokens), pos, bin, merging })
 }
 
 fn parse_unmergestart(enabled: bool, ts: i64, line: &[u8], filter: &FilterStr) -> Option<Hist> {
@@ -371,7 +821,6 @@ fn parse_syncstop(enabled: bool, ts: i64, line: &[u8], filter: &FilterStr) -> Op
 mod tests {
     use super::*;
     use crate::ArgParse;
-    use std::collections::HashMap;
 
     /// This checks parsing the given emerge.log.
     fn chk_hist(file: &str,
@@ -389,14 +838,19 @@ mod tests {
             "badversion" => (1327867709, 1327871057),
             "nullbytes" => (1327867709, 1327871057),
             "shortline" => (1327867709, 1327871057),
+            "binary" => (1700000000, 1700003030),
             o => unimplemented!("Unknown test log file {:?}", o),
         };
-        let hist = get_hist(&format!("tests/emerge.{}.log", file),
-                            filter_mints.map_or(TimeBound::None, |n| TimeBound::Unix(n)),
-                            filter_maxts.map_or(TimeBound::None, |n| TimeBound::Unix(n)),
+        let mut gc = Conf::from_str(format!("emlop l -F tests/emerge.{file}.log"));
+        gc.from = filter_mints.map_or(TimeBound::None, TimeBound::Unix);
+        gc.to = filter_maxts.map_or(TimeBound::None, TimeBound::Unix);
+        let hist = get_hist(&gc,
                             Show::parse(&String::from(show), "rptsmua", "test").unwrap(),
                             &filter_terms,
-                            exact).unwrap();
+                            &vec![],
+                            exact,
+                            false,
+                            None).unwrap();
         let re_atom = Regex::new("^[a-zA-Z0-9-]+/[a-zA-Z0-9_+-]+$").unwrap();
         let re_version = Regex::new("^[0-9][0-9a-z._-]*$").unwrap();
         let mut counts: HashMap<String, usize> = HashMap::new();
@@ -405,7 +859,8 @@ mod tests {
             let (kind, ts, ebuild, version) = match p {
                 Hist::RunStart { ts, .. } => ("RStart", ts, "c/e", "1"),
                 Hist::MergeStart { ts, .. } => ("MStart", ts, p.ebuild(), p.version()),
-                Hist::MergeStop { ts, .. } => ("MStop", ts, p.ebuild(), p.version()),
+                Hist::MergeStop { ts, bin: false, .. } => ("MStop", ts, p.ebuild(), p.version()),
+                Hist::MergeStop { ts, bin: true, .. } => ("MBin", ts, p.ebuild(), p.version()),
                 Hist::UnmergeStart { ts, .. } => ("UStart", ts, p.ebuild(), p.version()),
                 Hist::UnmergeStop { ts, .. } => ("UStop", ts, p.ebuild(), p.version()),
                 Hist::SyncStart { ts, .. } => ("SStart", ts, "c/e", "1"),
@@ -475,6 +930,56 @@ mod tests {
         chk_hist("shortline", "m", None, None, vec![], false, t);
     }
 
+    #[test]
+    /// Binary merges, with the various binpkg formats
+    fn parse_hist_binary() {
+        let t = vec![("MStart", 7),
+                     ("MStop", 2),
+                     ("MBin", 4),
+                     ("app-misc/foo", 5),
+                     ("app-misc/qux", 2),
+                     ("dev-lang/baz", 2)];
+        chk_hist("binary", "m", None, None, vec![], false, t);
+        let stops: Vec<(String, bool, Option<i64>)> =
+            get_hist(&Conf::from_str("emlop l -F tests/emerge.binary.log"),
+                     Show::m(),
+                     &vec![],
+                     &vec![],
+                     false,
+                     false,
+                     None).unwrap()
+                          .into_iter()
+                          .filter_map(|h| match h {
+                              Hist::MergeStop { key, bin, merging, .. } => {
+                                  Some((key, bin, merging))
+                              },
+                              _ => None,
+                          })
+                          .collect();
+        let expect = [("app-misc/foo-1.0", true, Some(1700000012)),
+                      ("www-client/falkon-24.08.3", true, Some(1700000032)),
+                      ("dev-libs/bar-2.1-r1", true, Some(1700000062)),
+                      ("dev-lang/baz-3.0", false, Some(1700000180)),
+                      ("app-misc/foo-1.1", false, Some(1700002050)),
+                      ("app-misc/qux-1.0", true, Some(1700003012))];
+        assert_eq!(stops, expect.map(|(k, b, m)| (k.to_owned(), b, m)));
+    }
+
+    #[test]
+    /// Merging markers of filtered-out packages aren't remembered
+    fn parse_merging_filter() {
+        let l = b"=== (1 of 4) Merging Binary (app-misc/foo-1.0::/usr/portage/packages/app-misc/foo-1.0.tbz2)";
+        let f = |t: &str| FilterStr::try_new(&vec![t.to_owned()], false, false, "").unwrap();
+        assert_eq!(parse_merging(true, l, &FilterStr::True), Some(("app-misc/foo-1.0", true)));
+        assert_eq!(parse_merging(true, l, &f("foo")), Some(("app-misc/foo-1.0", true)));
+        assert_eq!(parse_merging(true, l, &f("bar")), None);
+        assert_eq!(parse_merging(false, l, &FilterStr::True), None);
+        let l = b"=== (4 of 4) Merging (dev-lang/baz-3.0::/var/db/repos/gentoo/dev-lang/baz/baz-3.0.ebuild)";
+        assert_eq!(parse_merging(true, l, &FilterStr::True), Some(("dev-lang/baz-3.0", false)));
+        let l = b"=== (4 of 4) Compiling/Merging (dev-lang/baz-3.0::/var/db/repos/gentoo/dev-lang/baz/baz-3.0.ebuild)";
+        assert_eq!(parse_merging(true, l, &FilterStr::True), None);
+    }
+
     #[test]
     /// Basic counts, with every combination of command/merge/unmerge/sync
     fn parse_hist_nofilter() {
@@ -559,6 +1064,109 @@ mod tests {
         }
     }
 
+    #[test]
+    fn stale() {
+        let day = 60 * 60 * 24;
+        for (newest, now, res) in [(0, 40 * day, false),
+                                   (day, 30 * day, false),
+                                   (day, 32 * day, true),
+                                   (1520891098, 1520891098 + 365 * day, true)]
+        {
+            assert_eq!(warn_stale("f", newest, now), res, "{newest} {now}");
+        }
+    }
+
+    #[test]
+    fn tail() {
+        let f = "tests/emerge.10000.log";
+        let len = std::fs::metadata(f).unwrap().len();
+        for (show, num) in [("m", 1), ("m", 50), ("s", 3), ("r", 1), ("a", 100)] {
+            let show = Show::parse(&String::from(show), "rptsmua", "test").unwrap();
+            let pos = tail_offset(f, show, num, None).unwrap();
+            assert!(pos > 0 && pos < len, "{show} {num}");
+            // Starts at an emerge run
+            let mut line = String::new();
+            open_any_buffered(f, pos).unwrap().read_line(&mut line).unwrap();
+            assert!(line.contains(":  *** emerge "), "{show} {num} {line}");
+        }
+        assert_eq!(tail_offset(f, Show::m(), 100000, None).unwrap(), 0);
+        assert_eq!(tail_offset("tests/emerge.log.gz", Show::m(), 1, None).unwrap(), 0);
+        // Custom run starts are cut at, and counted, like `*** emerge` ones
+        let re = Regex::new("^Started emerge on").unwrap();
+        let show = Show::parse(&String::from("r"), "rptsmua", "test").unwrap();
+        let pos = tail_offset(f, show, 2, Some(&re)).unwrap();
+        let mut line = String::new();
+        open_any_buffered(f, pos).unwrap().read_line(&mut line).unwrap();
+        assert!(line.contains(": Started emerge on: "), "{line}");
+    }
+
+    #[test]
+    /// Resolving command bounds, counting from the start or the end of the file
+    fn filter_ts_runs() {
+        use TimeBound::*;
+        let (first, second, beforelast, last) = (1517698744, 1517698869, 1520890994, 1520891098);
+        let (prevsync, lastsync) = (1520868251, 1520891006);
+        let f = "tests/emerge.10000.log";
+        for (min, max, res) in [(RunFirst(0), None, Some((first, i64::MAX))),
+                                (RunFirst(1), None, Some((second, i64::MAX))),
+                                (RunLast(0), None, Some((last, i64::MAX))),
+                                (RunLast(1), None, Some((beforelast, i64::MAX))),
+                                (None, RunFirst(0), Some((i64::MIN, first))),
+                                (None, RunFirst(1), Some((i64::MIN, second))),
+                                (None, RunLast(0), Some((i64::MIN, last))),
+                                (None, RunLast(1), Some((i64::MIN, beforelast))),
+                                (RunFirst(0), RunLast(0), Some((first, last))),
+                                (RunFirst(0), RunFirst(1), Some((first, second))),
+                                (RunLast(1), RunLast(0), Some((beforelast, last))),
+                                (RunLast(0), RunLast(1), Option::None),
+                                (RunFirst(1), RunFirst(0), Option::None),
+                                (RunFirst(0), RunFirst(0), Option::None),
+                                (SyncLast, None, Some((lastsync, i64::MAX))),
+                                (SyncLast, RunLast(0), Some((lastsync, last))),
+                                (RunFirst(0), SyncLast, Some((first, lastsync))),
+                                (SyncPrev, SyncLast, Some((prevsync, lastsync))),
+                                // Swapped or missing bounds are errors
+                                (RunLast(0), RunLast(4), Option::None),
+                                (RunLast(1), RunFirst(0), Option::None),
+                                (RunFirst(1), Unix(first), Option::None),
+                                (Unix(last), RunLast(1), Option::None),
+                                (SyncLast, RunFirst(0), Option::None),
+                                (SyncLast, SyncPrev, Option::None),
+                                (RunLast(9999), None, Option::None),
+                                (None, RunFirst(9999), Option::None),
+                                (RunFirst(9999), RunLast(0), Option::None)]
+        {
+            assert_eq!(filter_ts(f, min, max, Option::None).ok(), res, "{min:?} {max:?}");
+        }
+    }
+
+    #[test]
+    /// Command bounds are resolved while parsing, with the same result as the equivalent timestamps
+    fn get_hist_runs() {
+        use TimeBound::*;
+        let (second, beforelast, last, lastsync) = (1517698869, 1520890994, 1520891098, 1520891006);
+        let hist = |from, to| {
+            let mut gc = Conf::from_str("emlop l -F tests/emerge.10000.log");
+            (gc.from, gc.to) = (from, to);
+            get_hist(&gc,
+                     Show::parse(&String::from("a"), "rptsmua", "test").unwrap(),
+                     &vec![],
+                     &vec![],
+                     false,
+                     false,
+                     Option::None).map(|r| r.iter().map(|h| format!("{h:?}")).collect::<Vec<_>>())
+        };
+        for (runs, unix) in [((RunFirst(1), None), (Unix(second), None)),
+                             ((RunLast(1), RunLast(0)), (Unix(beforelast), Unix(last))),
+                             ((SyncLast, None), (Unix(lastsync), None))]
+        {
+            let res = hist(runs.0, runs.1).unwrap();
+            assert!(!res.is_empty(), "{runs:?}");
+            assert_eq!(res, hist(unix.0, unix.1).unwrap(), "{runs:?}");
+        }
+        assert!(hist(RunLast(9999), None).is_err());
+    }
+
     #[test]
     /// Filtering by search term
     fn filter_terms() {
@@ -570,15 +1178,28 @@ mod tests {
                      ("a.", false, "ab", true, true),];
         for (terms, e, s, mpkg, mstr) in t {
             let t: Vec<String> = terms.split_whitespace().map(str::to_string).collect();
-            let f = FilterStr::try_new(&t, e).unwrap();
+            let f = FilterStr::try_new(&t, e, false, "").unwrap();
             assert_eq!(f.match_pkg(s), mpkg, "filter({t:?}, {e}).match_pkg({s:?})");
             assert_eq!(f.match_str(s), mstr, "filter({t:?}, {e}).match_str({s:?})");
         }
     }
 
+    #[test]
+    /// Regex errors name the offending term
+    fn filter_error() {
+        for (terms, bad) in [("a(", "'\x1B[33ma(\x1B[m'"),
+                             ("a b( c", "'\x1B[33mb(\x1B[m'"),
+                             ("a[ b", "'\x1B[33ma[\x1B[m'")]
+        {
+            let t: Vec<String> = terms.split_whitespace().map(str::to_string).collect();
+            let e = FilterStr::try_new(&t, false, false, "--foo").err().unwrap().to_string();
+            assert!(e.contains(bad) && e.contains("--foo"), "{terms:?} {e}");
+        }
+    }
+
     #[test]
     fn split_atom() {
-        let f = FilterStr::try_new(&vec![], false).unwrap();
+        let f = FilterStr::try_new(&vec![], false, false, "").unwrap();
         let g = |s| find_version(s, &f).map(|n| (&s[..n - 1], &s[n..]));
         assert_eq!(None, g(""));
         assert_eq!(None, g("a"));
@@ -621,12 +1242,11 @@ mod bench {
             _ => String::from("other"),
         };
         let show = Show::parse(&String::from("ms"), "rptsmua", "test").unwrap();
-        let file = String::from("benches/emerge.log");
-        let pkgs: Vec<_> =
-            get_hist(&file, TimeBound::None, TimeBound::None, show, &vec![], true).unwrap()
-                                                                                  .iter()
-                                                                                  .map(f)
-                                                                                  .collect();
+        let gc = Conf::from_str("emlop l -F benches/emerge.log");
+        let pkgs: Vec<_> = get_hist(&gc, show, &vec![], &vec![], true, false, None).unwrap()
+                                                                                   .iter()
+                                                                                   .map(f)
+                                                                                   .collect();
         assert_eq!(pkgs.len(), 21971);
         pkgs
     }
@@ -639,7 +1259,7 @@ mod bench {
                 let p = pkgs();
                 let t: Vec<String> = $t.split_whitespace().map(str::to_string).collect();
                 b.iter(move || {
-                     let f = FilterStr::try_new(&t, $e).unwrap();
+                     let f = FilterStr::try_new(&t, $e, false, "").unwrap();
                      p.iter().fold(true, |a, p| a ^ f.match_pkg(&p))
                  });
             }
@@ -685,11 +1305,10 @@ mod bench {
                                 o => panic!("unhandled show {o}"),
                             })
                             .sum();
-        let file = String::from("benches/emerge.log");
+        let gc = Conf::from_str("emlop l -F benches/emerge.log");
         b.iter(move || {
              let mut n = 0;
-             let hist =
-                 get_hist(&file, TimeBound::None, TimeBound::None, show, &vec![], true).unwrap();
+             let hist = get_hist(&gc, show, &vec![], &vec![], true, false, None).unwrap();
              for _ in hist {
                  n += 1;
              }
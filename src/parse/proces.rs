@@ -12,7 +12,7 @@ use libc::pid_t;
 use std::{collections::BTreeMap,
           fs::{read_dir, DirEntry, File},
           io::prelude::*,
-          path::PathBuf};
+          path::{Path, PathBuf}};
 
 #[derive(Debug, Clone, Copy)]
 pub enum ProcKind {
@@ -141,6 +141,11 @@ fn extend_tmpdirs(proc: PathBuf, tmpdirs: &mut Vec<PathBuf>) {
 
 pub type ProcList = BTreeMap<pid_t, Proc>;
 
+/// Whether `/proc` is available, running merges can't be detected without it
+pub fn has_procfs() -> bool {
+    Path::new("/proc/self/stat").exists()
+}
+
 /// Get command name, arguments, start time, and pid for all processes.
 pub fn get_all_proc(tmpdirs: &mut Vec<PathBuf>) -> ProcList {
     get_all_proc_result(tmpdirs).unwrap_or_else(|e| {
@@ -170,6 +175,14 @@ fn get_all_proc_result(tmpdirs: &mut Vec<PathBuf>) -> Result<ProcList, Error> {
     Ok(ret)
 }
 
+/// One-minute load average of the system
+pub fn get_loadavg() -> Option<f64> {
+    read_loadavg("/proc/loadavg")
+}
+fn read_loadavg(file: &str) -> Option<f64> {
+    let s = std::fs::read_to_string(file).map_err(|e| warn!("{file}: {e}")).ok()?;
+    s.split_ascii_whitespace().next()?.parse().ok()
+}
 
 #[cfg(test)]
 pub mod tests {
@@ -180,19 +193,19 @@ pub mod tests {
 
     /// Helper to create a process list
     pub fn procs(procs: &[(ProcKind, &str, pid_t, pid_t)]) -> ProcList {
-        BTreeMap::from_iter(procs.into_iter().map(|p| {
-                                                 (p.2,
-                                                  Proc { kind: p.0,
-                                                         cmdline: p.1.into(),
-                                                         start: p.2 as i64,
-                                                         pid: p.2,
-                                                         ppid: p.3 })
-                                             }))
+        BTreeMap::from_iter(procs.iter().map(|p| {
+                                            (p.2,
+                                             Proc { kind: p.0,
+                                                    cmdline: p.1.into(),
+                                                    start: p.2 as i64,
+                                                    pid: p.2,
+                                                    ppid: p.3 })
+                                        }))
     }
 
     fn parse_ps_time(s: &str) -> i64 {
         let fmt = format_description!("[month repr:short] [day padding:space] [hour]:[minute]:[second] [year]");
-        PrimitiveDateTime::parse(s, &fmt).expect(&format!("Cannot parse {}", s))
+        PrimitiveDateTime::parse(s, &fmt).unwrap_or_else(|_| panic!("Cannot parse {}", s))
                                          .assume_utc() // We run ps with TZ=UTC
                                          .unix_timestamp()
     }
@@ -215,25 +228,23 @@ pub mod tests {
         let ps_start = epoch_now();
         let cmd = Command::new("ps").env("TZ", "UTC")
                                     .env("LC_ALL", "C") // Use a consistent format for datetimes
-                                    .args(&["-o",
+                                    .args(["-o",
                                             "pid,lstart", // Output pid and start time
                                             "-ax", // All processes including those "not associated with a terminal"
                                             "--no-header"]) // No headers
                                     .output()
                                     .expect("failed to execute ps");
         let re = Regex::new("^ *([0-9]+) [A-Za-z]+ ([a-zA-Z0-9: ]+)$").unwrap();
-        for lineres in cmd.stdout.lines() {
-            if let Ok(line) = lineres {
-                match re.captures(&line) {
-                    Some(c) => {
-                        let pid = c.get(1).unwrap().as_str().parse::<i32>().unwrap();
-                        let time = parse_ps_time(c.get(2).unwrap().as_str());
-                        info.entry(pid)
-                            .and_modify(|t| t.2 = Some(time))
-                            .or_insert(("?".into(), None, Some(time)));
-                    },
-                    None => assert!(false, "Couldn't parse {}", line),
-                }
+        for line in cmd.stdout.lines().map_while(Result::ok) {
+            match re.captures(&line) {
+                Some(c) => {
+                    let pid = c.get(1).unwrap().as_str().parse::<i32>().unwrap();
+                    let time = parse_ps_time(c.get(2).unwrap().as_str());
+                    info.entry(pid)
+                        .and_modify(|t| t.2 = Some(time))
+                        .or_insert(("?".into(), None, Some(time)));
+                },
+                None => panic!("Couldn't parse {}", line),
             }
         }
         // Check the results. For debugging purposes it's usefull to print everything and only
@@ -258,7 +269,7 @@ pub mod tests {
     /// FmtProc should try shorten (elipsis at start) the command line when ther is no space
     #[test]
     fn proc_width() {
-        let conf = Conf::from_str(&format!("emlop p --color=n"));
+        let conf = Conf::from_str("emlop p --color=n");
         let t: Vec<_> = vec![// Here we have enough space
                              (1, "1", "1 1"),
                              (1, "12", "1 12"),
@@ -282,10 +293,17 @@ pub mod tests {
         }
     }
 
+    /// Load average is read from `/proc/loadavg`, or a file with the same format
+    #[test]
+    fn loadavg() {
+        assert_eq!(read_loadavg("tests/loadavg"), Some(3.18));
+        assert_eq!(read_loadavg("tests/notfound"), None);
+    }
+
     /// FmtProc should rewrite commands
     #[test]
     fn proc_cmdline() {
-        let conf = Conf::from_str(&format!("emlop p --color=n"));
+        let conf = Conf::from_str("emlop p --color=n");
         let t: Vec<_> =
             vec![("foo\0bar", "1 foo bar"),
                  ("foo\0bar\0", "1 foo bar"),
@@ -0,0 +1,207 @@
+//! Portage version ordering.
+//!
+//! Implements the algorithm from the Package Manager Specification, see
+//! https://projects.gentoo.org/pms/latest/pms.html#version-comparison
+
+use std::cmp::Ordering;
+
+/// Version suffixes, in increasing order
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+enum Suffix {
+    Alpha,
+    Beta,
+    Pre,
+    Rc,
+    P,
+}
+
+/// A version string split into its components, see `parse()`
+#[derive(Debug)]
+struct Version<'a> {
+    nums: Vec<&'a str>,
+    letter: Option<u8>,
+    suffixes: Vec<(Suffix, &'a str)>,
+    rev: &'a str,
+}
+
+/// Parse `1.2.3b_alpha4_p5-r6`, returns None if the string isn't a valid version
+fn parse(s: &str) -> Option<Version<'_>> {
+    let (s, rev) = match s.rsplit_once("-r") {
+        Some((s, r)) if is_num(r) => (s, r),
+        _ => (s, "0"),
+    };
+    let mut parts = s.split('_');
+    let mut base = parts.next()?;
+    let mut letter = None;
+    if let Some(l) = base.bytes().last().filter(u8::is_ascii_lowercase) {
+        letter = Some(l);
+        base = &base[..base.len() - 1];
+    }
+    let nums: Vec<&str> = base.split('.').collect();
+    if !nums.iter().all(|n| is_num(n)) {
+        return None;
+    }
+    let mut suffixes = vec![];
+    for p in parts {
+        let (suf, num) = [("alpha", Suffix::Alpha),
+                          ("beta", Suffix::Beta),
+                          ("pre", Suffix::Pre),
+                          ("rc", Suffix::Rc),
+                          ("p", Suffix::P)].into_iter()
+                                           .find_map(|(k, v)| p.strip_prefix(k).map(|n| (v, n)))?;
+        if !num.is_empty() && !is_num(num) {
+            return None;
+        }
+        suffixes.push((suf, num));
+    }
+    Some(Version { nums, letter, suffixes, rev })
+}
+
+/// Whether the string is a valid version (without the package name)
+pub fn is_version(s: &str) -> bool {
+    parse(s).is_some()
+}
+
+fn is_num(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Compare two strings of digits as integers, without risk of overflow
+fn cmp_num(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// Compare non-first numeric components
+///
+/// Components with a leading zero are compared as fractions: `1.01 < 1.1` and `1.010 == 1.01`.
+fn cmp_frac(a: &str, b: &str) -> Ordering {
+    if a.starts_with('0') || b.starts_with('0') {
+        a.trim_end_matches('0').cmp(b.trim_end_matches('0'))
+    } else {
+        cmp_num(a, b)
+    }
+}
+
+/// Compare two version strings (without the package name), following portage ordering
+///
+/// Invalid versions are compared as plain strings.
+pub fn version_cmp(a: &str, b: &str) -> Ordering {
+    let (Some(va), Some(vb)) = (parse(a), parse(b)) else {
+        return a.cmp(b);
+    };
+    // Numeric components, a longer version wins if the common part is equal
+    match cmp_num(va.nums[0], vb.nums[0]) {
+        Ordering::Equal => (),
+        o => return o,
+    }
+    for (na, nb) in va.nums.iter().zip(vb.nums.iter()).skip(1) {
+        match cmp_frac(na, nb) {
+            Ordering::Equal => (),
+            o => return o,
+        }
+    }
+    match va.nums.len().cmp(&vb.nums.len()) {
+        Ordering::Equal => (),
+        o => return o,
+    }
+    // Letter, a missing letter sorts first
+    match va.letter.cmp(&vb.letter) {
+        Ordering::Equal => (),
+        o => return o,
+    }
+    // Suffixes, pairwise then by the kind of the first extra suffix
+    for ((sa, na), (sb, nb)) in va.suffixes.iter().zip(vb.suffixes.iter()) {
+        match sa.cmp(sb).then_with(|| cmp_num(na, nb)) {
+            Ordering::Equal => (),
+            o => return o,
+        }
+    }
+    let common = va.suffixes.len().min(vb.suffixes.len());
+    match (va.suffixes.get(common), vb.suffixes.get(common)) {
+        (Some((Suffix::P, _)), None) => return Ordering::Greater,
+        (Some(_), None) => return Ordering::Less,
+        (None, Some((Suffix::P, _))) => return Ordering::Less,
+        (None, Some(_)) => return Ordering::Greater,
+        (_, _) => (),
+    }
+    // Revision
+    cmp_num(va.rev, vb.rev)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Ordering::*;
+
+    #[test]
+    fn parse_valid() {
+        for s in ["1", "1.2.3", "1.2b", "1_alpha", "1.0_p", "1.0_rc2_p3", "1-r1", "1.2a_beta3-r45"]
+        {
+            assert!(parse(s).is_some(), "{s}");
+        }
+        for s in ["", "a", "1.", ".1", "1..2", "1.2ab", "1_foo", "1_p1a", "1-r", "1-ra", "1.A"] {
+            assert!(parse(s).is_none(), "{s}");
+        }
+        let v = parse("1.2a_beta3_p-r45").unwrap();
+        assert_eq!(v.nums, vec!["1", "2"]);
+        assert_eq!(v.letter, Some(b'a'));
+        assert_eq!(v.suffixes, vec![(Suffix::Beta, "3"), (Suffix::P, "")]);
+        assert_eq!(v.rev, "45");
+    }
+
+    #[test]
+    fn cmp() {
+        #[rustfmt::skip]
+        let t = [// Numeric components
+                 ("1.0", "1.0", Equal),
+                 ("1.0", "1.1", Less),
+                 ("1.10", "1.9", Greater),
+                 ("1.0", "1.0.1", Less),
+                 ("2", "1.99", Greater),
+                 ("010", "10", Equal),
+                 ("64.0.3282.140", "64.0.3282.167", Less),
+                 ("20180221", "20180302", Less),
+                 ("99999999999999999999", "100000000000000000000", Less),
+                 // Leading zeros after the first component
+                 ("1.01", "1.1", Less),
+                 ("1.010", "1.01", Equal),
+                 ("1.001", "1.01", Less),
+                 ("1.09", "1.1", Less),
+                 ("1.0", "1.00", Equal),
+                 // Letter
+                 ("1.0a", "1.0", Greater),
+                 ("1.0a", "1.0b", Less),
+                 ("1.0z", "1.0.1", Less),
+                 // Suffixes
+                 ("1.0_rc1", "1.0", Less),
+                 ("1.0_p1", "1.0", Greater),
+                 ("1.0_alpha", "1.0_beta", Less),
+                 ("1.0_beta", "1.0_pre", Less),
+                 ("1.0_pre", "1.0_rc", Less),
+                 ("1.0_rc", "1.0_p", Less),
+                 ("1.0_alpha", "1.0_alpha0", Equal),
+                 ("1.0_alpha1", "1.0_alpha2", Less),
+                 ("1.0_alpha10", "1.0_alpha9", Greater),
+                 ("1.0_rc1_p1", "1.0_rc1", Greater),
+                 ("1.0_rc1_alpha", "1.0_rc1", Less),
+                 ("1.0_p20180221", "1.0_p20180302", Less),
+                 ("0.1.0", "0.1.0_p20180221", Less),
+                 ("1.0_p1", "1.0a", Less),
+                 // Revision
+                 ("1.0-r1", "1.0", Greater),
+                 ("1.0-r0", "1.0", Equal),
+                 ("1.0-r2", "1.0-r10", Less),
+                 ("1.0-r1", "1.0.1", Less),
+                 ("1.0_p1", "1.0-r9", Greater),
+                 ("52.6.0-r1", "52.6.0", Greater),
+                 // Invalid versions fall back to string comparison
+                 ("1.0foo", "1.0", Greater),
+                 ("abc", "abd", Less)];
+        for (a, b, o) in t {
+            assert_eq!(version_cmp(a, b), o, "{a} {b}");
+            assert_eq!(version_cmp(b, a), o.reverse(), "{b} {a}");
+        }
+    }
+}
@@ -14,6 +14,24 @@ impl<T: std::fmt::Display> Disp for T {
     }
 }
 
+/// Placeholder for skipped rows, using `--skip-fmt` if set
+///
+/// The kind is `first`, `last`, or `below`.
+pub struct FmtSkip(pub &'static str, pub usize);
+impl Disp for FmtSkip {
+    fn out(&self, buf: &mut Vec<u8>, conf: &Conf) -> usize {
+        let Self(kind, n) = *self;
+        let text = match &conf.skip_fmt {
+            Some(f) => f.replace("{n}", &n.to_string()).replace("{kind}", kind),
+            None if kind == "below" => format!("(skip {n} below)"),
+            None => format!("(skip {kind} {n})"),
+        };
+        buf.extend(conf.skip.val.as_bytes());
+        buf.extend(text.as_bytes());
+        text.chars().count()
+    }
+}
+
 #[derive(Clone, Copy)]
 enum Align {
     Left,
@@ -30,6 +48,8 @@ pub struct Table<'a, const N: usize> {
     buf: Vec<u8>,
     /// Visible length, and start/stop index into buffer
     rows: VecDeque<[(usize, usize, usize); N]>,
+    /// Sort key of each row
+    keys: VecDeque<i64>,
     /// Table header
     header: Option<[(usize, usize, usize); N]>,
     /// Number of rows skipped to print only the last N
@@ -43,19 +63,32 @@ pub struct Table<'a, const N: usize> {
     margins: [&'static str; N],
     /// Only print last N rows
     last: usize,
+    /// Columns hidden when no row has data, even if they have a header
+    optional: [bool; N],
+    /// Write rows as soon as they are added
+    line_buffered: bool,
+    /// Whether rows have already been written out
+    streamed: bool,
+    /// Sort rows by descending key before rendering
+    sort: bool,
 }
 
 impl<'a, const N: usize> Table<'a, N> {
     /// Initialize new table
     pub fn new(conf: &'a Conf) -> Table<'a, N> {
         Self { rows: VecDeque::with_capacity(32),
+               keys: VecDeque::with_capacity(32),
                buf: Vec::with_capacity(1024),
                skip: 0,
                conf,
                header: None,
                aligns: [Align::Right; N],
                margins: ["  "; N],
-               last: usize::MAX }
+               last: usize::MAX,
+               optional: [false; N],
+               line_buffered: false,
+               streamed: false,
+               sort: false }
     }
 
     /// Specify column alignment
@@ -76,6 +109,29 @@ impl<'a, const N: usize> Table<'a, N> {
         self
     }
 
+    /// Hide the column if no row has data, even if it has a header
+    pub const fn optional(mut self, col: usize) -> Self {
+        self.optional[col] = true;
+        self
+    }
+
+    /// Write rows as soon as they are added, instead of when the table is dropped
+    ///
+    /// Columns can't be aligned or hidden based on the following rows, so each row is aligned
+    /// individually and empty columns are kept.
+    pub const fn line_buffered(mut self, line_buffered: bool) -> Self {
+        self.line_buffered = line_buffered;
+        self
+    }
+
+    /// Sort rows by descending key (see `keyed_row()`) before rendering
+    ///
+    /// Rows are sorted after `last()` is applied, and are never streamed.
+    pub const fn sort(mut self, sort: bool) -> Self {
+        self.sort = sort;
+        self
+    }
+
     /// Add a section header
     pub fn header(mut self, row: [&str; N]) -> Self {
         if self.conf.header {
@@ -90,9 +146,19 @@ impl<'a, const N: usize> Table<'a, N> {
         self
     }
 
-    /// Is there actual data to flush ?
+    /// Start a new group of rows, repeating the header if configured
+    ///
+    /// The first group uses the normal header.
+    pub fn group(&mut self) {
+        if let Some(h) = self.header.filter(|_| self.conf.header_repeat && !self.rows.is_empty()) {
+            self.rows.push_back(h);
+            self.keys.push_back(0);
+        }
+    }
+
+    /// Did we get any data ?
     pub fn is_empty(&self) -> bool {
-        self.rows.is_empty()
+        self.rows.is_empty() && !self.streamed
     }
 
     /// Add one row of data
@@ -100,6 +166,11 @@ impl<'a, const N: usize> Table<'a, N> {
     /// The number of cells is set by const generic.
     /// Each cell is an array of displayables.
     pub fn row(&mut self, row: [&[&dyn Disp]; N]) {
+        self.keyed_row(0, row)
+    }
+
+    /// Add one row of data, with a key used by `sort()`
+    pub fn keyed_row(&mut self, key: i64, row: [&[&dyn Disp]; N]) {
         let mut idxrow = [(0, 0, 0); N];
         for i in 0..N {
             let start = self.buf.len();
@@ -107,10 +178,13 @@ impl<'a, const N: usize> Table<'a, N> {
             idxrow[i] = (len, start, self.buf.len());
         }
         self.rows.push_back(idxrow);
+        self.keys.push_back(key);
         if self.rows.len() > self.last {
             self.skip += 1;
             self.rows.pop_front();
+            self.keys.pop_front();
         }
+        self.stream();
     }
 
     /// Add one skip row
@@ -122,25 +196,64 @@ impl<'a, const N: usize> Table<'a, N> {
         let len = row.iter().map(|c| c.out(&mut self.buf, self.conf)).sum();
         idxrow[0] = (len, start, self.buf.len());
         self.rows.push_back(idxrow);
+        self.keys.push_back(0);
+        self.stream();
     }
 
-    fn flush(&self, mut out: impl std::io::Write) {
-        if self.is_empty() {
+    /// Write pending rows right away if we're line-buffered and don't need to wait for the last
+    /// rows
+    fn stream(&mut self) {
+        if self.line_buffered && self.last == usize::MAX && !self.sort {
+            let mut out = stdout().lock();
+            let widths = self.widths().map(|w| w.max(1));
+            self.flush_rows(&mut out, widths);
+            out.flush().unwrap_or(());
+            self.header = None;
+            self.rows.clear();
+            self.keys.clear();
+            self.buf.clear();
+            self.streamed = true;
+        }
+    }
+
+    /// Max len of each column, for the header+rows we have
+    fn widths(&self) -> [usize; N] {
+        std::array::from_fn(|i| {
+            let data = self.rows
+                           .iter()
+                           .filter(|r| Some(**r) != self.header)
+                           .fold(0, |m, r| usize::max(m, r[i].0));
+            match self.header {
+                _ if self.optional[i] && data == 0 => 0,
+                Some(h) => data.max(h[i].0),
+                None => data,
+            }
+        })
+    }
+
+    fn flush(&mut self, out: impl std::io::Write) {
+        if self.rows.is_empty() {
             return;
         }
-        // Check the max len of each column, for the header+rows we have
-        let widths: [usize; N] = std::array::from_fn(|i| {
-            self.rows.iter().chain(self.header.iter()).fold(0, |m, r| usize::max(m, r[i].0))
-        });
+        if self.sort {
+            let mut rows: Vec<_> = self.keys.drain(..).zip(self.rows.drain(..)).collect();
+            rows.sort_by_key(|(k, _)| std::cmp::Reverse(*k));
+            self.rows = rows.into_iter().map(|(_, r)| r).collect();
+        }
+        self.flush_rows(out, self.widths())
+    }
+
+    fn flush_rows(&self, mut out: impl std::io::Write, widths: [usize; N]) {
         // Show header
         if let Some(h) = self.header {
             self.flush_one(&mut out, widths, &h);
         }
         // Show skip row. Note that it doesn't participate to column alignment.
         if self.conf.showskip && self.skip > 0 {
-            writeln!(out,
-                     "{}(skip first {}){}",
-                     self.conf.skip.val, self.skip, self.conf.clr.val).unwrap_or(());
+            let mut buf = vec![];
+            FmtSkip("first", self.skip).out(&mut buf, self.conf);
+            out.write_all(&buf).unwrap_or(());
+            writeln!(out, "{}", self.conf.clr.val).unwrap_or(());
         }
         // Show remaining rows
         for row in &self.rows {
@@ -153,13 +266,14 @@ impl<'a, const N: usize> Table<'a, N> {
                  widths: [usize; N],
                  row: &[(usize, usize, usize); N]) {
         let mut first = true;
+        let lastcol = widths.iter().rposition(|&w| w > 0).unwrap_or(0);
         for i in 0..N {
             // Skip fully-empty columns
             if widths[i] == 0 {
                 continue;
             }
             let (len, pos0, pos1) = row[i];
-            if self.conf.out == OutStyle::Tab {
+            if matches!(self.conf.out, OutStyle::Tab | OutStyle::Tsv) {
                 if !first {
                     out.write_all(b"\t").unwrap_or(());
                 }
@@ -178,7 +292,7 @@ impl<'a, const N: usize> Table<'a, N> {
                     },
                     Align::Left => {
                         out.write_all(&self.buf[pos0..pos1]).unwrap_or(());
-                        if i < N - 1 {
+                        if i < lastcol {
                             out.write_all(pad).unwrap_or(());
                         }
                     },
@@ -190,6 +304,7 @@ impl<'a, const N: usize> Table<'a, N> {
     }
 
     #[cfg(test)]
+    #[allow(clippy::inherent_to_string, clippy::wrong_self_convention)]
     pub fn to_string(mut self) -> String {
         let mut out = Vec::with_capacity(self.buf.len());
         self.flush(&mut out);
@@ -208,7 +323,7 @@ impl<const N: usize> Drop for Table<'_, N> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::parse::Ansi;
+    use crate::parse::{Ansi, FmtPkg};
 
     #[test]
     fn last() {
@@ -255,6 +370,25 @@ mod test {
         assert_eq!(t.to_string(), "h\n(skip first 5)\n5\n6\n7\n8\n9\n");
     }
 
+    #[test]
+    fn sort() {
+        let conf = Conf::from_str("emlop log --color=n -H --showskip=n");
+
+        // Stable sort, descending
+        let mut t = Table::<1>::new(&conf).sort(true).header(["h"]);
+        for (k, v) in [(1, "a"), (3, "b"), (2, "c"), (3, "d")] {
+            t.keyed_row(k, [&[&v]]);
+        }
+        assert_eq!(t.to_string(), "h\nb\nd\nc\na\n");
+
+        // Sorted after keeping the last rows
+        let mut t = Table::<1>::new(&conf).sort(true).last(2);
+        for (k, v) in [(3, "a"), (1, "b"), (2, "c")] {
+            t.keyed_row(k, [&[&v]]);
+        }
+        assert_eq!(t.to_string(), "c\nb\n");
+    }
+
     #[test]
     fn align_cols() {
         let conf = Conf::from_str("emlop log --color=n --output=c");
@@ -268,6 +402,17 @@ mod test {
         assert_eq!(t.to_string(), res);
     }
 
+    #[test]
+    fn align_empty_last() {
+        let conf = Conf::from_str("emlop log --color=n --output=c");
+        let mut t = Table::<3>::new(&conf).align_left(0).align_left(1);
+        t.row([&[&"a"], &[&"short"], &[]]);
+        t.row([&[&"b"], &[&"looooooong"], &[]]);
+        let res = "a  short\n\
+                   b  looooooong\n";
+        assert_eq!(t.to_string(), res);
+    }
+
     #[test]
     fn align_longheader() {
         let conf = Conf::from_str("emlop log --color=n --output=c -H");
@@ -280,6 +425,18 @@ mod test {
         assert_eq!(t.to_string(), res);
     }
 
+    #[test]
+    fn optional_col() {
+        let conf = Conf::from_str("emlop log --color=n --output=c -H");
+        let mut t = Table::<3>::new(&conf).optional(1).optional(2).header(["a", "b", "c"]);
+        t.row([&[&1], &[], &[&3]]);
+        t.row([&[&1], &[], &[]]);
+        let res = "a  c\n\
+                   1  3\n\
+                   1   \n";
+        assert_eq!(t.to_string(), res);
+    }
+
     #[test]
     fn align_cols_last() {
         let conf = Conf::from_str("emlop log --color=n --output=c --showskip=n");
@@ -327,4 +484,27 @@ mod test {
                    >>> 123  1\n";
         assert_eq!(t.to_string(), res);
     }
+
+    #[test]
+    fn color_category() {
+        let conf = Conf::from_str("emlop log --color=y --color-category --output=c");
+        let mut t = Table::<2>::new(&conf).align_left(0);
+        t.row([&[&FmtPkg(&conf.pkg, "dev-lang/rust")], &[&1]]);
+        t.row([&[&FmtPkg(&conf.pkg, "dev-lang/python")], &[&1]]);
+        t.row([&[&FmtPkg(&conf.pkg, "nocategory")], &[&1]]);
+        let res = t.to_string();
+        let lines: Vec<&str> = res.lines().collect();
+        assert_eq!(Ansi::strip(lines[0], 100), "dev-lang/rust    1");
+        assert_eq!(Ansi::strip(lines[1], 100), "dev-lang/python  1");
+        assert_eq!(Ansi::strip(lines[2], 100), "nocategory       1");
+        assert_eq!(lines[0].split_once("dev-lang").unwrap().0,
+                   lines[1].split_once("dev-lang").unwrap().0);
+        assert!(lines[0].contains("dev-lang\x1B[1;32m/rust"), "{res:?}");
+
+        // Disabled when color is off
+        let conf = Conf::from_str("emlop log --color=n --color-category --output=c");
+        let mut t = Table::<2>::new(&conf).align_left(0);
+        t.row([&[&FmtPkg(&conf.merge, "dev-lang/rust")], &[&1]]);
+        assert_eq!(t.to_string(), ">>> dev-lang/rust  1\n");
+    }
 }
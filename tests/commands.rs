@@ -1,3 +1,6 @@
+// Some older tests predate these lints
+#![allow(clippy::useless_format, clippy::explicit_auto_deref)]
+
 use assert_cmd::Command;
 use std::{collections::HashMap,
           thread,
@@ -21,13 +24,14 @@ fn emlop(args: &str) -> Command {
     let mut e = Command::new(env!("CARGO_BIN_EXE_emlop"));
     e.env("TZ", "UTC");
     e.env("EMLOP_CONFIG", "");
+    e.env_remove("NO_COLOR").env_remove("CLICOLOR").env_remove("CLICOLOR_FORCE");
     e.args(args.replace("%F", "-F tests/emerge.").split_whitespace());
     e
 }
 
 /// Run emlop, check for success, return output as string
 fn emlop_out(args: &str) -> String {
-    let out = emlop(args).output().expect(&format!("could not run emlop {:?}", args));
+    let out = emlop(args).output().unwrap_or_else(|_| panic!("could not run emlop {:?}", args));
     assert!(out.status.success());
     assert!(out.stderr.is_empty());
     String::from_utf8(out.stdout).expect("Invalid utf8")
@@ -129,19 +133,65 @@ fn log() {
                2018-02-09 11:04:59  47:58 >>> mail-client/thunderbird-52.6.0-r1\n\
                2018-02-12 10:14:11     31 >>> kde-frameworks/kxmlrpcclient-5.43.0\n\
                (skip last 7)              \n"),
+             // Custom skip placeholder
+             ("%F10000.log l client -oc --first 4 --last 2 --skip-fmt=..{n}:{kind}",
+              "..2:first\n\
+               2018-02-09 11:04:59  47:58 >>> mail-client/thunderbird-52.6.0-r1\n\
+               2018-02-12 10:14:11     31 >>> kde-frameworks/kxmlrpcclient-5.43.0\n\
+               ..7:last                   \n"),
              // Skip silently
              ("%F10000.log l client -oc --first 4 --last 2 --showskip=n",
               "2018-02-09 11:04:59  47:58 >>> mail-client/thunderbird-52.6.0-r1\n\
-               2018-02-12 10:14:11     31 >>> kde-frameworks/kxmlrpcclient-5.43.0\n")];
+               2018-02-12 10:14:11     31 >>> kde-frameworks/kxmlrpcclient-5.43.0\n"),
+             // Sampling
+             ("%F10000.log l client -oc --every 3",
+              "2018-02-04 04:55:19  35:46 >>> mail-client/thunderbird-52.6.0\n\
+               2018-02-12 10:14:11     31 >>> kde-frameworks/kxmlrpcclient-5.43.0\n\
+               2018-02-22 13:32:53     44 >>> www-client/links-2.14-r1\n\
+               2018-03-12 10:35:22     14 >>> x11-apps/xlsclients-1.1.4\n"),
+             // Sampling before skipping
+             ("%F10000.log l client -oc --every 3 --first 3 --last 2",
+              "(skip first 1)\n\
+               2018-02-12 10:14:11  31 >>> kde-frameworks/kxmlrpcclient-5.43.0\n\
+               2018-02-22 13:32:53  44 >>> www-client/links-2.14-r1\n\
+               (skip last 1)           \n"),
+             // Version filter
+             ("%F10000.log l -e gentoo-sources -oc --since-version 4.15.7",
+              "2018-02-28 22:40:50  1:15 >>> sys-kernel/gentoo-sources-4.15.7\n\
+               2018-03-01 16:25:40  1:22 >>> sys-kernel/gentoo-sources-4.15.7-r1\n\
+               2018-03-12 10:33:08  2:00 >>> sys-kernel/gentoo-sources-4.15.9\n"),
+             // Offset and limit
+             ("%F10000.log l client -oc --skip 2 --take 3",
+              "(skip first 2)               \n\
+               2018-02-09 11:04:59    47:58 >>> mail-client/thunderbird-52.6.0-r1\n\
+               2018-02-12 10:14:11       31 >>> kde-frameworks/kxmlrpcclient-5.43.0\n\
+               2018-02-16 04:41:39  6:03:14 >>> www-client/chromium-64.0.3282.140\n\
+               (skip last 6)                \n"),
+             ("%F10000.log l client -oc --skip 9",
+              "(skip first 9)          \n\
+               2018-03-12 10:35:22  14 >>> x11-apps/xlsclients-1.1.4\n\
+               2018-03-12 11:03:53  16 >>> kde-frameworks/kxmlrpcclient-5.44.0\n"),
+             ("%F10000.log l client -oc --skip 20", "(skip first 11)\n"),
+             ("%F10000.log l client -oc --every 3 --skip 1 --take 1 --showskip=n",
+              "2018-02-12 10:14:11  31 >>> kde-frameworks/kxmlrpcclient-5.43.0\n")];
     for (a, o) in t {
         emlop(a).assert().stdout(o);
     }
+    emlop("%F10000.log l gentoo-sources --since-version 4.15").assert().code(2);
+    let out = emlop("%F10000.log l -e sys-kernel/gentoo-sources --since-version 4.15x1").output()
+                                                                                        .unwrap();
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Not a valid portage version"));
+    // Skipping everything is the same as finding nothing
+    emlop("%F10000.log l client --skip 20").assert().code(1);
+    emlop("%F10000.log l client --skip 11 --showskip=n").assert().code(1).stdout("");
+    emlop("%F10000.log l client --skip 10 --showskip=n").assert().code(0);
 }
 
 #[test]
 fn compressed() {
     // The important part here is that we're reading the gzip file
-    let o = "831\t60:07:06\t4:20\t832\t38:31\t2\n";
+    let o = "831\t60:07:06\t4:20\t832\t38:31\t2\t53:57:31\n";
     emlop("%Flog.gz s -st -ot").assert().stdout(o);
 }
 
@@ -199,8 +249,14 @@ fn timezone() {
     //  2021-03-26 17:08:20 +00:00  1:12 >>> sys-boot/grub-2.06_rc1\n\
     //  2021-03-29 11:57:14 +01:00    12 >>> sys-apps/install-xattr-0.8\n\
     //  2021-03-29 11:57:45 +01:00    31 >>> sys-devel/m4-1.4.18-r2\n"),
-    for (t, o) in t {
-        emlop("%Fdst.log l --date dto -oc").env("TZ", t).assert().stdout(o);
+    for (t, o) in &t {
+        emlop("%Fdst.log l --date dto -oc").env("TZ", t).assert().stdout(*o);
+    }
+    // Explicit timezone, overriding $TZ
+    for (a, i) in [("--tz=local", 1), ("--tz=utc", 0), ("--utc", 0), ("--tz=-09:30", 2)] {
+        emlop(&format!("%Fdst.log l --date dto -oc {a}")).env("TZ", "Europe/Moscow")
+                                                         .assert()
+                                                         .stdout(t[i].1);
     }
 }
 
@@ -263,6 +319,20 @@ fn predict_emerge_p() {
            dev-qt/qtcore-2  3:45\n\
            (skip last 3)        \n"
                                    .into(),
+          0),
+         // Sync estimates, for the repos of the last sync
+         ("%Fsync.log p -oc --show s",
+          "",
+          "Sync gentoo         10\n\
+           Sync steam-overlay   1\n\
+           Sync moltonel        1\n"
+                                    .into(),
+          0),
+         ("%F10000.log p -oc --show sm",
+          "[ebuild   R   ~] dev-qt/qtcore-5.9.4-r2\n",
+          "Sync gentoo               28\n\
+           dev-qt/qtcore-5.9.4-r2  3:45\n"
+                                          .into(),
           0)];
     for (a, i, o, e) in t {
         emlop(a).write_stdin(i).assert().code(e).stdout(o);
@@ -290,9 +360,18 @@ fn stats() {
               "gentoo          22  1:43:13     10\n\
                gentoo-portage   5  4:32:42  31:53\n",
               0),
-             ("%F10000.log s client -sst -oc", "11  24:00:24  2:10:56  10  27  2\n", 0),
+             // Merges that never completed don't show up when no emerge is running
+             ("%F10000.log s --live -e www-client/falkon -oc",
+              "www-client/falkon  1  6:02  6:02  0  0  ?\n",
+              0),
+             ("%F10000.log s -srt -oc",
+              "450  264  20  163  3  3.1\n\
+               \n\
+               831  60:07:06  4:20  832  38:31  2  53:57:31\n",
+              0),
+             ("%F10000.log s client -sst -oc", "11  24:00:24  2:10:56  10  27  2  24:00:24\n", 0),
              ("%F10000.log s client -sa -oc",
-              "450  267  20  163\n\
+              "450  264  20  163  3\n\
                \n\
                kde-frameworks/kxmlrpcclient  2        47       23  2   4  2\n\
                mail-client/thunderbird       2   1:23:44    41:52  2   6  3\n\
@@ -302,7 +381,46 @@ fn stats() {
                www-client/links              1        44       44  1   1  1\n\
                x11-apps/xlsclients           1        14       14  1   1  1\n\
                \n\
-               11  24:00:24  2:10:56  10  27  2\n",
+               11  24:00:24  2:10:56  10  27  2  24:00:24\n",
+              0),
+             ("%F10000.log s --preserved-rebuild -sa -oc",
+              "450  264  20  163  3\n\
+               \n\
+               gentoo  150  1:19:07  28\n\
+               \n\
+               dev-lang/php  1  7:28  7:28  1  2  2\n\
+               \n\
+               1  7:28  7:28  1  2  2  7:28\n",
+              0),
+             ("%F10000.log s --summary-only -sp -oc",
+              "gentoo  150  1:19:07  28\n\
+               \n\
+               831  60:07:06  4:20  832  38:31  2  53:57:31\n",
+              0),
+             ("%F10000.log s --summary-only client -oc",
+              "11  24:00:24  2:10:56  10  27  2  24:00:24\n",
+              0),
+             ("%F10000.log s client --by-version -oc -sp -f 2018-03-01",
+              "kde-frameworks/kxmlrpcclient  1       16       16  1  2  2\n\
+               \x20 5.43.0                      0        0        ?  1  2  2\n\
+               \x20 5.44.0                      1       16       16  0  0  ?\n\
+               www-client/chromium           1  7:42:07  7:42:07  1  3  3\n\
+               \x20 64.0.3282.167               0        0        ?  1  3  3\n\
+               \x20 64.0.3282.186               1  7:42:07  7:42:07  0  0  ?\n\
+               x11-apps/xlsclients           1       14       14  1  1  1\n\
+               \x20 1.1.3                       0        0        ?  1  1  1\n\
+               \x20 1.1.4                       1       14       14  0  0  ?\n",
+              0),
+             ("%F10000.log s client --no-predict -spt -oc",
+              "kde-frameworks/kxmlrpcclient  2        47  2   4\n\
+               mail-client/thunderbird       2   1:23:44  2   6\n\
+               www-client/chromium           3  21:41:24  3  12\n\
+               www-client/falkon             1      6:02  0   0\n\
+               www-client/firefox            1     47:29  1   3\n\
+               www-client/links              1        44  1   1\n\
+               x11-apps/xlsclients           1        14  1   1\n\
+               \n\
+               11  24:00:24  2:10:56  10  27  2  24:00:24\n",
               0),
              ("%F10000.log s gentoo-sources --avg arith -oc",
               "sys-kernel/gentoo-sources  10  15:04  1:30  11  3:20  16\n",
@@ -355,50 +473,51 @@ fn stats_grouped() {
                2018-03-01 sys-kernel/gentoo-sources  1   82   82  1  15  15\n\
                2018-03-05 sys-kernel/gentoo-sources  0    0    ?  1  23  23\n\
                2018-03-12 sys-kernel/gentoo-sources  1  120  120  1  13  13\n"),
-             ("%F10000.log s --duration s -st -oc -gy", "2018 831  216426  260  832  2311  2\n"),
+             ("%F10000.log s --duration s -st -oc -gy",
+              "2018 831  216426  260  832  2311  2  194251\n"),
              ("%F10000.log s --duration s -st -oc -gm",
-              "2018-02 533  158312  297  529  1497  2\n\
-               2018-03 298   58114  195  303   814  2\n"),
+              "2018-02 533  158312  297  529  1497  2  143460\n\
+               2018-03 298   58114  195  303   814  2   50791\n"),
              ("%F10000.log s --duration s -st -oc -gw",
-              "2018-05  63  33577  532   60  132  2\n\
-               2018-06  74  10070  136   68  225  3\n\
-               2018-07 281  58604  208  258  709  2\n\
-               2018-08  65  51276  788   69  197  2\n\
-               2018-09  71  14737  207   95  316  3\n\
-               2018-10 182  43782  240  187  519  2\n\
-               2018-11  95   4380   46   95  213  2\n"),
+              "2018-05  63  33577  532   60  132  2  33577\n\
+               2018-06  74  10070  136   68  225  3   8251\n\
+               2018-07 281  58604  208  258  709  2  47641\n\
+               2018-08  65  51276  788   69  197  2  49015\n\
+               2018-09  71  14737  207   95  316  3  14943\n\
+               2018-10 182  43782  240  187  519  2  36431\n\
+               2018-11  95   4380   46   95  213  2   4393\n"),
              ("%F10000.log s --duration s -st -oc -gd",
-              "2018-02-03  32   2741     85   32   70  2\n\
-               2018-02-04  31  30836    994   28   62  2\n\
-               2018-02-05   4    158     39    3    5  1\n\
-               2018-02-06  44   4288     97   44  174  3\n\
-               2018-02-07  15    857     57   13   28  2\n\
-               2018-02-08   5    983    196    4    8  2\n\
-               2018-02-09   6   3784    630    4   10  2\n\
-               2018-02-12 208  29239    140  206  587  2\n\
-               2018-02-13   1     19     19    0    0  ?\n\
-               2018-02-14  44   4795    108   44   92  2\n\
-               2018-02-15   3    137     45    3    6  2\n\
-               2018-02-16  21  23914   1138    3   14  4\n\
-               2018-02-18   4    500    125    2   10  5\n\
-               2018-02-19   2  28977  14488    2    6  3\n\
-               2018-02-20   2    488    244    1    2  2\n\
-               2018-02-21  37   5522    149   36   93  2\n\
-               2018-02-22  16  15396    962   23   82  3\n\
-               2018-02-23   6    854    142    5   11  2\n\
-               2018-02-24   2     39     19    2    3  1\n\
-               2018-02-26  10   2730    273    9   18  2\n\
-               2018-02-27  35   1403     40   49  175  3\n\
-               2018-02-28   5    652    130   16   41  2\n\
-               2018-03-01  13   9355    719   13   40  3\n\
-               2018-03-02   5    510    102    5   37  7\n\
-               2018-03-03   3     87     29    3    5  1\n\
-               2018-03-05   9    168     18   21   84  4\n\
-               2018-03-06   3  27746   9248    1    3  3\n\
-               2018-03-07  46   2969     64   43   90  2\n\
-               2018-03-08  74   5441     73   73  202  2\n\
-               2018-03-09  50   7458    149   49  140  2\n\
-               2018-03-12  95   4380     46   95  213  2\n"),
+              "2018-02-03  32   2741     85   32   70  2   2741\n\
+               2018-02-04  31  30836    994   28   62  2  30836\n\
+               2018-02-05   4    158     39    3    5  1    158\n\
+               2018-02-06  44   4288     97   44  174  3   2701\n\
+               2018-02-07  15    857     57   13   28  2    625\n\
+               2018-02-08   5    983    196    4    8  2    983\n\
+               2018-02-09   6   3784    630    4   10  2   3784\n\
+               2018-02-12 208  29239    140  206  587  2  19412\n\
+               2018-02-13   1     19     19    0    0  ?     19\n\
+               2018-02-14  44   4795    108   44   92  2   3851\n\
+               2018-02-15   3    137     45    3    6  2    137\n\
+               2018-02-16  21  23914   1138    3   14  4  23914\n\
+               2018-02-18   4    500    125    2   10  5    308\n\
+               2018-02-19   2  28977  14488    2    6  3  28563\n\
+               2018-02-20   2    488    244    1    2  2    488\n\
+               2018-02-21  37   5522    149   36   93  2   3916\n\
+               2018-02-22  16  15396    962   23   82  3  15151\n\
+               2018-02-23   6    854    142    5   11  2    858\n\
+               2018-02-24   2     39     19    2    3  1     39\n\
+               2018-02-26  10   2730    273    9   18  2   2730\n\
+               2018-02-27  35   1403     40   49  175  3   1558\n\
+               2018-02-28   5    652    130   16   41  2    688\n\
+               2018-03-01  13   9355    719   13   40  3   9370\n\
+               2018-03-02   5    510    102    5   37  7    510\n\
+               2018-03-03   3     87     29    3    5  1     87\n\
+               2018-03-05   9    168     18   21   84  4    239\n\
+               2018-03-06   3  27746   9248    1    3  3  27746\n\
+               2018-03-07  46   2969     64   43   90  2   2534\n\
+               2018-03-08  74   5441     73   73  202  2   2680\n\
+               2018-03-09  50   7458    149   49  140  2   3718\n\
+               2018-03-12  95   4380     46   95  213  2   4393\n"),
              ("%F10000.log s --duration s -ss -oc -gy", "2018 gentoo  150  4747  28\n"),
              ("%F10000.log s --duration s -ss -oc -gm",
               "2018-02 gentoo  90  2411  15\n\
@@ -452,21 +571,21 @@ fn stats_grouped() {
         for l in o.lines() {
             let cols: Vec<&str> = l.split_ascii_whitespace().collect();
             let tot = tots.entry(a.split_whitespace().last().unwrap()).or_insert((0, 0, 0, 0));
-            match cols.len() {
+            match (cols.len(), cols[1].parse::<u64>().is_ok()) {
                 // Sync
-                5 => {
+                (5, false) => {
                     (*tot).0 += to_u64(&cols, 2);
                     (*tot).1 += to_u64(&cols, 3);
                 },
                 // merge
-                8 => {
+                (8, false) => {
                     (*tot).0 += to_u64(&cols, 2);
                     (*tot).1 += to_u64(&cols, 3);
                     (*tot).2 += to_u64(&cols, 5);
                     (*tot).3 += to_u64(&cols, 6);
                 },
-                // Total
-                7 => {
+                // Total, wall time isn't checked because it depends on grouping
+                (8, true) => {
                     (*tot).0 += to_u64(&cols, 1);
                     (*tot).1 += to_u64(&cols, 2);
                     (*tot).2 += to_u64(&cols, 4);
@@ -503,7 +622,7 @@ fn negative_merge_time() {
                        kde-plasma/kwin    3  9:06  4:33  2  3  1\n\
                        net-misc/chrony    1    34    34  0  0  ?\n\
                        \n\
-                       5  10:06  2:01  2  3  1\n"))];
+                       5  10:06  2:01  2  3  1  10:07\n"))];
     for (a, o) in t {
         emlop(a).assert().success().stdout(o);
     }
@@ -521,6 +640,1061 @@ fn negative_merge_time_pred() {
     emlop(a).write_stdin(i).assert().success().stdout(o);
 }
 
+/// Concurrent emerges of the same package into different roots are paired correctly
+#[test]
+fn concurrent_roots() {
+    assert_eq!(emlop_out("%Fconcurrent.log l -oc"),
+               "2023-11-14 22:18:30   5:00 >>> dev-libs/icu-74.1\n\
+                2023-11-14 22:23:40  10:00 >>> dev-libs/icu-74.1\n");
+    assert_eq!(emlop_out("%Fconcurrent.log l -oc --target-root"),
+               "2023-11-14 22:18:30   5:00 >>> dev-libs/icu-74.1 to /\n\
+                2023-11-14 22:23:40  10:00 >>> dev-libs/icu-74.1 to /mnt/chroot\n");
+    assert_eq!(emlop_out("%Fconcurrent.log s -oc"), "dev-libs/icu  2  15:00  7:30  0  0  ?\n");
+}
+
+/// Packages present in multiple files are only predicted once.
+#[test]
+fn predict_pretend_file() {
+    let a = "%F10000.log p -stm --date unix -oc --pretend-file tests/emerge-p.basic.out \
+             --pretend-file tests/emerge-pv.basic.out --pretend-file tests/emerge-p.blocker.out";
+    let o = format!("sys-devel/gcc-6.4.0-r1                 ? \n\
+                     sys-libs/readline-7.0_p3               ? \n\
+                     app-portage/emlop-0.1.0_p20180221   1:04 \n\
+                     app-shells/bash-4.4_p12                ? \n\
+                     dev-db/postgresql-10.3              2:58 \n\
+                     app-admin/syslog-ng-3.13.2             ? \n\
+                     dev-lang/php-7.1.13                 7:28 \n\
+                     Estimate for 7 ebuilds, 4 unknown  12:10 @ {}\n",
+                    ts(12 * 60 + 10));
+    emlop(a).assert().success().stdout(o);
+    emlop("%F10000.log p --pretend-file tests/notfound").assert().code(2);
+    // Skip packages merged since the given date/command
+    let o = format!("sys-devel/gcc-6.4.0-r1              ? \n\
+                     sys-libs/readline-7.0_p3            ? \n\
+                     app-shells/bash-4.4_p12             ? \n\
+                     app-admin/syslog-ng-3.13.2          ? \n\
+                     Estimate for 4 ebuilds, 4 unknown  40 @ {}\n",
+                    ts(40));
+    for since in ["2018-01-01", "1c"] {
+        emlop(&format!("{a} --pretend-since {since}")).assert().success().stdout(o.clone());
+    }
+    emlop(&format!("{a} --pretend-since 9999c")).assert().code(2);
+}
+
+/// Packages can also be read from a JSON list of atoms, alone or with `emerge -p` files.
+#[test]
+fn predict_plan_json() {
+    let a = "%F10000.log p -stm --date unix -oc --plan-json tests/plan.json";
+    let o = format!("sys-devel/gcc-6.4.0-r1                ? \n\
+                     app-portage/emlop-0.1.0_p20180221  1:04 \n\
+                     dev-db/postgresql-10.3             2:58 \n\
+                     Estimate for 3 ebuilds, 1 unknown  4:12 @ {}\n",
+                    ts(4 * 60 + 12));
+    emlop(a).assert().success().stdout(o);
+    let o = format!("sys-devel/gcc-6.4.0-r1                ? \n\
+                     sys-libs/readline-7.0_p3              ? \n\
+                     app-portage/emlop-0.1.0_p20180221  1:04 \n\
+                     app-shells/bash-4.4_p12               ? \n\
+                     dev-db/postgresql-10.3             2:58 \n\
+                     Estimate for 5 ebuilds, 3 unknown  4:32 @ {}\n",
+                    ts(4 * 60 + 32));
+    emlop(&format!("{a} --pretend-file tests/emerge-p.basic.out")).assert().success().stdout(o);
+    emlop("%F10000.log p --plan-json tests/notfound").assert().code(2);
+    emlop("%F10000.log p --plan-json tests/emerge-p.basic.out").assert().code(2);
+}
+
+/// Atoms given on the command line, with or without versions, take precedence over STDIN
+#[test]
+fn predict_atoms() {
+    let a = "%F10000.log p -stm --date unix -oc --atoms sys-kernel/gentoo-sources \
+             =dev-db/postgresql-10.3:10 app-misc/notfound sys-kernel/gentoo-sources";
+    let o = format!("sys-kernel/gentoo-sources          1:21 \n\
+                     dev-db/postgresql-10.3             2:58 \n\
+                     app-misc/notfound                     ? \n\
+                     Estimate for 3 ebuilds, 1 unknown  4:29 @ {}\n",
+                    ts(4 * 60 + 29));
+    emlop(a).write_stdin("[ebuild   R   ] sys-devel/gcc-6.4.0-r1\n").assert().success().stdout(o);
+}
+
+#[test]
+fn min_samples() {
+    let o = format!("app-crypt/gnupg-2.2.4              52~ \n\
+                     sys-kernel/gentoo-sources-4.14.1  1:21 \n\
+                     Estimate for 2 ebuilds            2:13 @ {}\n",
+                    ts(2 * 60 + 13));
+    emlop("%F10000.log p -stm --date unix -oc --min-samples 3")
+        .write_stdin("[ebuild   R   ~] app-crypt/gnupg-2.2.4\n\
+                      [ebuild   R   ~] sys-kernel/gentoo-sources-4.14.1\n")
+        .assert()
+        .success()
+        .stdout(o);
+    let t = [("-e gnupg qtcore",
+              "app-crypt/gnupg  1     52    52  1  2  2\n\
+               dev-qt/qtcore    4  14:58  3:45  4  7  2\n"),
+             ("-e gnupg qtcore --min-samples 3",
+              "app-crypt/gnupg  1     52   52~  1  2  2~\n\
+               dev-qt/qtcore    4  14:58  3:45  4  7   2\n"),
+             ("-e qtcore --limit 2 --min-samples 3", "dev-qt/qtcore  4  14:58  3:44~  4  7  1~\n")];
+    for (a, o) in t {
+        assert_eq!(emlop_out(&format!("%F10000.log s -sp -oc {a}")), o, "{a}");
+    }
+    // The marker color doesn't leak into the next column
+    assert_eq!(emlop_out("%F10000.log s -sp -ot --color=y -e qtcore --limit 2 --min-samples 3"),
+               "\x1B[1;32mdev-qt/qtcore\t\x1B[33m4\t\x1B[1;35m14:58\t\x1B[1;35m3:44\x1B[37m~\x1B[m\t\
+                \x1B[33m4\t\x1B[1;35m7\t\x1B[1;35m1\x1B[37m~\x1B[m\x1B[m\n");
+}
+
+#[test]
+fn predict_scale() {
+    let o = format!("app-crypt/gnupg-2.2.4                26 \n\
+                     sys-kernel/gentoo-sources-4.14.1     41 \n\
+                     foo/bar-1                             ? \n\
+                     Estimate for 3 ebuilds, 1 unknown  1:17 @ {}\n",
+                    ts(60 + 17));
+    emlop("%F10000.log p -stm --date unix -oc --scale 0.5")
+        .write_stdin("[ebuild   R   ~] app-crypt/gnupg-2.2.4\n\
+                      [ebuild   R   ~] sys-kernel/gentoo-sources-4.14.1\n\
+                      [ebuild   R   ~] foo/bar-1\n")
+        .assert()
+        .success()
+        .stdout(o);
+    for a in ["0", "101", "fast"] {
+        emlop(&format!("p --scale {a}")).assert().failure().code(2);
+    }
+}
+
+#[test]
+fn predict_pred_range() {
+    let pkgs = "[ebuild   R   ~] sys-kernel/gentoo-sources-4.14.1\n\
+                [ebuild   R   ~] kde-frameworks/kio-5.44.0\n";
+    let t = [("", "1:21", "3:37"),
+             ("--pred-from 2018-03-01", "1:41", "3:09"),
+             ("--pred-to 2018-02-10", "1:35", "?"),
+             ("--pred-from 2018-02-20 --pred-to 2018-02-25", "1:17", "?")];
+    for (a, p1, p2) in t {
+        emlop(&format!("%F10000.log p -sm -ot {a}"))
+            .write_stdin(pkgs)
+            .assert()
+            .success()
+            .stdout(format!("sys-kernel/gentoo-sources-4.14.1\t{p1}\n\
+                             kde-frameworks/kio-5.44.0\t{p2}\n"));
+    }
+    emlop("%F10000.log p --pred-from 2018-03-01 --pred-to 2018-02-01").write_stdin(pkgs)
+                                                                      .assert()
+                                                                      .failure();
+}
+
+#[test]
+fn predict_no_total() {
+    let pkgs = "[ebuild   R   ~] app-crypt/gnupg-2.2.4\n\
+                [ebuild   R   ~] sys-kernel/gentoo-sources-4.14.1\n";
+    let tot = format!("sys-kernel/gentoo-sources-4.14.1  1:21 \n\
+                       Estimate for 2 ebuilds            2:13 @ {}\n",
+                      ts(2 * 60 + 13));
+    let t = [("-stm", tot.as_str()),
+             ("-stm --no-total", "sys-kernel/gentoo-sources-4.14.1  1:21\n"),
+             ("-sm", "sys-kernel/gentoo-sources-4.14.1  1:21\n"),
+             ("-sa --no-total", "sys-kernel/gentoo-sources-4.14.1  1:21\n")];
+    for (a, o) in t {
+        emlop(&format!("%F10000.log p {a} -n1 --showskip=n --date unix -oc")).write_stdin(pkgs)
+                                                                             .assert()
+                                                                             .success()
+                                                                             .stdout(o.to_owned());
+    }
+}
+
+#[test]
+fn predict_finish_times() {
+    let a =
+        "%F10000.log p -stm --date unix -oc --finish-times --pretend-file tests/emerge-p.basic.out";
+    let o = format!("sys-devel/gcc-6.4.0-r1                ? @ {}\n\
+                     sys-libs/readline-7.0_p3              ? @ {}\n\
+                     app-portage/emlop-0.1.0_p20180221  1:04 @ {}\n\
+                     app-shells/bash-4.4_p12               ? @ {}\n\
+                     dev-db/postgresql-10.3             2:58 @ {}\n\
+                     Estimate for 5 ebuilds, 3 unknown  4:32 @ {}\n",
+                    ts(10),
+                    ts(20),
+                    ts(84),
+                    ts(94),
+                    ts(272),
+                    ts(272));
+    emlop(a).assert().success().stdout(o);
+}
+
+/// Check that `--help-all` contains the long help of each subcommand
+#[test]
+fn help_all() {
+    let out = emlop_out("--help-all");
+    for s in ["log", "predict", "stats", "accuracy", "complete"] {
+        assert!(out.contains(&format!("\nUsage: emlop {s} [OPTIONS]")), "{s}");
+    }
+    assert!(out.contains("Ignored if color is disabled."));
+}
+
+#[test]
+fn log_downgrades() {
+    assert_eq!(emlop_out("%F10000.log l -e postgresql emlop -oc --mark-downgrades"),
+               "2018-02-12 09:30:13  2:58 >>> dev-db/postgresql-10.2\n\
+                2018-02-12 09:33:03  2:50 >>> dev-db/postgresql-9.5.11 (downgrade)\n\
+                2018-02-21 15:51:30  1:07 >>> app-portage/emlop-0.1.0_p20180221\n\
+                2018-02-21 15:58:16  1:23 >>> app-portage/emlop-0.1.0_p20180221\n\
+                2018-03-02 16:39:46  2:58 >>> dev-db/postgresql-10.3\n\
+                2018-03-02 16:43:00  3:14 >>> dev-db/postgresql-9.5.12 (downgrade)\n\
+                2018-03-02 23:47:55  1:04 >>> app-portage/emlop-0.1.0_p20180302\n\
+                2018-03-02 23:50:33  1:00 >>> app-portage/emlop-0.1.0_p20180302\n\
+                2018-03-07 22:39:11  1:03 >>> app-portage/emlop-0.1.0 (downgrade)\n");
+}
+
+#[test]
+fn no_reinstalls() {
+    assert_eq!(emlop_out("%F10000.log l -e postgresql emlop -oc --no-reinstalls"),
+               "2018-02-12 09:30:13  2:58 >>> dev-db/postgresql-10.2\n\
+                2018-02-12 09:33:03  2:50 >>> dev-db/postgresql-9.5.11\n\
+                2018-02-21 15:51:30  1:07 >>> app-portage/emlop-0.1.0_p20180221\n\
+                2018-03-02 16:39:46  2:58 >>> dev-db/postgresql-10.3\n\
+                2018-03-02 16:43:00  3:14 >>> dev-db/postgresql-9.5.12\n\
+                2018-03-02 23:47:55  1:04 >>> app-portage/emlop-0.1.0_p20180302\n\
+                2018-03-07 22:39:11  1:03 >>> app-portage/emlop-0.1.0\n");
+    assert_eq!(emlop_out("%F10000.log s -e emlop mlt -oc"),
+               "app-portage/emlop  5  5:37  1:04  4   5  1\n\
+                media-libs/mlt     3  4:13    43  3  13  3\n");
+    assert_eq!(emlop_out("%F10000.log s -e emlop mlt -oc --no-reinstalls"),
+               "app-portage/emlop  3  3:14  1:04  4   5  1\n\
+                media-libs/mlt     1  2:51  2:51  3  13  3\n");
+}
+
+#[test]
+fn stats_top() {
+    assert_eq!(emlop_out("%F10000.log s --top 2 -sp -ot"),
+               "www-client/chromium\t3\t21:41:24\t7:42:07\t3\t12\t3\n\
+                app-office/libreoffice\t3\t7:15:37\t2:14:06\t3\t12\t4\n\
+                (499 others)\t825\t31:10:05\t\t826\t38:07\t\n");
+    assert_eq!(emlop_out("%F10000.log s --top 2 -sp -ot --showskip=n"),
+               "www-client/chromium\t3\t21:41:24\t7:42:07\t3\t12\t3\n\
+                app-office/libreoffice\t3\t7:15:37\t2:14:06\t3\t12\t4\n");
+    // No others row when everything fits
+    assert_eq!(emlop_out("%F10000.log s --top 5 -sp -ot -e firefox thunderbird"),
+               "mail-client/thunderbird\t2\t1:23:44\t41:52\t2\t6\t3\n\
+                www-client/firefox\t1\t47:29\t47:29\t1\t3\t3\n");
+    // Sorted by time even when everything fits
+    assert_eq!(emlop_out("%F10000.log s --top 5 -sp -ot -e firefox gentoo-sources"),
+               "www-client/firefox\t1\t47:29\t47:29\t1\t3\t3\n\
+                sys-kernel/gentoo-sources\t10\t15:04\t1:21\t11\t3:20\t13\n");
+}
+
+#[test]
+fn stats_histogram() {
+    assert_eq!(emlop_out("%F10000.log s --histogram -ot -e kde-plasma/powerdevil sys-kernel/gentoo-sources www-client/firefox"),
+               "kde-plasma/powerdevil\t6\t26:17\t2:45\t6\t16\t2\n  \
+                57 █▂   ▂ 14:15\t\t\t\t\t\t\n\
+                sys-kernel/gentoo-sources\t10\t15:04\t1:21\t11\t3:20\t13\n  \
+                1:15 ██  ▆    ▆ 2:00\t\t\t\t\t\t\n\
+                www-client/firefox\t1\t47:29\t47:29\t1\t3\t3\n  \
+                47:29 █ 47:29\t\t\t\t\t\t\n");
+}
+
+#[test]
+fn stats_clock() {
+    assert_eq!(emlop_out("%Fsync.log s -ss --clock -oc -H --from 2020-01-01 --no-predict"),
+               "Repo           Syncs  Total time\n\
+                gentoo             7        1:09\n\
+                moltonel           8          26\n\
+                steam-overlay      5          10\n\
+                \n\
+                Repo           0  1  2  3  4  5  6  7  8  9  10  11  12  13  14  15  16  17  18  19  20  21  22  23\n\
+                gentoo         0  0  0  0  0  0  0  0  1  0   0   0   0   0   0   2   2   0   0   0   1   1   0   0\n\
+                moltonel       0  0  0  0  0  0  0  0  1  0   0   0   0   0   0   2   2   0   0   0   2   1   0   0\n\
+                steam-overlay  0  0  0  0  0  0  0  0  0  0   0   0   0   0   0   1   2   0   0   0   1   1   0   0\n");
+    assert_eq!(emlop_out("%Fsync.log s -ss --clock -ot -gy --to 2017-01-01"),
+               "2007\tgentoo-portage\t5\t4:32:42\t31:53\n\
+                2016\tgentoo\t2\t1:27\t43\n\
+                \n\
+                2007\tgentoo-portage\t0\t0\t0\t0\t1\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t1\t0\t1\t1\t0\t1\n\
+                2016\tgentoo\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t1\t0\t1\n");
+}
+
+#[test]
+fn stats_overview() {
+    assert_eq!(emlop_out("%F10000.log s --overview -sp gentoo-sources -oc"),
+               "1 distinct package, 10 distinct versions, 10 total merges over 2018-02-04 20:05:02 - 2018-03-12 10:33:08\n\
+                \n\
+                sys-kernel/gentoo-sources  10  15:04  1:21  11  3:20  13\n");
+    assert_eq!(emlop_out("%F10000.log s --overview -ss -oc"), "gentoo  150  1:19:07  28\n");
+    assert!(emlop_out("%F10000.log s --overview -sp -e gentoo-sources --from 2018-03-12 -oc")
+            .starts_with("1 distinct package, 1 distinct version, 1 total merge over "));
+}
+
+#[test]
+fn stats_group_regex() {
+    let t =
+        [("--group-regex ^([a-z]+)- -e qtcore baloo",
+          "dev  4  14:58  3:45  4  7  2\n\
+               kde  2   2:44  1:22  2  6  3\n"),
+         // The whole match is used without a capture group, non-matching packages are `other`
+         ("--group-regex kde -N3",
+          "kde    537  14:17:30  39  539  23:29  2\nother  294  45:49:36  14  293  15:02  2\n"),
+         ("--group-regex kde kde-apps/kde", "kde  33  55:09  20  33  1:30  2\n")];
+    for (a, o) in t {
+        assert_eq!(emlop_out(&format!("%F10000.log s -oc {a}")), o, "{a}");
+    }
+    // Comparisons and binary savings use the same key
+    assert_eq!(emlop_out("%F10000.log s -oc --compare ..2018-02-20 2018-02-20.. -e qtcore baloo \
+                          --group-regex ^([a-z]+)-"),
+               "dev  2  3:45  2  3:44   +0%\nkde  1  1:31  1  1:13  -19%\n");
+    assert_eq!(emlop_out("%Fbinary.log s -st -oc --group-regex ^([a-z]+)-"),
+               "6  2  4  3:32  35  1  1  1  3:32  2:58\n");
+    emlop("%F10000.log s --group-regex (").assert().code(2);
+    emlop("%F10000.log s --group-regex kde --by-version").assert().code(2);
+}
+
+#[test]
+fn stats_unpredicted() {
+    let t = [("s -st -oc -H",
+              "Merges  Total time  Average time  Unmerges  Total time  Average time  Wall time  Unpredicted\n     \
+               2          10             5         0           0             ?         10            1\n"),
+             ("s -st -oc --no-predict", "2  10  5  0  0  ?  10\n")];
+    for (a, o) in t {
+        assert_eq!(emlop_out(&format!("%Funpredicted.log {a}")), o, "{a}");
+    }
+}
+
+#[test]
+fn stats_csv_series() {
+    let t = [("-e dev-qt/qtcore -gw --from 2018-02-20 --to 2018-03-05",
+              "week,package,merges,seconds\n\
+               2018-08,dev-qt/qtcore,2,448\n\
+               2018-09,dev-qt/qtcore,0,0\n\
+               2018-10,dev-qt/qtcore,0,0\n"),
+             ("-st -gm", "month,merges,seconds\n2018-02,533,158312\n2018-03,298,58114\n"),
+             // Empty days are included, from the first merge up to `--to`
+             ("-e dev-qt/qtcore --to 2018-02-07",
+              "date,package,merges,seconds\n\
+               2018-02-03,dev-qt/qtcore,1,223\n\
+               2018-02-04,dev-qt/qtcore,0,0\n\
+               2018-02-05,dev-qt/qtcore,0,0\n\
+               2018-02-06,dev-qt/qtcore,1,227\n\
+               2018-02-07,dev-qt/qtcore,0,0\n"),
+             ("--group-regex ^([a-z]+)- -e qtcore baloo -gm",
+              "month,package,merges,seconds\n\
+               2018-02,dev,4,898\n\
+               2018-02,kde,1,91\n\
+               2018-03,dev,0,0\n\
+               2018-03,kde,1,73\n")];
+    for (a, o) in t {
+        assert_eq!(emlop_out(&format!("%F10000.log s --csv-series {a}")), o, "{a}");
+    }
+    emlop("%F10000.log s --csv-series nonexistent").assert()
+                                                   .code(1)
+                                                   .stdout("date,package,merges,seconds\n");
+    // Options that don't apply to the series are rejected
+    for a in ["--count", "--top 2", "-N", "-n3", "--histogram", "--by-version", "-op"] {
+        emlop(&format!("%F10000.log s --csv-series {a}")).assert().code(2).stdout("");
+    }
+}
+
+/// A package without any usable merge time is predicted like one that was never merged
+#[test]
+fn predict_negative_times() {
+    let out = format!("cat/bar-1                         ? \n\
+                       Estimate for 1 ebuild, 1 unknown  7 @ {}\n",
+                      ts(7));
+    let pkgs = "[ebuild   R   ] cat/bar-1\n";
+    emlop("%Fnegative.log p -stm --unknown 7 --date unix -oc").write_stdin(pkgs)
+                                                              .assert()
+                                                              .success()
+                                                              .stdout(out);
+}
+
+#[test]
+fn stats_when() {
+    let t = [("-e dev-qt/qtcore --when first", "2018-02-03 23:38:20\n"),
+             ("-e dev-qt/qtcore --when l --date unix", "1519379753\n"),
+             ("qtcore baloo --when last --date ymd", "2018-03-12\n"),
+             ("-e dev-qt/qtcore --when first --from 2018-02-05", "2018-02-06 13:50:56\n")];
+    for (a, o) in t {
+        assert_eq!(emlop_out(&format!("%F10000.log s {a}")), o, "{a}");
+    }
+    emlop("%F10000.log s --when first notfound").assert().code(1).stdout("");
+    emlop("%F10000.log s --when first --count").assert().code(2);
+}
+
+#[test]
+fn stats_prometheus() {
+    assert_eq!(emlop_out("%F10000.log s -op -sps qtcore"),
+               "# HELP emlop_merge_total Number of merges\n\
+                # TYPE emlop_merge_total counter\n\
+                emlop_merge_total{package=\"dev-qt/qtcore\"} 4\n\
+                # HELP emlop_merge_seconds_total Time spent merging\n\
+                # TYPE emlop_merge_seconds_total counter\n\
+                emlop_merge_seconds_total{package=\"dev-qt/qtcore\"} 898\n\
+                # HELP emlop_unmerge_total Number of unmerges\n\
+                # TYPE emlop_unmerge_total counter\n\
+                emlop_unmerge_total{package=\"dev-qt/qtcore\"} 4\n\
+                # HELP emlop_unmerge_seconds_total Time spent unmerging\n\
+                # TYPE emlop_unmerge_seconds_total counter\n\
+                emlop_unmerge_seconds_total{package=\"dev-qt/qtcore\"} 7\n\
+                # HELP emlop_sync_total Number of syncs\n\
+                # TYPE emlop_sync_total counter\n\
+                # HELP emlop_sync_seconds_total Time spent syncing\n\
+                # TYPE emlop_sync_seconds_total counter\n");
+    assert_eq!(emlop_out("%F10000.log s --output=prometheus -ss"),
+               "# HELP emlop_sync_total Number of syncs\n\
+                # TYPE emlop_sync_total counter\n\
+                emlop_sync_total{repo=\"gentoo\"} 150\n\
+                # HELP emlop_sync_seconds_total Time spent syncing\n\
+                # TYPE emlop_sync_seconds_total counter\n\
+                emlop_sync_seconds_total{repo=\"gentoo\"} 4747\n");
+    for a in ["l -op", "p -op", "s -op -gy", "s -op --compare ..2018-02-20 2018-02-20.."] {
+        emlop(&format!("%F10000.log {a}")).assert().code(2);
+    }
+}
+
+#[test]
+fn stats_active_days() {
+    assert_eq!(emlop_out("%F10000.log s -st -gm --active-days -oc -H"),
+               "Month   Merges  Total time  Average time  Unmerges  Total time  Average time  Wall time  Active days  Daily time\n\
+                2018-02    533    43:58:32          4:57       529       24:57             2   39:51:00           22     2:01:04\n\
+                2018-03    298    16:08:34          3:15       303       13:34             2   14:06:31            9     1:49:07\n");
+    assert_eq!(emlop_out("%F10000.log s -st --active-days -oc"),
+               "831  60:07:06  4:20  832  38:31  2  53:57:31  31  1:57:36\n");
+}
+
+#[test]
+fn stats_compare() {
+    let t = [("%F10000.log s -oc -H --compare ..2018-02-20 2018-02-20.. -e gentoo-sources",
+              "Package                    Merges  Predict time  Merges  Predict time  Change\n\
+               sys-kernel/gentoo-sources       5          1:35       5          1:19    -16%\n",
+              0),
+             ("%F10000.log s -oc --compare 2018-03-01.. 2018-02-01..2018-03-01 -e gentoo-sources",
+              "sys-kernel/gentoo-sources  2  1:41  8  1:20  -20%\n",
+              0),
+             // A merge starting on the boundary is only counted in the second range
+             ("%F10000.log s -oc --compare ..1517852325 1517852325.. -e gentoo-sources",
+              "sys-kernel/gentoo-sources  1  1:21  9  1:22  +1%\n",
+              0),
+             // Relative bounds can contain commas
+             ("%F10000.log s -oc --compare ..1year,2months 1year,2months.. -e gentoo-sources",
+              "sys-kernel/gentoo-sources  10  1:21  0  ?\n",
+              0),
+             ("%F10000.log s -oc --compare ..2000-01-01 ..2000-01-01 -e gentoo-sources", "", 1),
+             ("%F10000.log s -oc --compare 2018-01-01 2018-02-01.. -e gentoo-sources", "", 2),
+             ("%F10000.log s -oc --compare ..2018-02-20 -e gentoo-sources", "", 2),
+             ("%F10000.log s -oc --compare .. .. -gy", "", 2),
+             ("%F10000.log s -oc --compare .. .. --top 2", "", 2),
+             ("%F10000.log s -oc --compare .. .. -sp", "", 2),
+             ("%F10000.log s -oc --compare .. .. --no-predict", "", 2)];
+    for (a, o, c) in t {
+        emlop(a).assert().code(c).stdout(o);
+    }
+    // Options that only apply to the usual stats tables are rejected
+    for a in ["--count", "--histogram", "--overview", "--active-days", "--by-version", "--clock"] {
+        emlop(&format!("%F10000.log s --compare .. .. {a}")).assert().code(2).stdout("");
+    }
+}
+
+#[test]
+fn binary_filter() {
+    let t = [("%Fbinary.log l -oc --binary only",
+              "2023-11-14 22:13:42  12 >>> app-misc/foo-1.0\n\
+               2023-11-14 22:14:10  20 >>> www-client/falkon-24.08.3\n\
+               2023-11-14 22:14:30  10 >>> dev-libs/bar-2.1-r1\n\
+               2023-11-14 23:03:40  10 >>> app-misc/qux-1.0\n",
+              0),
+             ("%Fbinary.log l -oc --binary e",
+              "2023-11-14 22:16:30  1:50 >>> dev-lang/baz-3.0\n\
+               2023-11-14 22:47:40    50 >>> app-misc/foo-1.1\n",
+              0),
+             ("%Fbinary.log s -oc --binary exclude -sp",
+              "app-misc/foo  1    50    50  1  1  1\n\
+               dev-lang/baz  1  1:50  1:50  0  0  ?\n",
+              0),
+             ("%Fbinary.log s -oc -st -H",
+              "Merges  Source  Binary  Total time  Average time  Unmerges  Total time  Average time  Wall time  Binary savings\n     \
+               6       2       4        3:32            35         1           1             1       3:32              38\n",
+              0),
+             ("%Fbinary.log s -oc -st --binary only", "4  0  4  52  13  1  1  1  52\n", 0),
+             ("%Fbinary.log s -oc -st --binary exclude", "2  2:40  1:20  1  1  1  2:41\n", 0),
+             ("%Fbinary.log l -oc --binary only baz", "", 1),
+             ("%Fbinary.log l -oc --binary all", "", 2)];
+    for (a, o, c) in t {
+        emlop(a).assert().code(c).stdout(o);
+    }
+}
+
+#[test]
+fn header_repeat() {
+    let t = [("-gm -sp -Hr gentoo-sources",
+              "Month   Package                    Merges  Total time  Predict time  Unmerges  Total time  Predict time\n\
+               2018-02 sys-kernel/gentoo-sources       8       11:42          1:20         8        2:29            13\n\
+               Month   Package                    Merges  Total time  Predict time  Unmerges  Total time  Predict time\n\
+               2018-03 sys-kernel/gentoo-sources       2        3:22          1:41         3          51            15\n"),
+             ("-gm -st --header=repeat gentoo-sources",
+              "Month   Merges  Total time  Average time  Unmerges  Total time  Average time  Wall time\n\
+               2018-02      8       11:42          1:27         8        2:29            18      14:11\n\
+               2018-03      2        3:22          1:41         3          51            17       4:13\n"),
+             ("-sp -Hr gentoo-sources",
+              "Package                    Merges  Total time  Predict time  Unmerges  Total time  Predict time\n\
+               sys-kernel/gentoo-sources      10       15:04          1:21        11        3:20            13\n")];
+    for (a, o) in t {
+        assert_eq!(emlop_out(&format!("%F10000.log s -oc {a}")), o, "{a}");
+    }
+}
+
+#[test]
+fn glob() {
+    let t = [("l -n2 --glob kde-frameworks/k*",
+              "2018-03-12 11:20:35  24 >>> kde-frameworks/krunner-5.44.0\n\
+               2018-03-12 11:21:29  54 >>> kde-frameworks/kfilemetadata-5.44.0\n",
+              0),
+             ("s -sp --glob *-sources",
+              "sys-kernel/gentoo-sources  10  15:04  1:21  11  3:20  13\n",
+              0),
+             ("s -ss --glob gentoo", "gentoo  150  1:19:07  28\n", 0),
+             ("l --glob gentoo-source", "", 1),
+             ("l --glob -e gentoo-sources", "", 2)];
+    for (a, o, c) in t {
+        emlop(&format!("%F10000.log {a} -oc --showskip=n")).assert().code(c).stdout(o);
+    }
+}
+
+#[test]
+fn invert() {
+    let t = [("l -sms --invert kde gentoo sys -n3",
+              "2018-03-12 10:37:30  14 >>> x11-apps/xmessage-1.0.5\n\
+               2018-03-12 11:23:12  34 >>> dev-java/icedtea-bin-3.7.0\n\
+               2018-03-12 11:23:30  18 >>> net-misc/kafka-bin-0.11.0.2-r1\n",
+              0),
+             // Explicit repo terms are not inverted
+             ("l -sms --invert kde --repo gentoo -n2",
+              "2018-03-12 15:24:11  45 Sync gentoo\n\
+               2018-03-12 21:43:26  12 Sync gentoo\n",
+              0),
+             ("s -ss --invert gentoo", "", 1),
+             ("s -sp --invert --glob [a-w]*/* -N1", "x11-apps/appres  1  14  14  1  2  2\n", 0),
+             // No effect without search terms
+             ("l --invert -n1", "2018-03-12 11:24:43  1:13 >>> kde-frameworks/baloo-5.44.0\n", 0)];
+    for (a, o, c) in t {
+        emlop(&format!("%F10000.log {a} -oc --showskip=n")).assert().code(c).stdout(o);
+    }
+}
+
+#[test]
+fn coalesce_updates() {
+    let t =
+        [("-smu -n4",
+          "2018-03-12 11:23:30    18 >>> net-misc/kafka-bin-0.11.0.2-r1\n\
+               2018-03-12 11:24:39     2 <<< kde-frameworks/baloo-5.43.0\n\
+               2018-03-12 11:24:43  1:13 >>> kde-frameworks/baloo-5.44.0\n\
+               2018-03-12 15:22:21    13 <<< sys-kernel/gentoo-sources-4.15.7-r1\n"),
+         ("-smu -n3 --coalesce-updates",
+          "2018-03-12 11:23:30    18 >>> net-misc/kafka-bin-0.11.0.2-r1 (replaced 0.11.0.2)\n\
+               2018-03-12 11:24:43  1:13 >>> kde-frameworks/baloo-5.44.0 (replaced 5.43.0)\n\
+               2018-03-12 15:22:21    13 <<< sys-kernel/gentoo-sources-4.15.7-r1\n"),
+         ("-sm -n1 --coalesce-updates",
+          "2018-03-12 11:24:43  1:13 >>> kde-frameworks/baloo-5.44.0\n")];
+    for (a, o) in t {
+        assert_eq!(emlop_out(&format!("%F10000.log l -oc --showskip=n {a}")), o, "{a}");
+    }
+    // A failed merge doesn't swallow a later unmerge, even when runs aren't shown
+    let t = [("-smu",
+              "2023-11-14 22:30:07     2 <<< cat/foo-1\n\
+               2023-11-14 22:48:30  1:40 >>> cat/foo-3 (replaced 2)\n"),
+             ("-sa",
+              "2023-11-14 22:13:20       Emerge --oneshot cat/foo\n\
+               2023-11-14 22:30:00       Emerge --unmerge cat/foo\n\
+               2023-11-14 22:30:07     2 <<< cat/foo-1\n\
+               2023-11-14 22:46:40       Emerge --oneshot cat/foo\n\
+               2023-11-14 22:48:30  1:40 >>> cat/foo-3 (replaced 2)\n")];
+    for (a, o) in t {
+        assert_eq!(emlop_out(&format!("%Ffailedmerge.log l -oc --coalesce-updates {a}")), o, "{a}");
+    }
+    // The unmerge is still shown when the merge row is filtered out
+    assert_eq!(emlop_out("%Fbinary.log l -oc -smu --coalesce-updates --binary=exclude"),
+               "2023-11-14 22:13:41     1 <<< app-misc/foo-0.9\n\
+                2023-11-14 22:16:30  1:50 >>> dev-lang/baz-3.0\n\
+                2023-11-14 22:47:40    50 >>> app-misc/foo-1.1\n");
+}
+
+#[test]
+fn stats_first_last() {
+    let t = [("-n2 --showskip=n",
+              "x11-terms/cool-retro-term  1  45  45  0  0  ?\n\
+               x11-themes/xcursor-themes  1  15  15  1  2  2\n"),
+             ("-N1 -n2 --showskip=n", "x11-terms/cool-retro-term  1  45  45  0  0  ?\n"),
+             ("-N2 --top=5",
+              "www-client/chromium       3  21:41:24  7:42:07    3     12  3\n\
+               app-office/libreoffice    3   7:15:37  2:14:06    3     12  4\n\
+               (499 others)            825  31:10:05           826  38:07   \n"),
+             ("-N2 -gy --count", "2\n")];
+    for (a, o) in t {
+        assert_eq!(emlop_out(&format!("%F10000.log s -sp -oc {a}")), o, "{a}");
+    }
+}
+
+#[test]
+fn stats_count() {
+    let t = [("%F10000.log s --count", "501\n", 0),
+             ("%F10000.log s --count -gm", "751\n", 0),
+             ("%F10000.log s --count --top 5", "5\n", 0),
+             ("%F10000.log s --count client", "7\n", 0),
+             ("%F10000.log s --count nomatch", "0\n", 1)];
+    for (a, o, c) in t {
+        emlop(a).assert().code(c).stdout(o);
+    }
+}
+
+/// Adjacent ranges split with `--to-exclusive` don't overlap
+#[test]
+fn to_exclusive() {
+    let man = "2018-02-02 22:09:08  ? >>> sys-apps/man-pages-4.14\n";
+    assert_eq!(emlop_out("%F10000.log l -oc --to 1517609348"), man);
+    emlop("%F10000.log l -oc --to 1517609348 --to-exclusive").assert().code(1).stdout("");
+    assert_eq!(emlop_out("%F10000.log l -oc --from 1517609348 -N1 --showskip=n"), man);
+    emlop("%F10000.log l --to-exclusive").assert().code(2);
+}
+
+#[test]
+fn accuracy() {
+    let t = [("%F10000.log a -sm -oc -n2 gentoo-sources",
+              "(skip first 8)\n\
+               2018-03-01 16:25:40  >>> sys-kernel/gentoo-sources-4.15.7-r1  1:22  1:20   2.4%\n\
+               2018-03-12 10:33:08  >>> sys-kernel/gentoo-sources-4.15.9     2:00  1:21  32.5%\n",
+              0),
+             ("%F10000.log a -ss -oc -n3",
+              "(skip first 147)\n\
+               2018-03-12 14:14:53  Sync gentoo  26  27    3.8%\n\
+               2018-03-12 15:24:11  Sync gentoo  45  28   37.8%\n\
+               2018-03-12 21:43:26  Sync gentoo  12  34  183.3%\n",
+              0),
+             ("%F10000.log a -st -oc gentoo", "sys-kernel/gentoo-sources  16.2%\n", 0),
+             ("%F10000.log a -sst -oc -n1 gentoo",
+              "(skip first 149)\n\
+               2018-03-12 21:43:26  Sync gentoo  12  34  183.3%\n\
+               Sync gentoo                176.7%\n\
+               sys-kernel/gentoo-sources   16.2%\n",
+              0),
+             ("%F10000.log a -sm notfound", "", 1)];
+    for (a, o, c) in t {
+        emlop(a).assert().code(c).stdout(o);
+    }
+}
+
+/// Predictions using fewer values than `--limit` are reported at `-v`
+#[test]
+fn limit_window() {
+    let w = "[WARN  emlop::commands]";
+    let t =
+        [("s -e dev-qt/qtcore --limit 5",
+          format!("{w} 1 of 1 predictions used fewer than --limit=5 values, 4.0 on average\n")),
+         ("a -e dev-qt/qtcore --limit 2",
+          format!("{w} 1 of 3 predictions used fewer than --limit=2 values, 1.7 on average\n")),
+         ("s -e dev-qt/qtcore --limit 4", String::new()),
+         ("s -e dev-qt/qtcore --limit 5 --no-predict", String::new())];
+    for (a, e) in t {
+        emlop(&format!("%F10000.log {a} -v")).assert().success().stderr(e);
+    }
+    emlop("%F10000.log s -e dev-qt/qtcore --limit 5").assert().success().stderr("");
+}
+
+/// Remote logs are read using the `ssh` command, replaced here by a local script
+#[cfg(feature = "ssh")]
+#[test]
+fn ssh_logfile() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/tests/ssh:{}", std::env::var("PATH").unwrap());
+    let o = "2018-03-12 11:23:30    18 >>> net-misc/kafka-bin-0.11.0.2-r1\n\
+             2018-03-12 11:24:43  1:13 >>> kde-frameworks/baloo-5.44.0\n";
+    for f in ["emerge.10000.log", "emerge.log.gz"] {
+        emlop(&format!("-F ssh://localhost{dir}/tests/{f} l -n2 -oc --showskip=n")).env("PATH",
+                                                                                        &path)
+                                                                                   .assert()
+                                                                                   .success()
+                                                                                   .stdout(o);
+    }
+    let out = emlop("-F ssh://localhost/notfound l").env("PATH", &path).output().unwrap();
+    assert_eq!(out.status.code(), Some(2));
+    assert_eq!(out.stdout, b"");
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(err.contains("Cannot open \"ssh://localhost/notfound\": ssh failed"), "{err}");
+    emlop("-F ssh:///notfound l").assert().code(2);
+}
+
+/// Command bounds that are swapped or out of range are errors, not silently ignored
+#[test]
+fn command_bounds() {
+    let t = [("--from 1C --to 5C", "did you swap --to and --from"),
+             ("--from 5c --to 1c", "did you swap --to and --from"),
+             ("--from -2c --to -2c", "did you swap --to and --from"),
+             ("--from 1C --to 2018-02-03", "did you swap --to and --from"),
+             ("--to 9999c", "No such emerge command for --to"),
+             ("--from 9999c --to 1C", "No such emerge command for --from")];
+    for (a, e) in t {
+        let out = emlop(&format!("%F10000.log l {a}")).output().unwrap();
+        assert_eq!(out.status.code(), Some(2), "{a}");
+        assert!(String::from_utf8_lossy(&out.stderr).contains(e), "{a}");
+    }
+    assert_eq!(emlop_out("%F10000.log l --from 5C --to -1c -sr -oc"),
+               "2018-03-12 14:16:17 Emerge --newuse --update --ask --deep --quiet-build=y --verbose world\n\
+                2018-03-12 15:11:16 Emerge --ask --quiet-build=y --depclean\n\
+                2018-03-12 15:23:26 Emerge --quiet-build=y --sync\n\
+                2018-03-12 21:43:14 Emerge --quiet-build=y --sync\n\
+                2018-03-12 21:44:58 Emerge --update --quiet-build=y --ask --newuse --verbose --deep world\n");
+}
+
+/// Commands count from the start, or from the end with `-` or `C`, for both bounds
+#[test]
+fn command_directions() {
+    let first = "2018-02-03 22:59:04 Emerge --quiet-build=y --sync\n\
+                 2018-02-03 23:01:09 Emerge --update --verbose --quiet-build=y --backtrack=100 --deep --newuse --ask world\n";
+    let last = "2018-03-12 21:43:14 Emerge --quiet-build=y --sync\n\
+                2018-03-12 21:44:58 Emerge --update --quiet-build=y --ask --newuse --verbose --deep world\n";
+    for (a, o) in [("--from 1c --to 2c", first),
+                   ("--from c --to 2commands", first),
+                   ("--from 2C --to 1C", last),
+                   ("--from -2c --to -1c", last),
+                   ("--from -2c --to C", last),
+                   ("--from 2C --to -c", last)]
+    {
+        assert_eq!(emlop_out(&format!("%F10000.log l -sr -oc {a}")), o, "{a}");
+    }
+}
+
+#[test]
+fn since_sync() {
+    assert_eq!(emlop_out("%F10000.log l --since-sync -sa -oc"),
+               "2018-03-12 21:43:26  ? Sync gentoo\n\
+                2018-03-12 21:44:58    Emerge --update --quiet-build=y --ask --newuse --verbose --deep world\n");
+    assert_eq!(emlop_out("%Fsync.log l --since-sync -ss -oc"),
+               "2020-06-18 16:21:56  ? Sync moltonel\n");
+}
+
+#[test]
+fn between_syncs() {
+    assert_eq!(emlop_out("%F10000.log l --between-syncs -sa -oc"),
+               "2018-03-12 15:24:11   ? Sync gentoo\n\
+                2018-03-12 21:43:14     Emerge --quiet-build=y --sync\n\
+                2018-03-12 21:43:26  12 Sync gentoo\n");
+    // Repos synced by the same command count as one sync
+    assert_eq!(emlop_out("%Fsync.log l --between-syncs -ss -oc"),
+               "2020-06-17 20:24:03  ? Sync moltonel\n\
+                2020-06-18 16:21:54  6 Sync gentoo\n\
+                2020-06-18 16:21:55  1 Sync steam-overlay\n\
+                2020-06-18 16:21:56  1 Sync moltonel\n");
+    emlop("%Fbinary.log l --between-syncs").assert().code(2);
+    emlop("%F10000.log l --between-syncs --from 1d").assert().code(2);
+}
+
+#[test]
+fn repo_filter() {
+    // Without --repo, search terms apply to both packages and repos
+    assert_eq!(emlop_out("%F10000.log l -sms firefox --to 2018-02-05 -oc"),
+               "2018-02-04 05:42:48  47:29 >>> www-client/firefox-58.0.1\n");
+    assert_eq!(emlop_out("%F10000.log l -sms firefox --repo gentoo --to 2018-02-05 -oc"),
+               "2018-02-03 23:00:13   1:08 Sync gentoo\n\
+                2018-02-04 05:42:48  47:29 >>> www-client/firefox-58.0.1\n\
+                2018-02-04 20:00:50     56 Sync gentoo\n\
+                2018-02-04 23:04:14     36 Sync gentoo\n");
+    assert_eq!(emlop_out("%Fsync.log l -ss --repo mol --repo guru --from 2020-06-17 -oc"),
+               "2020-06-17 20:24:03  1 Sync moltonel\n\
+                2020-06-18 16:21:56  1 Sync moltonel\n");
+}
+
+#[test]
+fn pkg_file() {
+    // File entries match exactly, even when other search terms are regexes
+    for a in ["", "-e"] {
+        assert_eq!(emlop_out(&format!("%F10000.log s -sp -oc --pkg-file tests/pkgs.txt {a}")),
+                   "kde-frameworks/kio  3  13:40   3:37  3  7  2\n\
+                    sys-devel/llvm      1  32:10  32:10  1  3  3\n");
+    }
+    assert_eq!(emlop_out("%F10000.log l -oc --pkg-file tests/pkgs.txt thunder"),
+               "2018-02-04 04:55:19  35:46 >>> mail-client/thunderbird-52.6.0\n\
+                2018-02-09 11:04:59  47:58 >>> mail-client/thunderbird-52.6.0-r1\n\
+                2018-02-12 10:04:56   3:37 >>> kde-frameworks/kio-5.43.0\n\
+                2018-02-19 09:46:32   6:54 >>> kde-frameworks/kio-5.43.0-r1\n\
+                2018-02-26 10:09:49  32:10 >>> sys-devel/llvm-5.0.1\n\
+                2018-03-12 11:00:36   3:09 >>> kde-frameworks/kio-5.44.0\n");
+    emlop("%F10000.log l --pkg-file tests/notfound").assert().code(2);
+    emlop("%F10000.log l --pkg-file /dev/null").assert().code(2);
+}
+
+#[test]
+fn log_tail() {
+    // Same output as `--last` when there are no search terms
+    for a in ["-sm -n3", "-sa -n4", "-ss -n2", "-sr -n5", "-smu -n50", "-sa -n1000"] {
+        let tail = a.replace("-n", "--tail ");
+        assert_eq!(emlop_out(&format!("%F10000.log l --showskip=n {a}")),
+                   emlop_out(&format!("%F10000.log l --showskip=n {tail}")),
+                   "{a}");
+    }
+    // Search terms apply to the tail
+    assert_eq!(emlop_out("%F10000.log l -oc --tail 5 baloo"),
+               "2018-03-12 11:24:43  1:13 >>> kde-frameworks/baloo-5.44.0\n");
+    // Compressed logs are read in full
+    assert_eq!(emlop_out("-F tests/emerge.log.gz l --showskip=n -n2"),
+               emlop_out("-F tests/emerge.log.gz l --showskip=n --tail 2"));
+    // Logs with an upper bound are read in full too
+    for a in ["--to 2018-02-10", "--to 10c", "--to 2018-02-10 --to-exclusive"] {
+        assert_eq!(emlop_out(&format!("%F10000.log l --showskip=n {a} -n2")),
+                   emlop_out(&format!("%F10000.log l --showskip=n {a} --tail 2")),
+                   "{a}");
+    }
+    emlop("%F10000.log l --tail 0").assert().code(2);
+}
+
+#[test]
+fn log_line_buffered() {
+    // Same output as the buffered version, as long as no column is fully empty
+    for a in ["-sa -ot", "-sa -ot -n10", "-sm -ot -H"] {
+        assert_eq!(emlop_out(&format!("%F10000.log l {a}")),
+                   emlop_out(&format!("%F10000.log l {a} --line-buffered")),
+                   "{a}");
+    }
+    assert_eq!(emlop_out("%F10000.log l -sr -ot -N1 --showskip=n --line-buffered"),
+               "2018-02-03 22:59:04\t\tEmerge --quiet-build=y --sync\n");
+    assert_eq!(emlop_out("%F10000.log l -sa -oc -N3 --showskip=n --line-buffered"),
+               "2018-02-02 22:09:08  ? >>> sys-apps/man-pages-4.14\n\
+                2018-02-03 22:59:04    Emerge --quiet-build=y --sync\n\
+                2018-02-03 23:00:13  1:08 Sync gentoo\n");
+}
+
+#[test]
+fn log_json_lines() {
+    let t = [("-sa -N4 --date unix --showskip -H",
+              "{\"date\":1517609348,\"duration\":null,\"package\":\"sys-apps/man-pages\",\"type\":\"merge\",\"version\":\"4.14\"}\n\
+               {\"args\":\"--quiet-build=y --sync\",\"date\":1517698744,\"type\":\"run\"}\n\
+               {\"date\":1517698813,\"duration\":68,\"repo\":\"gentoo\",\"type\":\"sync\"}\n\
+               {\"args\":\"--update --verbose --quiet-build=y --backtrack=100 --deep --newuse --ask world\",\"date\":1517698869,\"type\":\"run\"}\n"),
+             ("-sm -N2 -n1 --target-root --showskip",
+              "{\"build_duration\":4,\"date\":\"2018-02-03 23:11:47\",\"duration\":12,\"package\":\"sys-process/parallel\",\"root\":\"/\",\"type\":\"merge\",\"version\":\"20171222\"}\n"),
+             ("-su -n1 --date ymd",
+              "{\"date\":\"2018-03-12\",\"duration\":13,\"package\":\"sys-kernel/gentoo-sources\",\"type\":\"unmerge\",\"version\":\"4.15.7-r1\"}\n")];
+    for (a, o) in t {
+        assert_eq!(emlop_out(&format!("%F10000.log l --json-lines {a}")), o, "{a}");
+    }
+    // Colors don't leak into the json
+    assert_eq!(emlop_out("%F10000.log l --json-lines -su -n1 --date ymd --color=y"),
+               "{\"date\":\"2018-03-12\",\"duration\":13,\"package\":\"sys-kernel/gentoo-sources\",\"type\":\"unmerge\",\"version\":\"4.15.7-r1\"}\n");
+}
+
+#[test]
+fn log_sort() {
+    let t = [("qtcore --sort dur -N3",
+              "2018-02-06 13:50:56  3:47 >>> dev-qt/qtcore-5.9.4-r1\n\
+               2018-02-03 23:38:20  3:43 >>> dev-qt/qtcore-5.9.4\n\
+               2018-02-23 09:52:03  3:38 >>> dev-qt/qtcore-4.8.7-r4\n"),
+             // Rows are selected before sorting, skip rows aren't shown
+             ("qtcore --sort d -n2 --showskip",
+              "2018-02-23 09:55:53  3:50 >>> dev-qt/qtcore-5.9.4-r2\n\
+               2018-02-23 09:52:03  3:38 >>> dev-qt/qtcore-4.8.7-r4\n"),
+             // Runs have no duration and go last
+             ("-sa -N4 --sort duration",
+              "2018-02-03 23:00:13  1:08 Sync gentoo\n\
+               2018-02-02 22:09:08     ? >>> sys-apps/man-pages-4.14\n\
+               2018-02-03 22:59:04       Emerge --quiet-build=y --sync\n\
+               2018-02-03 23:01:09       Emerge --update --verbose --quiet-build=y --backtrack=100 --deep --newuse --ask world\n"),
+             ("qtcore --sort date -N2 --showskip=n",
+              "2018-02-03 23:38:20  3:43 >>> dev-qt/qtcore-5.9.4\n\
+               2018-02-06 13:50:56  3:47 >>> dev-qt/qtcore-5.9.4-r1\n"),
+             ("qtcore --sort dur -N2 --json-lines --date unix",
+              "{\"build_duration\":217,\"date\":1517925056,\"duration\":227,\"package\":\"dev-qt/qtcore\",\"type\":\"merge\",\"version\":\"5.9.4-r1\"}\n\
+               {\"build_duration\":213,\"date\":1517701100,\"duration\":223,\"package\":\"dev-qt/qtcore\",\"type\":\"merge\",\"version\":\"5.9.4\"}\n")];
+    for (a, o) in t {
+        assert_eq!(emlop_out(&format!("%F10000.log l -oc {a}")), o, "{a}");
+    }
+    emlop("%F10000.log l --sort size").assert().code(2);
+}
+
+#[test]
+fn log_run_regex() {
+    let a = "%F10000.log l -sr --to 2018-02-04T00:05 -oc";
+    assert_eq!(emlop_out(a),
+               "2018-02-03 22:59:04 Emerge --quiet-build=y --sync\n\
+                2018-02-03 23:01:09 Emerge --update --verbose --quiet-build=y --backtrack=100 --deep --newuse --ask world\n\
+                2018-02-03 23:09:31 Emerge --quiet-build=y --deep --update --newuse --backtrack=100 world\n");
+    assert_eq!(emlop_out(&format!("{a} --run-regex ^Started.emerge.on:(?<args>.*)")),
+               "2018-02-03 22:59:04 Emerge Feb 03, 2018 23:59:04\n\
+                2018-02-03 22:59:04 Emerge --quiet-build=y --sync\n\
+                2018-02-03 23:01:09 Emerge Feb 04, 2018 00:01:08\n\
+                2018-02-03 23:01:09 Emerge --update --verbose --quiet-build=y --backtrack=100 --deep --newuse --ask world\n\
+                2018-02-03 23:09:31 Emerge Feb 04, 2018 00:09:31\n\
+                2018-02-03 23:09:31 Emerge --quiet-build=y --deep --update --newuse --backtrack=100 world\n");
+    // Without an `args` group, the whole line is used
+    assert!(emlop_out(&format!("{a} --run-regex ^Started")).contains(" Emerge Started emerge on: "));
+}
+
+#[test]
+fn complete_null() {
+    assert_eq!(emlop_out("%F10000.log complete kxmlrpc"), "kde-frameworks/kxmlrpcclient\n");
+    assert_eq!(emlop_out("%F10000.log complete -0 client"),
+               "mail-client/thunderbird\0www-client/firefox\0kde-frameworks/kxmlrpcclient\0\
+                www-client/chromium\0www-client/links\0www-client/falkon\0x11-apps/xlsclients\0");
+}
+
+/// Run the generated bash completion with a stub `emlop complete`, and check if it was called
+#[cfg(feature = "clap_complete")]
+#[test]
+fn complete_bash() {
+    let script = emlop_out("complete --shell bash");
+    let t = [("emlop l ''", true),
+             ("emlop -F /tmp/emerge.log s ''", true),
+             ("emlop --logfile=/tmp/emerge.log a ''", true),
+             ("emlop -F log p ''", false),
+             ("emlop -H s p ''", false),
+             ("emlop p ''", false)];
+    for (words, called) in t {
+        let cmd = format!("eval \"$SCRIPT\"
+                           emlop() {{ echo STUB; }}
+                           COMP_WORDS=({words})
+                           COMP_CWORD=$((${{#COMP_WORDS[@]}} - 1))
+                           _emlop_dynamic emlop '' x
+                           echo \"${{COMPREPLY[@]}}\"");
+        let out = std::process::Command::new("bash").args(["-c", &cmd])
+                                                    .env("SCRIPT", &script)
+                                                    .output()
+                                                    .unwrap();
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        assert_eq!(stdout.contains("STUB"), called, "{words}: {stdout}");
+    }
+}
+
+#[test]
+fn theme_preset() {
+    let t = [("--color=y",
+              "2018-03-12 11:24:43\t\x1B[1;35m1:13\t\x1B[1;32mkde-frameworks/baloo-5.44.0\x1B[33m\x1B[m\n"),
+             ("--color=y --theme-preset=light",
+              "2018-03-12 11:24:43\t\x1B[0;35m1:13\t\x1B[0;32mkde-frameworks/baloo-5.44.0\x1B[34m\x1B[m\n"),
+             ("--color=y --theme-preset=none", "2018-03-12 11:24:43\t1:13\t>>> kde-frameworks/baloo-5.44.0\n"),
+             ("--color=n --theme-preset=light", "2018-03-12 11:24:43\t1:13\t>>> kde-frameworks/baloo-5.44.0\n")];
+    for (a, o) in t {
+        assert_eq!(emlop_out(&format!("%F10000.log l -n1 --showskip=n {a}")), o, "{a}");
+    }
+}
+
+#[test]
+fn quiet() {
+    // Warnings and errors are silenced, even with `-v`, but the exit code remains
+    let t = [("%Fnegtime.log l -v", 0, true),
+             ("%Fnegtime.log l -v -q", 0, false),
+             ("l --logfile notfound", 2, true),
+             ("l --logfile notfound -q", 2, false)];
+    for (a, code, stderr) in t {
+        let out = emlop(a).output().unwrap();
+        assert_eq!(out.status.code(), Some(code), "{a}");
+        assert_eq!(!out.stderr.is_empty(), stderr, "{a}");
+    }
+}
+
+#[test]
+fn staleness_check() {
+    let t = [("%F10000.log l -n1", false),
+             ("%F10000.log l -n1 --staleness-check", true),
+             ("%F10000.log p --staleness-check=y", true),
+             ("%F10000.log l -n1 --staleness-check -t 2018-03-01", false),
+             ("%F10000.log l -n1 --staleness-check -f 2019-01-01", false)];
+    for (a, stale) in t {
+        let out = emlop(a).output().unwrap();
+        let stderr = String::from_utf8(out.stderr).unwrap();
+        assert_eq!(stderr.contains("days old"), stale, "{a} {stderr}");
+    }
+}
+
+#[test]
+fn dump_config() {
+    let out = emlop_out("%F10000.log s --dump-config --date d -gm --limit 5");
+    for s in ["Stats(",
+              "logfile: \"tests/emerge.10000.log\"",
+              "date_fmt: DateStyle(\n            \"1970-01-01\",",
+              "group: Month",
+              "lim: 5,"]
+    {
+        assert!(out.contains(s), "{s:?} not in {out}");
+    }
+    emlop("%F10000.log s --dump-config --limit 0").assert().code(2);
+    // Dumped like normal output
+    let f = format!("{}/dump_config.txt", env!("CARGO_TARGET_TMPDIR"));
+    assert_eq!(emlop_out(&format!("%F10000.log l --dump-config --out {f}")), "");
+    assert!(std::fs::read_to_string(&f).unwrap().starts_with("Log("));
+    let out =
+        emlop("%F10000.log l --dump-config --pager=always").env("PAGER", "cat").output().unwrap();
+    assert!(out.status.success());
+    assert!(String::from_utf8_lossy(&out.stdout).starts_with("Log("));
+}
+
+/// Progress is only displayed on a terminal
+#[test]
+fn progress() {
+    assert_eq!(emlop_out("%F10000.log l -n1 --showskip=n --progress -oc"),
+               "2018-03-12 11:24:43  1:13 >>> kde-frameworks/baloo-5.44.0\n");
+    emlop("%F10000.log l --progress maybe").assert().code(2);
+}
+
+/// Like `--output=tab`, but colors can't be enabled
+#[test]
+fn output_tsv() {
+    assert_eq!(emlop_out("%F10000.log l -n2 --showskip=n -H -otsv --color=y"),
+               "Date\tDuration\tPackage/Repo\n\
+                2018-03-12 11:23:30\t18\t>>> net-misc/kafka-bin-0.11.0.2-r1\n\
+                2018-03-12 11:24:43\t1:13\t>>> kde-frameworks/baloo-5.44.0\n");
+    for a in ["s -sa", "l -sa -N20", "p -sa --pretend-file tests/emerge-p.basic.out"] {
+        assert_eq!(emlop_out(&format!("%F10000.log {a} -H -otsv --color=y")),
+                   emlop_out(&format!("%F10000.log {a} -H -ot --color=n")),
+                   "{a}");
+    }
+}
+
+#[test]
+fn outfile() {
+    let f = format!("{}/outfile.txt", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&f, "previous content\n").unwrap();
+    assert_eq!(emlop_out(&format!("%F10000.log l -n2 --showskip=n --out {f}")), "");
+    assert_eq!(std::fs::read_to_string(&f).unwrap(),
+               "2018-03-12 11:23:30\t18\t>>> net-misc/kafka-bin-0.11.0.2-r1\n\
+                2018-03-12 11:24:43\t1:13\t>>> kde-frameworks/baloo-5.44.0\n");
+    emlop("%F10000.log l --out /nonexistent/outfile.txt").assert().code(2).stdout("");
+}
+
+/// Closing stdout early shouldn't cause a panic
+#[test]
+fn broken_pipe() {
+    use std::process::{Command, Stdio};
+    for a in ["s -sa", "complete a"] {
+        let mut child =
+            Command::new(env!("CARGO_BIN_EXE_emlop")).env("TZ", "UTC")
+                                                     .env("EMLOP_CONFIG", "")
+                                                     .args(["-F", "tests/emerge.10000.log"])
+                                                     .args(a.split_whitespace())
+                                                     .stdout(Stdio::piped())
+                                                     .stderr(Stdio::piped())
+                                                     .spawn()
+                                                     .unwrap();
+        drop(child.stdout.take());
+        let out = child.wait_with_output().unwrap();
+        assert!(out.stderr.is_empty(), "{a}: {}", String::from_utf8_lossy(&out.stderr));
+    }
+}
+
+/// Output goes through `$PAGER` when enabled, but not by default or when not on a tty
+#[test]
+fn pager() {
+    let t = [("--pager", "P:2018-03-12 11:24:43\t1:13\t>>> kde-frameworks/baloo-5.44.0\n"),
+             ("--pager=never", "2018-03-12 11:24:43\t1:13\t>>> kde-frameworks/baloo-5.44.0\n"),
+             ("--pager=auto", "2018-03-12 11:24:43\t1:13\t>>> kde-frameworks/baloo-5.44.0\n"),
+             ("", "2018-03-12 11:24:43\t1:13\t>>> kde-frameworks/baloo-5.44.0\n")];
+    for (a, o) in t {
+        let out = emlop(&format!("%F10000.log l -n1 --showskip=n {a}")).env("PAGER", "sed s/^/P:/")
+                                                                       .output()
+                                                                       .unwrap();
+        assert!(out.status.success(), "{a}");
+        assert_eq!(String::from_utf8(out.stdout).unwrap(), o, "{a}");
+    }
+    // `always`/`never` are pager values, not color values
+    emlop("%F10000.log l -n1 --pager=yes").env("PAGER", "cat").assert().success();
+    emlop("%F10000.log l -n1 --color=always").assert().code(2);
+    emlop("%F10000.log l -n1 --pager=bad").assert().code(2);
+}
+
 #[test]
 fn exit_status() {
     // 0: no problem
@@ -530,6 +1704,8 @@ fn exit_status() {
              ("-h", 0),
              ("-V", 0),
              ("l -h", 0),
+             ("--help-all", 0),
+             ("--help-all -H", 2),
              ("", 2),
              ("s --foo", 2),
              ("badcmd", 2),
@@ -541,8 +1717,11 @@ fn exit_status() {
              ("l bad_regex_[a-z", 2),
              ("s bad_regex_[a-z", 2),
              ("p bad_regex_[a-z", 2),
+             ("l --run-regex bad_regex_[a-z", 2),
+             ("l --since-sync --from 1d", 2),
              // Normal behaviour
              ("%F10000.log p", 1),
+             ("%F10000.log p --status-code", 1),
              ("%F10000.log l", 0),
              ("%F10000.log l -sm", 0),
              ("%F10000.log l -e icu", 0),
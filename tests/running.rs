@@ -0,0 +1,105 @@
+//! Tests that need running emerge processes
+//!
+//! They live in their own test binary, so that the fake processes don't leak into the
+//! `predict` output of the other tests, which run concurrently.
+
+use assert_cmd::Command;
+use std::{path::PathBuf,
+          process::{Child, Command as StdCommand},
+          thread,
+          time::Duration};
+
+/// A process with a chosen name, killed on drop
+struct FakeProc(Child);
+impl FakeProc {
+    fn start(name: &str, target: &str, args: &[&str]) -> Self {
+        let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("fakebin");
+        std::fs::create_dir_all(&dir).unwrap();
+        let bin = dir.join(name);
+        if !bin.exists() {
+            std::os::unix::fs::symlink(target, &bin).unwrap();
+        }
+        let child = StdCommand::new(bin).args(args).spawn().expect("could not start fake process");
+        // Give it time to exec, so that /proc shows the new name
+        thread::sleep(Duration::from_millis(100));
+        Self(child)
+    }
+}
+impl Drop for FakeProc {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// `predict --status-code` exits with 3 through the normal exit path, so the pager gets all the
+/// output and is waited for
+#[test]
+fn status_code_running() {
+    let _emerge = FakeProc::start("emerge", "/bin/sleep", &["60"]);
+    let mut e = Command::cargo_bin("emlop").unwrap();
+    e.env("TZ", "UTC").env("EMLOP_CONFIG", "").env("PAGER", "cat");
+    e.args(["-F", "tests/emerge.10000.log", "p", "--status-code", "--pager=always", "-oc"]);
+    let out = e.write_stdin("").output().unwrap();
+    assert_eq!(out.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&out.stdout).contains("No pending merge found, emerge is running"));
+    let mut e = Command::cargo_bin("emlop").unwrap();
+    e.env("TZ", "UTC").env("EMLOP_CONFIG", "");
+    e.args(["-F", "tests/emerge.10000.log", "p", "-oc"]);
+    e.write_stdin("").assert().code(1);
+}
+
+/// A merge that is currently running only gets added to the resume list, not to an explicit list
+#[test]
+fn running_not_pending() {
+    let _sandbox =
+        FakeProc::start("python3",
+                        "/bin/sh",
+                        &["-c", "sleep 60; :", "sandbox [dev-lang/rust-1.65.0] sandbox"]);
+    let mut e = Command::cargo_bin("emlop").unwrap();
+    e.env("TZ", "UTC").env("EMLOP_CONFIG", "");
+    e.args(["-F", "tests/emerge.10000.log", "p", "-sm", "-oc"]);
+    let out = e.pipe_stdin("tests/emerge-p.basic.out").unwrap().output().unwrap();
+    assert_eq!(out.status.code(), Some(0));
+    let out = String::from_utf8_lossy(&out.stdout);
+    assert!(out.contains("sys-devel/gcc-6.4.0-r1"), "{out}");
+    assert!(!out.contains("dev-lang/rust"), "{out}");
+}
+
+/// `stats --live` uses the `--group-regex` key to name and predict the ongoing merge
+#[test]
+fn live_group_regex() {
+    let _sandbox = FakeProc::start("python3",
+                                   "/bin/sh",
+                                   &["-c",
+                                     "sleep 60; :",
+                                     "sandbox [www-client/chromium-65.0.3325.146] sandbox"]);
+    let mut e = Command::cargo_bin("emlop").unwrap();
+    e.env("TZ", "UTC").env("EMLOP_CONFIG", "");
+    e.args(["-F", "tests/emerge.10000.log", "s", "-sp", "-oc", "--live", "-e", "chromium"]);
+    e.args(["--group-regex", "^([a-z]+)-"]);
+    let out = e.output().unwrap();
+    assert_eq!(out.status.code(), Some(0));
+    let out = String::from_utf8_lossy(&out.stdout);
+    let live = out.lines().find(|l| l.contains("(in progress)")).unwrap_or_else(|| panic!("{out}"));
+    assert!(live.starts_with("www (in progress) ") && live.trim_end().ends_with(" 7:42:07"),
+            "{out}");
+}
+
+/// Without `/proc`, predict tells why only pretended and resumed merges are predicted
+///
+/// Ignored by default: needs the privileges to unmount `/proc` in a private mount namespace.
+#[ignore]
+#[test]
+fn predict_no_procfs() {
+    let mut e = Command::new("unshare");
+    e.env("TZ", "UTC").env("EMLOP_CONFIG", "");
+    e.args(["--mount", "--fork", "sh", "-c", "umount -l /proc && exec \"$0\" \"$@\""]);
+    e.arg(env!("CARGO_BIN_EXE_emlop"));
+    e.args(["-F", "tests/emerge.10000.log", "p", "-oc"]);
+    let out = e.write_stdin("").output().unwrap();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("/proc is unavailable, only predicting pretended or resumed merges"),
+            "{stderr}");
+    assert_eq!(stderr.lines().count(), 1, "{stderr}");
+}